@@ -1,7 +1,7 @@
 use std::{marker::PhantomData, rc::Rc};
 
 use crate::{
-    dtype::Dtype,
+    dtype::NumDtype,
     ops::{vec::el_unary, Op},
     shape::Shape,
     tensor::{Tensor, TensorBox, TensorTrait},
@@ -10,18 +10,18 @@ use crate::{
 
 use num::NumCast;
 
-pub trait Converts<T: Dtype, S: Shape> {
+pub trait Converts<T: NumDtype, S: Shape> {
     fn convert(self) -> Tensor<T, S>;
 }
 
 #[derive(Debug)]
-pub struct ConvertStruct<T: Dtype, S: Shape, TensorType: Converts<T, S>> {
+pub struct ConvertStruct<T: NumDtype, S: Shape, TensorType: Converts<T, S>> {
     data: TensorType,
     _dtype: PhantomData<T>,
     _shape: PhantomData<S>,
 }
 
-impl<T: Dtype, S: Shape, TensorType: Converts<T, S>> ConvertStruct<T, S, TensorType> {
+impl<T: NumDtype, S: Shape, TensorType: Converts<T, S>> ConvertStruct<T, S, TensorType> {
     fn new(t: TensorType) -> Self {
         Self {
             data: t,
@@ -31,7 +31,7 @@ impl<T: Dtype, S: Shape, TensorType: Converts<T, S>> ConvertStruct<T, S, TensorT
     }
 }
 
-impl<T: Dtype, S: Shape, OT: Dtype> Op for ConvertStruct<T, S, Tensor<OT, S>>
+impl<T: NumDtype, S: Shape, OT: NumDtype> Op for ConvertStruct<T, S, Tensor<OT, S>>
 where
     Tensor<OT, S>: Converts<T, S>,
     T: NumCast,
@@ -56,16 +56,19 @@ where
 
     fn forward(self) -> Tensor<T, S> {
         let value = el_unary(|v| NumCast::from(*v).unwrap(), &self.data.borrow_value());
-        let data = TensorData::new(value, self.data.requires_grad());
+        // Only signed tensors can require grad, so converting to an unsigned dtype detaches the
+        // output. `T` can't be matched on, but only signed dtypes can represent -1
+        let signed = T::from_i8(-1).is_some();
+        let data = TensorData::new(value, self.data.requires_grad() && signed);
         unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
     }
 
-    fn operands(&self) -> Vec<TensorBox> {
+    fn operands(&self) -> Vec<TensorBox<'_>> {
         vec![TensorBox::new(self.data.id, &self.data)]
     }
 }
 
-impl<T: Dtype, S: Shape, OT: Dtype> Converts<T, S> for Tensor<OT, S>
+impl<T: NumDtype, S: Shape, OT: NumDtype> Converts<T, S> for Tensor<OT, S>
 where
     T: NumCast,
     OT: NumCast,
@@ -76,3 +79,25 @@ where
         ConvertStruct::new(self).forward()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Converts;
+    use crate::shape::I;
+    use crate::tensor::{Tensor, TensorTrait};
+
+    #[test]
+    fn test_convert_grad() {
+        let x: Tensor<f64, (I<2>,)> = Tensor::new_with_grad([1.0, 2.0]);
+        let i: Tensor<i32, (I<2>,)> = x.clone().convert();
+        let y: Tensor<f64, (I<2>,)> = i.convert();
+        (y * Tensor::new([-1.0, 1.0])).reduce_sum().backward();
+        assert_eq!(*x.borrow_grad().unwrap(), [-1.0, 1.0]);
+
+        // Unsigned tensors can't require grad, so the round trip through u8 is detached
+        let u: Tensor<u8, (I<2>,)> = x.clone().convert();
+        assert!(!u.requires_grad());
+        let y: Tensor<f64, (I<2>,)> = u.convert();
+        assert!(!(y * Tensor::new([-1.0, 1.0])).reduce_sum().requires_grad());
+    }
+}
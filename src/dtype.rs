@@ -1,11 +1,67 @@
-use num::{FromPrimitive, Signed};
+use num::{Float, FromPrimitive, Integer, Num, PrimInt, Signed};
 
-pub trait Dtype:
-    Copy + Signed + PartialOrd<Self> + std::fmt::Debug + FromPrimitive + 'static
-{
-}
+/// Any type that can be stored in a tensor, including `bool`. Tensors of these can be created,
+/// cloned, reshaped and compared, but arithmetic and grads need a `NumDtype`. This is implemented
+/// for each primitive rather than as a blanket impl, so that e.g. `[f64; 3]` isn't a dtype and
+/// nested arrays are always read as multidimensional tensors.
+pub trait Dtype: Copy + PartialEq + PartialOrd<Self> + std::fmt::Debug + 'static {}
 
-impl<T> Dtype for T where
-    T: Copy + Signed + PartialOrd<T> + std::fmt::Debug + FromPrimitive + 'static
-{
+macro_rules! impl_dtype {
+    ($($t:ty),*) => {
+        $(impl Dtype for $t {})*
+    };
 }
+
+impl_dtype!(bool, f32, f64, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Numeric dtypes, which support arithmetic and backprop.
+///
+/// ```compile_fail
+/// use mlframework::Tensor;
+/// let x = Tensor::new([true, false]);
+/// x.relu();
+/// ```
+pub trait NumDtype: Dtype + Num + FromPrimitive {}
+
+impl<T> NumDtype for T where T: Dtype + Num + FromPrimitive {}
+
+/// Numeric dtypes which can be negated. Only tensors of these can require grad, as the grads of
+/// ops like subtraction are negative.
+///
+/// ```compile_fail
+/// use mlframework::Tensor;
+/// let x = Tensor::new([1u8, 2]);
+/// -x;
+/// ```
+///
+/// ```compile_fail
+/// use mlframework::Tensor;
+/// let x = Tensor::new_with_grad([1u8, 2]);
+/// ```
+pub trait SignedDtype: NumDtype + Signed {}
+
+impl<T> SignedDtype for T where T: NumDtype + Signed {}
+
+/// Signed and unsigned integer dtypes, which have floor division, remainders and bitwise ops.
+/// Gather and index ops take indices of any of these.
+///
+/// ```compile_fail
+/// use mlframework::Tensor;
+/// let x = Tensor::new([1.0, 2.0]);
+/// x.floor_div(Tensor::new([2.0]));
+/// ```
+pub trait IntDtype: NumDtype + PrimInt + Integer {}
+
+impl<T> IntDtype for T where T: NumDtype + PrimInt + Integer {}
+
+/// Dtypes with transcendental functions, e.g. for `exp` or `tanh`. Integer tensors don't have
+/// these ops.
+///
+/// ```compile_fail
+/// use mlframework::Tensor;
+/// let x = Tensor::new([1, 2, 3]);
+/// x.exp();
+/// ```
+pub trait FloatDtype: SignedDtype + Float {}
+
+impl<T> FloatDtype for T where T: SignedDtype + Float {}
@@ -0,0 +1,668 @@
+use std::rc::Rc;
+
+use crate::{
+    dtype::{FloatDtype, IntDtype, NumDtype},
+    ops::{
+        gather::{GatherStruct, IndexSelectStruct, ScatterAddStruct},
+        index::NarrowStruct,
+        pad::{PadMode, PadStruct},
+        reduce::{
+            LogSumExpAxisStruct, MaxAxisStruct, MeanAxisStruct, MinAxisStruct, ProdAxisStruct,
+            SumAxisStruct,
+        },
+        softmax::{LogSoftmaxStruct, SoftmaxStruct},
+        tensor::{BmmStruct, MatmulStruct},
+        vec::{concat, split_parts},
+        Op,
+    },
+    reshape::{ExpandStruct, ViewStruct},
+    shape::{Dims, DynShape, Shape, ShapeMismatch},
+    tensor::{Tensor, TensorBox, TensorTrait},
+    tensor_data::TensorData,
+};
+
+/// A tensor whose rank and dims are only known at runtime. Ops check shapes when they are
+/// created and panic on mismatches. Use `try_into` to check the shape once and continue with
+/// a typed `Tensor`.
+///
+/// Elementwise, scalar, activation and logical ops work on any shape. On top of those,
+/// `DynTensor` has runtime-checked versions of matmul/bmm, transpose/permute/reshape, the axis
+/// reductions, narrow/select/split/chunk, squeeze/unsqueeze/expand, concat/stack,
+/// gather/scatter_add/index_select, pad and softmax/log_softmax, taking axes as arguments.
+/// Apart from concat/stack these are the typed ops with a `DynShape` output, so e.g. `permute`
+/// is a strided view here too. Convolution, pooling, normalization and the losses are only
+/// implemented for typed tensors.
+pub type DynTensor<T> = Tensor<T, DynShape>;
+
+/// Changes the shape type of a tensor without touching its data. Storage (and therefore grad) is
+/// shared with the operand, like `ReshapeStruct`, so the operand has to be contiguous.
+#[derive(Debug)]
+pub struct CastShapeStruct<T: NumDtype, Si: Dims, So: Dims>(Tensor<T, Si>, So);
+
+impl<T: NumDtype, Si: Dims, So: Dims> Op for CastShapeStruct<T, Si, So> {
+    type Produces = Tensor<T, So>;
+
+    fn propogate_grad(&self, _t: &Self::Produces) {
+        // Data is shared with the operand, therefore no grad propogation occurs
+    }
+
+    fn recompute(&self, _t: &Self::Produces) {}
+
+    fn forward(self) -> Self::Produces {
+        let data = self.0.data.clone();
+        let shape = self.1.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.0.id, &self.0)]
+    }
+}
+
+impl<T: NumDtype, S: Dims> Tensor<T, S> {
+    /// Erase the static shape of this tensor. The result shares storage and stays in the graph.
+    pub fn into_dyn(self) -> DynTensor<T> {
+        let shape = DynShape(self.shape.dims());
+        CastShapeStruct(self.contiguous(), shape).forward()
+    }
+}
+
+impl<T: NumDtype, S: Shape> TryFrom<DynTensor<T>> for Tensor<T, S> {
+    type Error = ShapeMismatch;
+
+    fn try_from(value: DynTensor<T>) -> Result<Self, Self::Error> {
+        if value.shape.0 != S::shape() {
+            return Err(ShapeMismatch {
+                expected: S::shape().to_vec(),
+                found: value.shape.0.clone(),
+            });
+        }
+        Ok(CastShapeStruct(value.contiguous(), S::default()).forward())
+    }
+}
+
+/// Concatenation of any number of tensors along `axis`, which is also how `stack` is built.
+#[derive(Debug)]
+pub struct DynConcatStruct<T: NumDtype>(Vec<DynTensor<T>>, usize);
+
+// Concat
+impl<T: NumDtype> DynConcatStruct<T> {
+    fn outer(&self) -> usize {
+        self.0[0].shape.0[..self.1].iter().product()
+    }
+
+    fn compute(&self) -> Vec<T> {
+        let values: Vec<_> = self.0.iter().map(|t| t.borrow_value()).collect();
+        let parts: Vec<&[T]> = values.iter().map(|v| &v[..]).collect();
+        concat(&parts, self.outer())
+    }
+}
+
+impl<T: NumDtype> Op for DynConcatStruct<T> {
+    type Produces = DynTensor<T>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = concat([a_0, .., a_n], axis)
+        // d_da_i = split(d_dt, axis)[i]
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let lens: Vec<_> = self.0.iter().map(|a| a.shape.num_els()).collect();
+            for (a, d_da) in self.0.iter().zip(split_parts(d_dt, self.outer(), &lens)) {
+                a.update_grad(d_da);
+            }
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        assert!(
+            !self.0.is_empty(),
+            "Cannot concatenate an empty list of tensors"
+        );
+        let first = &self.0[0].shape.0;
+        check_axis(first, self.1);
+        let mut shape = first.clone();
+        shape[self.1] = 0;
+        for t in &self.0 {
+            let dims = &t.shape.0;
+            assert!(
+                dims.len() == first.len()
+                    && (0..dims.len()).all(|i| i == self.1 || dims[i] == first[i]),
+                "Cannot concatenate shapes {:?} and {:?} along axis {}",
+                first,
+                dims,
+                self.1
+            );
+            shape[self.1] += dims[self.1];
+        }
+        let requires_grad = self.0.iter().any(|t| t.requires_grad());
+        let data = TensorData::new(self.compute(), requires_grad);
+        let shape = DynShape(shape);
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        self.0
+            .iter()
+            .map(|t| TensorBox::new(t.id, t as &dyn TensorTrait))
+            .collect()
+    }
+}
+
+fn check_axis(shape: &[usize], axis: usize) {
+    assert!(
+        axis < shape.len(),
+        "Axis {} is out of bounds for shape {:?}",
+        axis,
+        shape
+    );
+}
+
+/// `shape` with `axis` replaced by `len`, or removed if `len` is `None`.
+fn with_axis(shape: &[usize], axis: usize, len: Option<usize>) -> DynShape {
+    let mut shape = shape.to_vec();
+    match len {
+        Some(len) => shape[axis] = len,
+        None => {
+            shape.remove(axis);
+        }
+    }
+    DynShape(shape)
+}
+
+// Reductions along `axis`, which either drop it or keep it with size 1. As for typed tensors,
+// reducing the only axis of a 1d tensor keeps it.
+macro_rules! impl_dyn_reduce_axis {
+    ($s:ident, $tf:ident, $tf_keepdim:ident) => {
+        pub fn $tf(self, axis: usize) -> DynTensor<T> {
+            check_axis(&self.shape.0, axis);
+            let len = (self.shape.0.len() == 1).then_some(1);
+            let out_shape = with_axis(&self.shape.0, axis, len);
+            $s {
+                data: self,
+                axis,
+                out_shape,
+            }
+            .forward()
+        }
+
+        pub fn $tf_keepdim(self, axis: usize) -> DynTensor<T> {
+            check_axis(&self.shape.0, axis);
+            let out_shape = with_axis(&self.shape.0, axis, Some(1));
+            $s {
+                data: self,
+                axis,
+                out_shape,
+            }
+            .forward()
+        }
+    };
+}
+
+impl<T: NumDtype> DynTensor<T> {
+    pub fn matmul(&self, other: DynTensor<T>) -> DynTensor<T> {
+        let (a, b) = (&self.shape.0, &other.shape.0);
+        assert!(
+            a.len() == 2 && b.len() == 2,
+            "Matmul expects 2d tensors, found shapes {:?} and {:?}",
+            a,
+            b
+        );
+        let shape = DynShape(vec![a[0], b[1]]);
+        MatmulStruct(self.shallow_clone(), other, shape).forward()
+    }
+
+    /// Batched matmul of a `(B, N, M)` tensor with a `(B, M, O)` tensor, or with a `(M, O)`
+    /// tensor which is shared across the batch.
+    pub fn bmm(&self, other: DynTensor<T>) -> DynTensor<T> {
+        let (a, b) = (&self.shape.0, &other.shape.0);
+        assert!(
+            a.len() == 3 && (b.len() == 2 || (b.len() == 3 && b[0] == a[0])),
+            "Bmm expects shapes (B, N, M) and (B, M, O) or (M, O), found {:?} and {:?}",
+            a,
+            b
+        );
+        assert_eq!(
+            a[2],
+            b[b.len() - 2],
+            "Inner dims of bmm operands do not match"
+        );
+        let shape = DynShape(vec![a[0], a[1], b[b.len() - 1]]);
+        BmmStruct(self.shallow_clone(), other, shape).forward()
+    }
+
+    pub fn transpose(self) -> DynTensor<T> {
+        assert_eq!(self.shape.0.len(), 2, "Transpose expects a 2d tensor");
+        self.permute(&[1, 0])
+    }
+
+    /// Reorder axes so that output axis `i` is input axis `axes[i]`, as a view.
+    pub fn permute(self, axes: &[usize]) -> DynTensor<T> {
+        let dims = &self.shape.0;
+        let mut sorted = axes.to_vec();
+        sorted.sort();
+        assert!(
+            sorted.iter().copied().eq(0..dims.len()),
+            "Axes {:?} are not a valid permutation for shape {:?}",
+            axes,
+            dims
+        );
+        let layout = self.data.layout(dims).permute(axes);
+        ViewStruct {
+            shape: DynShape(layout.shape.clone()),
+            layout: Some(layout),
+            data: self,
+        }
+        .forward()
+    }
+
+    /// Reinterpret the data with a new shape. Panics if the number of elements differs.
+    pub fn reshape(self, shape: &[usize]) -> DynTensor<T> {
+        assert_eq!(
+            self.shape.num_els(),
+            shape.iter().product(),
+            "Cannot reshape {:?} to {:?}",
+            self.shape.0,
+            shape
+        );
+        CastShapeStruct(self.contiguous(), DynShape(shape.to_vec())).forward()
+    }
+
+    impl_dyn_reduce_axis!(SumAxisStruct, sum_axis, sum_axis_keepdim);
+    impl_dyn_reduce_axis!(MeanAxisStruct, mean_axis, mean_axis_keepdim);
+    impl_dyn_reduce_axis!(ProdAxisStruct, prod_axis, prod_axis_keepdim);
+    impl_dyn_reduce_axis!(MaxAxisStruct, max_axis, max_axis_keepdim);
+    impl_dyn_reduce_axis!(MinAxisStruct, min_axis, min_axis_keepdim);
+
+    /// Take `start..start + len` along `axis` as a view.
+    pub fn narrow(self, axis: usize, start: usize, len: usize) -> DynTensor<T> {
+        check_axis(&self.shape.0, axis);
+        assert!(
+            start + len <= self.shape.0[axis],
+            "Range {}..{} is out of bounds for axis {} of shape {:?}",
+            start,
+            start + len,
+            axis,
+            self.shape.0
+        );
+        let shape = with_axis(&self.shape.0, axis, Some(len));
+        NarrowStruct::with_shape(self, axis, start, len, shape).forward()
+    }
+
+    /// Take element `idx` along `axis`, dropping that axis. `select(0, idx)` is the runtime
+    /// version of `index`.
+    pub fn select(self, axis: usize, idx: usize) -> DynTensor<T> {
+        check_axis(&self.shape.0, axis);
+        assert!(
+            idx < self.shape.0[axis],
+            "Index {} is out of bounds for axis {} of shape {:?}",
+            idx,
+            axis,
+            self.shape.0
+        );
+        let shape = with_axis(&self.shape.0, axis, None);
+        NarrowStruct::with_shape(self, axis, idx, 1, shape).forward()
+    }
+
+    /// Split along `axis` into `..at` and `at..`.
+    pub fn split(self, axis: usize, at: usize) -> (DynTensor<T>, DynTensor<T>) {
+        check_axis(&self.shape.0, axis);
+        let len = self.shape.0[axis].checked_sub(at).unwrap_or_else(|| {
+            panic!(
+                "Cannot split axis {} of shape {:?} at {}",
+                axis, self.shape.0, at
+            )
+        });
+        (
+            self.shallow_clone().narrow(axis, 0, at),
+            self.narrow(axis, at, len),
+        )
+    }
+
+    /// Split along `axis` into `n` equally sized chunks. Panics if `n` doesn't divide the axis.
+    pub fn chunk(self, axis: usize, n: usize) -> Vec<DynTensor<T>> {
+        check_axis(&self.shape.0, axis);
+        let size = self.shape.0[axis];
+        assert!(
+            n > 0 && size.is_multiple_of(n),
+            "Cannot chunk axis {} of shape {:?} into {} chunks",
+            axis,
+            self.shape.0,
+            n
+        );
+        let len = size / n;
+        (0..n)
+            .map(|i| self.shallow_clone().narrow(axis, i * len, len))
+            .collect()
+    }
+
+    /// Insert a size 1 axis at `axis`.
+    pub fn unsqueeze(self, axis: usize) -> DynTensor<T> {
+        assert!(
+            axis <= self.shape.0.len(),
+            "Axis {} is out of bounds for inserting into shape {:?}",
+            axis,
+            self.shape.0
+        );
+        let mut shape = self.shape.0.clone();
+        shape.insert(axis, 1);
+        let layout =
+            (!self.data.is_contiguous()).then(|| self.data.layout(&self.shape.0).insert_axis(axis));
+        ViewStruct {
+            data: self,
+            layout,
+            shape: DynShape(shape),
+        }
+        .forward()
+    }
+
+    /// Remove the size 1 axis at `axis`. Panics if the axis has any other size.
+    pub fn squeeze(self, axis: usize) -> DynTensor<T> {
+        check_axis(&self.shape.0, axis);
+        assert_eq!(
+            self.shape.0[axis], 1,
+            "Cannot squeeze axis {} of shape {:?}",
+            axis, self.shape.0
+        );
+        let shape = with_axis(&self.shape.0, axis, None);
+        let layout =
+            (!self.data.is_contiguous()).then(|| self.data.layout(&self.shape.0).remove_axis(axis));
+        ViewStruct {
+            data: self,
+            layout,
+            shape,
+        }
+        .forward()
+    }
+
+    /// Broadcast the size 1 axes of this tensor to `shape`, as a view.
+    pub fn expand(self, shape: &[usize]) -> DynTensor<T> {
+        let dims = &self.shape.0;
+        let lead = shape.len().checked_sub(dims.len());
+        assert!(
+            lead.is_some_and(|lead| {
+                dims.iter()
+                    .zip(&shape[lead..])
+                    .all(|(d, s)| *d == 1 || d == s)
+            }),
+            "Cannot expand shape {:?} to {:?}",
+            dims,
+            shape
+        );
+        ExpandStruct(self, DynShape(shape.to_vec())).forward()
+    }
+
+    /// Concatenate `self` and `other` along `axis`.
+    pub fn concat(self, other: DynTensor<T>, axis: usize) -> DynTensor<T> {
+        DynConcatStruct(vec![self, other], axis).forward()
+    }
+
+    /// Stack tensors of the same shape along a new axis inserted at `axis`.
+    pub fn stack(tensors: Vec<DynTensor<T>>, axis: usize) -> DynTensor<T> {
+        let parts = tensors.into_iter().map(|t| t.unsqueeze(axis)).collect();
+        DynConcatStruct(parts, axis).forward()
+    }
+
+    /// Take `out[.., j, ..] = self[.., idx[.., j, ..], ..]` along `axis`. `idx` has the same
+    /// shape as `self` except along `axis`.
+    pub fn gather<Ix: IntDtype>(self, axis: usize, idx: DynTensor<Ix>) -> DynTensor<T> {
+        check_gather_index(&self.shape.0, &idx.shape.0, axis);
+        GatherStruct {
+            data: self,
+            idx,
+            axis,
+        }
+        .forward()
+    }
+
+    /// Add `src[.., j, ..]` into `self[.., idx[.., j, ..], ..]` along `axis`. Repeated indices
+    /// accumulate.
+    pub fn scatter_add<Ix: IntDtype>(
+        self,
+        axis: usize,
+        idx: DynTensor<Ix>,
+        src: DynTensor<T>,
+    ) -> DynTensor<T> {
+        check_gather_index(&self.shape.0, &idx.shape.0, axis);
+        assert_eq!(
+            idx.shape.0, src.shape.0,
+            "Scatter indices and source have different shapes"
+        );
+        ScatterAddStruct {
+            data: self,
+            idx,
+            src,
+            axis,
+        }
+        .forward()
+    }
+
+    /// Select the slices `idx` along `axis`, where `idx` is 1d.
+    pub fn index_select<Ix: IntDtype>(self, axis: usize, idx: DynTensor<Ix>) -> DynTensor<T> {
+        check_axis(&self.shape.0, axis);
+        assert_eq!(
+            idx.shape.0.len(),
+            1,
+            "Index select expects a 1d index tensor"
+        );
+        let out_shape = with_axis(&self.shape.0, axis, Some(idx.shape.0[0]));
+        IndexSelectStruct {
+            data: self,
+            idx,
+            axis,
+            out_shape,
+        }
+        .forward()
+    }
+
+    /// Pad `axis` with `before` elements before and `after` elements after the data. Panics if
+    /// reflect or circular padding is larger than the axis.
+    pub fn pad(self, axis: usize, before: usize, after: usize, mode: PadMode<T>) -> DynTensor<T> {
+        check_axis(&self.shape.0, axis);
+        let len = self.shape.0[axis] + before + after;
+        let shape = with_axis(&self.shape.0, axis, Some(len));
+        PadStruct::new(self, axis, before, after, mode, shape).forward()
+    }
+}
+
+fn check_gather_index(shape: &[usize], idx_shape: &[usize], axis: usize) {
+    check_axis(shape, axis);
+    assert!(
+        idx_shape.len() == shape.len()
+            && (0..shape.len()).all(|i| i == axis || idx_shape[i] == shape[i]),
+        "Index shape {:?} doesn't match shape {:?} outside axis {}",
+        idx_shape,
+        shape,
+        axis
+    );
+}
+
+impl<T: FloatDtype> DynTensor<T> {
+    impl_dyn_reduce_axis!(LogSumExpAxisStruct, logsumexp, logsumexp_keepdim);
+
+    /// Normalize `exp(x)` to sum to 1 along `axis`.
+    pub fn softmax(self, axis: usize) -> DynTensor<T> {
+        check_axis(&self.shape.0, axis);
+        SoftmaxStruct(self, axis).forward()
+    }
+
+    /// `ln(softmax(x))` along `axis`.
+    pub fn log_softmax(self, axis: usize) -> DynTensor<T> {
+        check_axis(&self.shape.0, axis);
+        LogSoftmaxStruct(self, axis).forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DynTensor;
+    use crate::ops::PadMode;
+    use crate::shape::{DynShape, ShapeMismatch, I};
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_dyn_ops_grad() {
+        let x = DynTensor::from_vec_and_shape_with_grad(
+            vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0],
+            DynShape(vec![2, 3]),
+        );
+        let w = Tensor::new_with_grad([[1.0], [0.0], [-1.0]]).into_dyn();
+        let b = DynTensor::from_vec_and_shape(vec![1.0], DynShape(vec![1]));
+        let y = x.clone().matmul(w.clone()) + b;
+        assert_eq!(y.shape(), &DynShape(vec![2, 1]));
+        assert_eq!(*y.borrow_value(), [-1.0, -1.0]);
+
+        let s: Tensor<f64, (I<1>,)> = y.sum_axis(0).try_into().unwrap();
+        s.backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[1.0, 0.0, -1.0, 1.0, 0.0, -1.0][..])
+        );
+        assert_eq!(w.borrow_grad().as_deref(), Some(&[5.0, 7.0, 9.0][..]));
+    }
+
+    #[test]
+    fn test_try_into_typed() {
+        let x = DynTensor::from_vec_and_shape((0..6).collect(), DynShape(vec![3, 2]));
+        let t: Result<Tensor<i32, (I<2>, I<3>)>, _> = x.clone().try_into();
+        assert_eq!(
+            t.unwrap_err(),
+            ShapeMismatch {
+                expected: vec![2, 3],
+                found: vec![3, 2]
+            }
+        );
+        let t: Tensor<i32, (I<2>, I<3>)> = x.transpose().try_into().unwrap();
+        assert_eq!(*t.borrow_value(), [0, 2, 4, 1, 3, 5]);
+    }
+
+    #[test]
+    fn test_cast_strided_view() {
+        // Casting the shape of a view copies it first, so the layout matches the new shape
+        let x = Tensor::new([[1, 2, 3], [4, 5, 6]]);
+        let f: Tensor<i32, (I<6>,)> = x.transpose().into_dyn().reshape(&[6]).try_into().unwrap();
+        assert_eq!(*f.borrow_value(), [1, 4, 2, 5, 3, 6]);
+        assert_eq!(*f.narrow::<0, 1, 2>().borrow_value(), [4, 2]);
+    }
+
+    #[test]
+    fn test_dyn_views() {
+        let x = DynTensor::from_vec_and_shape_with_grad(
+            (0..6).map(f64::from).collect(),
+            DynShape(vec![2, 3]),
+        );
+        let n = x.clone().transpose().narrow(0, 1, 2);
+        assert_eq!(n.shape(), &DynShape(vec![2, 2]));
+        assert_eq!(*n.borrow_value(), [1.0, 4.0, 2.0, 5.0]);
+        let s = x.clone().select(1, 2);
+        assert_eq!(*s.borrow_value(), [2.0, 5.0]);
+
+        let (l, r) = x.clone().split(1, 1);
+        assert_eq!(r.shape(), &DynShape(vec![2, 2]));
+        assert_eq!(*l.borrow_value(), [0.0, 3.0]);
+        let c = x.clone().chunk(0, 2);
+        assert_eq!(*c[1].borrow_value(), [3.0, 4.0, 5.0]);
+
+        let u = s.clone().unsqueeze(0);
+        assert_eq!(u.shape(), &DynShape(vec![1, 2]));
+        let e = u.expand(&[3, 2]);
+        assert_eq!(*e.borrow_value(), [2.0, 5.0, 2.0, 5.0, 2.0, 5.0]);
+        assert_eq!(e.shape(), &DynShape(vec![3, 2]));
+        let q = n.narrow(1, 0, 1).squeeze(1);
+        assert_eq!(*q.borrow_value(), [1.0, 2.0]);
+
+        let y = e.sum_axis(0) + q;
+        let total: Tensor<f64, (I<1>,)> = y.sum_axis(0).try_into().unwrap();
+        total.backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[0.0, 1.0, 4.0, 0.0, 0.0, 3.0][..])
+        );
+    }
+
+    #[test]
+    fn test_dyn_permute_and_keepdim() {
+        let x = DynTensor::from_vec_and_shape_with_grad(
+            (0..24).map(f64::from).collect(),
+            DynShape(vec![2, 3, 4]),
+        );
+        let p = x.shallow_clone().permute(&[2, 0, 1]);
+        assert_eq!(p.shape(), &DynShape(vec![4, 2, 3]));
+        assert_eq!(p.borrow_value()[..6], [0.0, 4.0, 8.0, 12.0, 16.0, 20.0]);
+        // Like the typed permute it's a view, so it sees writes to x
+        x.replace_data_with((0..24).map(|v| f64::from(v) * 2.0).collect());
+        assert_eq!(p.borrow_value()[1], 8.0);
+
+        let m = p.max_axis_keepdim(2);
+        assert_eq!(m.shape(), &DynShape(vec![4, 2, 1]));
+        let total: Tensor<f64, (I<1>,)> = m.reshape(&[8]).sum_axis(0).try_into().unwrap();
+        total.backward();
+        // The max over the original axis 1 is at index 2
+        let grad = x.borrow_grad().unwrap().to_vec();
+        assert_eq!(grad.iter().sum::<f64>(), 8.0);
+        assert!(grad[8..12].iter().chain(&grad[20..]).all(|g| *g == 1.0));
+    }
+
+    #[test]
+    fn test_dyn_concat_and_indexing() {
+        let a = DynTensor::from_vec_and_shape_with_grad(vec![1.0, 2.0], DynShape(vec![2, 1]));
+        let b = DynTensor::from_vec_and_shape_with_grad(vec![3.0, 4.0], DynShape(vec![2, 1]));
+        let c = a.clone().concat(b.clone(), 1);
+        assert_eq!(*c.borrow_value(), [1.0, 3.0, 2.0, 4.0]);
+        let s = DynTensor::stack(vec![a.clone(), b.clone()], 0);
+        assert_eq!(s.shape(), &DynShape(vec![2, 2, 1]));
+        assert_eq!(*s.borrow_value(), [1.0, 2.0, 3.0, 4.0]);
+
+        let idx = DynTensor::from_vec_and_shape(vec![1usize, 1], DynShape(vec![2, 1]));
+        let g = c.clone().gather(1, idx.clone());
+        assert_eq!(*g.borrow_value(), [3.0, 4.0]);
+        let i = c.clone().index_select(
+            0,
+            DynTensor::from_vec_and_shape(vec![1u8], DynShape(vec![1])),
+        );
+        assert_eq!(*i.borrow_value(), [2.0, 4.0]);
+        let sc = c.scatter_add(1, idx, g);
+        assert_eq!(*sc.borrow_value(), [1.0, 6.0, 2.0, 8.0]);
+
+        let p = sc.pad(1, 1, 0, PadMode::Replicate);
+        assert_eq!(*p.borrow_value(), [1.0, 1.0, 6.0, 2.0, 2.0, 8.0]);
+        let total: Tensor<f64, (I<1>,)> = p.sum_axis(1).sum_axis(0).try_into().unwrap();
+        total.backward();
+        assert_eq!(a.borrow_grad().as_deref(), Some(&[2.0, 2.0][..]));
+        assert_eq!(b.borrow_grad().as_deref(), Some(&[2.0, 2.0][..]));
+    }
+
+    #[test]
+    fn test_dyn_softmax_and_bmm() {
+        let x = DynTensor::from_vec_and_shape(vec![0.0, 0.0, 1000.0, 1000.0], DynShape(vec![2, 2]));
+        assert_eq!(*x.clone().softmax(1).borrow_value(), [0.5, 0.5, 0.5, 0.5]);
+        assert_eq!(
+            *x.log_softmax(0).borrow_value(),
+            [-1000.0, -1000.0, 0.0, 0.0]
+        );
+
+        let a = DynTensor::from_vec_and_shape_with_grad(
+            (1..=8).map(f64::from).collect(),
+            DynShape(vec![2, 2, 2]),
+        );
+        let w = DynTensor::from_vec_and_shape_with_grad(vec![1.0, 0.0], DynShape(vec![2, 1]));
+        let y = a.bmm(w.clone());
+        assert_eq!(y.shape(), &DynShape(vec![2, 2, 1]));
+        assert_eq!(*y.borrow_value(), [1.0, 3.0, 5.0, 7.0]);
+        let total: Tensor<f64, (I<1>,)> = y.reshape(&[4]).sum_axis(0).try_into().unwrap();
+        total.backward();
+        assert_eq!(w.borrow_grad().as_deref(), Some(&[16.0, 20.0][..]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot concatenate shapes")]
+    fn test_dyn_concat_mismatch() {
+        let a = DynTensor::from_vec_and_shape(vec![1, 2], DynShape(vec![1, 2]));
+        let b = DynTensor::from_vec_and_shape(vec![1, 2, 3], DynShape(vec![1, 3]));
+        a.concat(b, 0);
+    }
+}
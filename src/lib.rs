@@ -3,7 +3,10 @@
 pub mod build_model;
 pub mod change_dtype;
 pub mod dtype;
+pub mod dyn_tensor;
+pub mod loss;
 pub mod module;
+pub mod nn;
 pub mod ops;
 pub mod optim;
 pub mod random;
@@ -13,5 +16,12 @@ pub mod tensor;
 mod tensor_data;
 pub mod tensor_from;
 mod tensor_id;
+#[cfg(test)]
+mod test_util;
 
+// Lets unit tests use the exported macros, which refer to the crate by name
+#[cfg(test)]
+extern crate self as mlframework;
+
+pub use dyn_tensor::DynTensor;
 pub use tensor::Tensor;
@@ -0,0 +1,532 @@
+//! Loss functions. Each takes predictions and targets of the same shape, plus a `LossReduction`
+//! which decides whether the per-element losses are averaged, summed or returned as they are,
+//! e.g. `loss::mse(y_hat, y, Mean).backward()`.
+
+use crate::ops::gather::indices;
+use crate::ops::grad::{
+    cosine_similarity_grad, el_bce_loss_grad, el_bce_with_logits_loss_grad, el_hinge_loss_grad,
+    el_huber_loss_grad, el_kl_div_loss_grad, el_l1_loss_grad, el_mse_loss_grad,
+    el_smooth_l1_loss_grad, nll_loss_grad,
+};
+use crate::ops::vec::{
+    cosine_similarity, el_bce_loss, el_bce_with_logits_loss, el_hinge_loss, el_huber_loss,
+    el_kl_div_loss, el_l1_loss, el_mse_loss, el_mul, el_smooth_l1_loss, nll_loss,
+};
+use crate::ops::Op;
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{
+    dtype::{FloatDtype, IntDtype},
+    shape::{Dims, I},
+    tensor::Tensor,
+};
+use std::borrow::Cow;
+use std::rc::Rc;
+
+/// How the per-element losses are combined.
+pub trait LossReduction {
+    type Output<T: FloatDtype, S: Dims>;
+
+    fn reduce<T: FloatDtype, S: Dims>(self, losses: Tensor<T, S>) -> Self::Output<T, S>;
+}
+
+/// Average the losses.
+#[derive(Debug, Clone, Copy)]
+pub struct Mean;
+
+/// Sum the losses.
+#[derive(Debug, Clone, Copy)]
+pub struct Sum;
+
+/// Return the per-element losses.
+#[derive(Debug, Clone, Copy)]
+pub struct NoReduction;
+
+impl LossReduction for Mean {
+    type Output<T: FloatDtype, S: Dims> = Tensor<T, (I<1>,)>;
+
+    fn reduce<T: FloatDtype, S: Dims>(self, losses: Tensor<T, S>) -> Tensor<T, (I<1>,)> {
+        let n = T::from_usize(losses.shape.num_els()).expect("Failed to cast size to dtype");
+        losses.reduce_sum() / Tensor::new([n])
+    }
+}
+
+impl LossReduction for Sum {
+    type Output<T: FloatDtype, S: Dims> = Tensor<T, (I<1>,)>;
+
+    fn reduce<T: FloatDtype, S: Dims>(self, losses: Tensor<T, S>) -> Tensor<T, (I<1>,)> {
+        losses.reduce_sum()
+    }
+}
+
+impl LossReduction for NoReduction {
+    type Output<T: FloatDtype, S: Dims> = Tensor<T, S>;
+
+    fn reduce<T: FloatDtype, S: Dims>(self, losses: Tensor<T, S>) -> Tensor<T, S> {
+        losses
+    }
+}
+
+macro_rules! impl_el_loss_op {
+    ($s:ident, $f:expr, $df:expr) => {
+        #[derive(Debug)]
+        pub struct $s<T: FloatDtype, S: Dims>(Tensor<T, S>, Tensor<T, S>);
+
+        impl<T: FloatDtype, S: Dims> $s<T, S> {
+            fn compute(&self, p: &[T], t: &[T]) -> Vec<T> {
+                $f(p, t)
+            }
+
+            fn grads<'a>(&self, p: &'a [T], t: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+                $df(p, t)
+            }
+        }
+
+        impl_el_loss_op!(@op $s);
+    };
+    // Losses with a scalar parameter, e.g. the delta of `huber`
+    ($s:ident, $p:ty, $f:expr, $df:expr) => {
+        #[derive(Debug)]
+        pub struct $s<T: FloatDtype, S: Dims>(Tensor<T, S>, Tensor<T, S>, $p);
+
+        impl<T: FloatDtype, S: Dims> $s<T, S> {
+            fn compute(&self, p: &[T], t: &[T]) -> Vec<T> {
+                $f(p, t, self.2)
+            }
+
+            fn grads<'a>(&self, p: &'a [T], t: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+                $df(p, t, self.2)
+            }
+        }
+
+        impl_el_loss_op!(@op $s);
+    };
+    (@op $s:ident) => {
+        impl<T: FloatDtype, S: Dims> Op for $s<T, S> {
+            type Produces = Tensor<T, S>;
+
+            fn propogate_grad(&self, l: &Self::Produces) {
+                // l = f(p, t)
+                // d_dp = d_dl * dl_dp, d_dt = d_dl * dl_dt
+                if let Some(d_dl) = l.data.grad_ref().as_ref() {
+                    let (d_dp, d_dt) = {
+                        let p = self.0.borrow_value();
+                        let t = self.1.borrow_value();
+                        let (dl_dp, dl_dt) = self.grads(&p, &t);
+                        (el_mul(d_dl, &dl_dp), el_mul(d_dl, &dl_dt))
+                    };
+                    self.0.update_grad(d_dp);
+                    self.1.update_grad(d_dt);
+                } else {
+                    panic!("Attempted to propogate grad, but no grad value exists.")
+                }
+            }
+
+            fn recompute(&self, l: &Self::Produces) {
+                let data = self.compute(&self.0.borrow_value(), &self.1.borrow_value());
+                l.data.replace(data)
+            }
+
+            fn forward(self) -> Self::Produces {
+                assert_eq!(
+                    self.0.shape, self.1.shape,
+                    "Predictions and targets of a loss must have the same shape"
+                );
+                let value = self.compute(&self.0.borrow_value(), &self.1.borrow_value());
+                let data = TensorData::new(value, self.0.requires_grad() || self.1.requires_grad());
+                let shape = self.0.shape.clone();
+                unsafe {
+                    Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape)
+                }
+            }
+
+            fn operands(&self) -> Vec<TensorBox<'_>> {
+                vec![
+                    TensorBox::new(self.0.id, &self.0),
+                    TensorBox::new(self.1.id, &self.1),
+                ]
+            }
+        }
+    };
+}
+
+impl_el_loss_op!(MseLossStruct, el_mse_loss, el_mse_loss_grad);
+impl_el_loss_op!(L1LossStruct, el_l1_loss, el_l1_loss_grad);
+impl_el_loss_op!(HuberLossStruct, T, el_huber_loss, el_huber_loss_grad);
+impl_el_loss_op!(
+    SmoothL1LossStruct,
+    T,
+    el_smooth_l1_loss,
+    el_smooth_l1_loss_grad
+);
+impl_el_loss_op!(BceLossStruct, el_bce_loss, el_bce_loss_grad);
+impl_el_loss_op!(
+    BceWithLogitsLossStruct,
+    el_bce_with_logits_loss,
+    el_bce_with_logits_loss_grad
+);
+impl_el_loss_op!(KlDivLossStruct, el_kl_div_loss, el_kl_div_loss_grad);
+impl_el_loss_op!(HingeLossStruct, T, el_hinge_loss, el_hinge_loss_grad);
+
+/// Negative log likelihood of integer class targets, one per row.
+#[derive(Debug)]
+pub struct NllLossStruct<T: FloatDtype, Ix: IntDtype, const N: usize, const C: usize>(
+    Tensor<T, (I<N>, I<C>)>,
+    Tensor<Ix, (I<N>,)>,
+);
+
+impl<T: FloatDtype, Ix: IntDtype, const N: usize, const C: usize> Op
+    for NllLossStruct<T, Ix, N, C>
+{
+    type Produces = Tensor<T, (I<N>,)>;
+
+    fn propogate_grad(&self, l: &Self::Produces) {
+        // l[n] = -a[n, t[n]]
+        if let Some(d_dl) = l.data.grad_ref().as_ref() {
+            self.0
+                .update_grad(nll_loss_grad(d_dl, &indices(&self.1, C), C));
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, l: &Self::Produces) {
+        let data = nll_loss(&self.0.borrow_value(), &indices(&self.1, C), C);
+        l.data.replace(data)
+    }
+
+    fn forward(self) -> Self::Produces {
+        let value = nll_loss(&self.0.borrow_value(), &indices(&self.1, C), C);
+        let data = TensorData::new(value, self.0.requires_grad());
+        unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![
+            TensorBox::new(self.0.id, &self.0),
+            TensorBox::new(self.1.id, &self.1),
+        ]
+    }
+}
+
+/// Cosine embedding loss of pairs of rows, with a target of 1 for pairs which should be similar
+/// and -1 for pairs which should not.
+#[derive(Debug)]
+pub struct CosineEmbeddingLossStruct<T: FloatDtype, const N: usize, const D: usize> {
+    a: Tensor<T, (I<N>, I<D>)>,
+    b: Tensor<T, (I<N>, I<D>)>,
+    target: Tensor<T, (I<N>,)>,
+    margin: T,
+}
+
+impl<T: FloatDtype, const N: usize, const D: usize> CosineEmbeddingLossStruct<T, N, D> {
+    fn compute(&self) -> Vec<T> {
+        let cos = cosine_similarity(&self.a.borrow_value(), &self.b.borrow_value(), D);
+        cos.iter()
+            .zip(self.target.borrow_value().iter())
+            .map(|(c, y)| {
+                if *y > T::zero() {
+                    T::one() - *c
+                } else {
+                    (*c - self.margin).max(T::zero())
+                }
+            })
+            .collect()
+    }
+}
+
+impl<T: FloatDtype, const N: usize, const D: usize> Op for CosineEmbeddingLossStruct<T, N, D> {
+    type Produces = Tensor<T, (I<N>,)>;
+
+    fn propogate_grad(&self, l: &Self::Produces) {
+        // l = 1 - cos(a, b) for y = 1, else max(0, cos(a, b) - margin)
+        // dl_dcos = -1 for y = 1, else 1 where cos(a, b) > margin and 0 elsewhere
+        if let Some(d_dl) = l.data.grad_ref().as_ref() {
+            let (d_da, d_db) = {
+                let (a, b) = (self.a.borrow_value(), self.b.borrow_value());
+                let cos = cosine_similarity(&a, &b, D);
+                let (dcos_da, dcos_db) = cosine_similarity_grad(&a, &b, D);
+                let d_dcos: Vec<T> = cos
+                    .iter()
+                    .zip(self.target.borrow_value().iter())
+                    .zip(d_dl.iter())
+                    .map(|((c, y), g)| {
+                        if *y > T::zero() {
+                            -*g
+                        } else if *c > self.margin {
+                            *g
+                        } else {
+                            T::zero()
+                        }
+                    })
+                    .collect();
+                let scale = |dcos: Vec<T>| -> Vec<T> {
+                    dcos.chunks(D)
+                        .zip(&d_dcos)
+                        .flat_map(|(row, g)| row.iter().map(move |x| *x * *g))
+                        .collect()
+                };
+                (scale(dcos_da), scale(dcos_db))
+            };
+            self.a.update_grad(d_da);
+            self.b.update_grad(d_db);
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, l: &Self::Produces) {
+        l.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let data = TensorData::new(
+            self.compute(),
+            self.a.requires_grad() || self.b.requires_grad(),
+        );
+        unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![
+            TensorBox::new(self.a.id, &self.a),
+            TensorBox::new(self.b.id, &self.b),
+            TensorBox::new(self.target.id, &self.target),
+        ]
+    }
+}
+
+/// Mean squared error, `(pred - target)^2`.
+pub fn mse<T: FloatDtype, S: Dims, R: LossReduction>(
+    pred: Tensor<T, S>,
+    target: Tensor<T, S>,
+    reduction: R,
+) -> R::Output<T, S> {
+    reduction.reduce(MseLossStruct(pred, target).forward())
+}
+
+/// Mean absolute error, `|pred - target|`.
+pub fn l1<T: FloatDtype, S: Dims, R: LossReduction>(
+    pred: Tensor<T, S>,
+    target: Tensor<T, S>,
+    reduction: R,
+) -> R::Output<T, S> {
+    reduction.reduce(L1LossStruct(pred, target).forward())
+}
+
+/// Squared error for errors up to `delta`, and linear in the error beyond.
+pub fn huber<T: FloatDtype, S: Dims, R: LossReduction>(
+    pred: Tensor<T, S>,
+    target: Tensor<T, S>,
+    delta: T,
+    reduction: R,
+) -> R::Output<T, S> {
+    assert!(delta > T::zero(), "huber delta must be positive");
+    reduction.reduce(HuberLossStruct(pred, target, delta).forward())
+}
+
+/// `huber` with `delta = beta`, divided by `beta` so the linear part has a slope of 1.
+pub fn smooth_l1<T: FloatDtype, S: Dims, R: LossReduction>(
+    pred: Tensor<T, S>,
+    target: Tensor<T, S>,
+    beta: T,
+    reduction: R,
+) -> R::Output<T, S> {
+    assert!(beta > T::zero(), "smooth_l1 beta must be positive");
+    reduction.reduce(SmoothL1LossStruct(pred, target, beta).forward())
+}
+
+/// Binary cross-entropy of probabilities `pred` against targets in `[0, 1]`. Log probabilities
+/// are clamped to at least -100.
+pub fn binary_cross_entropy<T: FloatDtype, S: Dims, R: LossReduction>(
+    pred: Tensor<T, S>,
+    target: Tensor<T, S>,
+    reduction: R,
+) -> R::Output<T, S> {
+    reduction.reduce(BceLossStruct(pred, target).forward())
+}
+
+/// Binary cross-entropy of `sigmoid(logits)` against targets in `[0, 1]`, without computing the
+/// sigmoid, which is stable for large logits.
+pub fn binary_cross_entropy_with_logits<T: FloatDtype, S: Dims, R: LossReduction>(
+    logits: Tensor<T, S>,
+    target: Tensor<T, S>,
+    reduction: R,
+) -> R::Output<T, S> {
+    reduction.reduce(BceWithLogitsLossStruct(logits, target).forward())
+}
+
+/// Negative log likelihood of `log_probs` with shape (batch, classes) against one integer class
+/// per row.
+pub fn nll<T: FloatDtype, Ix: IntDtype, const N: usize, const C: usize, R: LossReduction>(
+    log_probs: Tensor<T, (I<N>, I<C>)>,
+    target: Tensor<Ix, (I<N>,)>,
+    reduction: R,
+) -> R::Output<T, (I<N>,)> {
+    reduction.reduce(NllLossStruct(log_probs, target).forward())
+}
+
+/// Categorical cross-entropy of `logits` with shape (batch, classes) against one integer class
+/// per row, i.e. `nll` of `log_softmax` over the classes.
+pub fn cross_entropy<
+    T: FloatDtype,
+    Ix: IntDtype,
+    const N: usize,
+    const C: usize,
+    R: LossReduction,
+>(
+    logits: Tensor<T, (I<N>, I<C>)>,
+    target: Tensor<Ix, (I<N>,)>,
+    reduction: R,
+) -> R::Output<T, (I<N>,)> {
+    nll(logits.log_softmax::<1>(), target, reduction)
+}
+
+/// KL divergence of `target` probabilities from the distribution with log probabilities
+/// `log_probs`, `target * (ln(target) - log_probs)`.
+pub fn kl_div<T: FloatDtype, S: Dims, R: LossReduction>(
+    log_probs: Tensor<T, S>,
+    target: Tensor<T, S>,
+    reduction: R,
+) -> R::Output<T, S> {
+    reduction.reduce(KlDivLossStruct(log_probs, target).forward())
+}
+
+/// `max(0, margin - target * pred)` for targets of 1 or -1.
+pub fn hinge<T: FloatDtype, S: Dims, R: LossReduction>(
+    pred: Tensor<T, S>,
+    target: Tensor<T, S>,
+    margin: T,
+    reduction: R,
+) -> R::Output<T, S> {
+    reduction.reduce(HingeLossStruct(pred, target, margin).forward())
+}
+
+/// `1 - cos(a, b)` for rows with a target of 1, and `max(0, cos(a, b) - margin)` for rows with
+/// a target of -1.
+pub fn cosine_embedding<T: FloatDtype, const N: usize, const D: usize, R: LossReduction>(
+    a: Tensor<T, (I<N>, I<D>)>,
+    b: Tensor<T, (I<N>, I<D>)>,
+    target: Tensor<T, (I<N>,)>,
+    margin: T,
+    reduction: R,
+) -> R::Output<T, (I<N>,)> {
+    reduction.reduce(
+        CosineEmbeddingLossStruct {
+            a,
+            b,
+            target,
+            margin,
+        }
+        .forward(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_close, check_grad};
+
+    #[test]
+    fn test_reductions() {
+        let p = Tensor::new([[1.0, 2.0], [3.0, 4.0]]);
+        let t = Tensor::new([[1.0, 0.0], [5.0, 4.5]]);
+        let l: Tensor<f64, (I<2>, I<2>)> = mse(p.clone(), t.clone(), NoReduction);
+        assert_close(&l.borrow_value(), &[0.0, 4.0, 4.0, 0.25]);
+        assert_close(&mse(p.clone(), t.clone(), Sum).borrow_value(), &[8.25]);
+        assert_close(&mse(p, t, Mean).borrow_value(), &[2.0625]);
+    }
+
+    #[test]
+    fn test_losses() {
+        let p = Tensor::new([0.5, -2.0, 3.0]);
+        let t = Tensor::new([1.0, 1.0, 0.0]);
+        let l1_loss = l1(p.clone(), t.clone(), NoReduction);
+        assert_close(&l1_loss.borrow_value(), &[0.5, 3.0, 3.0]);
+        let h = huber(p.clone(), t.clone(), 1.0, NoReduction);
+        assert_close(&h.borrow_value(), &[0.125, 2.5, 2.5]);
+        let s = smooth_l1(p.clone(), t.clone(), 2.0, NoReduction);
+        assert_close(&s.borrow_value(), &[0.0625, 2.0, 2.0]);
+        let h = hinge(p.clone(), Tensor::new([1.0, -1.0, 1.0]), 1.0, NoReduction);
+        assert_close(&h.borrow_value(), &[0.5, 0.0, 0.0]);
+
+        // Logits large enough to overflow exp are fine
+        let x = Tensor::new([1000.0, -1000.0, 0.0]);
+        let b = binary_cross_entropy_with_logits(x, t.clone(), NoReduction);
+        assert_close(&b.borrow_value(), &[0.0, 1000.0, 2f64.ln()]);
+        let b = binary_cross_entropy(Tensor::new([1.0, 0.0, 0.5]), t, NoReduction);
+        assert_close(&b.borrow_value(), &[0.0, 100.0, 2f64.ln()]);
+
+        let q = Tensor::new([0.25f64, 0.75, 1e-3]).ln();
+        let k = kl_div(q, Tensor::new([0.5, 0.5, 0.0]), Sum);
+        assert_close(
+            &k.borrow_value(),
+            &[0.5 * 2f64.ln() + 0.5 * (2.0f64 / 3.0).ln()],
+        );
+    }
+
+    #[test]
+    fn test_cross_entropy() {
+        let logits = Tensor::new_with_grad([[2.0, 1.0, 0.0], [0.0, 0.0, 1000.0]]);
+        let targets: Tensor<i64, (I<2>,)> = Tensor::new([0, 2]);
+        let l = cross_entropy(logits.clone(), targets.clone(), NoReduction);
+        let lse = (1f64 + (-1f64).exp() + (-2f64).exp()).ln();
+        assert_close(&l.borrow_value(), &[lse, 0.0]);
+
+        cross_entropy(logits.clone(), targets, Mean).backward();
+        let s = [1.0, (-1f64).exp(), (-2f64).exp()].map(|e| e / lse.exp());
+        assert_close(
+            &logits.borrow_grad().unwrap(),
+            &[(s[0] - 1.0) / 2.0, s[1] / 2.0, s[2] / 2.0, 0.0, 0.0, 0.0],
+        );
+
+        let log_probs = Tensor::new_with_grad([[-0.1, -2.0], [-1.5, -0.3]]);
+        nll(log_probs.clone(), Tensor::new([1, 0]), Sum).backward();
+        assert_eq!(
+            log_probs.borrow_grad().as_deref(),
+            Some(&[0.0, -1.0, -1.0, 0.0][..])
+        );
+    }
+
+    #[test]
+    fn test_loss_grads() {
+        // With respect to both operands
+        type T3 = Tensor<f64, (I<3>,)>;
+        type T1 = Tensor<f64, (I<1>,)>;
+        let losses: [fn(T3, T3) -> T1; 8] = [
+            |p, t| mse(p, t, Sum),
+            |p, t| l1(p, t, Sum),
+            |p, t| huber(p, t, 0.5, Sum),
+            |p, t| smooth_l1(p, t, 0.5, Sum),
+            |p, t| binary_cross_entropy(p, t, Sum),
+            |p, t| binary_cross_entropy_with_logits(p, t, Sum),
+            |p, t| kl_div(p, t, Sum),
+            |p, t| hinge(p, t, 1.0, Sum),
+        ];
+        let (p, t) = ([0.2, 0.7, 0.4], [0.9, 0.3, 0.45]);
+        for loss in losses {
+            check_grad(|pt| loss(pt, Tensor::new(t)), &p);
+            check_grad(|tt| loss(Tensor::new(p), tt), &t);
+        }
+    }
+
+    #[test]
+    fn test_cosine_embedding() {
+        let a = Tensor::new_with_grad([[1.0, 0.0], [1.0, 1.0]]);
+        let b = Tensor::new_with_grad([[1.0, 1.0], [2.0, 2.0]]);
+        let l = cosine_embedding(
+            a.clone(),
+            b.clone(),
+            Tensor::new([1.0, -1.0]),
+            0.5,
+            NoReduction,
+        );
+        let c = std::f64::consts::FRAC_1_SQRT_2;
+        assert_close(&l.borrow_value(), &[1.0 - c, 0.5]);
+
+        l.reduce_sum().backward();
+        // Row 0: -dcos/da, row 1: cos(a, b) = 1 is at its max so its grads are 0
+        assert_close(&a.borrow_grad().unwrap(), &[0.0, -c, 0.0, 0.0]);
+        assert_close(&b.borrow_grad().unwrap(), &[-c / 2.0, c / 2.0, 0.0, 0.0]);
+    }
+}
@@ -3,11 +3,12 @@
 use mlframework::{
     build_mod,
     change_dtype::Converts,
+    loss::{mse, Sum},
     optim::GradientDescent,
     random::randn,
     reshape::Reshapes,
     s, t,
-    tensor::{remove_inputs, TensorTrait},
+    tensor::TensorTrait,
     Tensor,
 };
 
@@ -25,11 +26,9 @@ fn simple_computation() {
     println!("##### Simple Computation #####");
     let x = Tensor::new([2.0; 3]);
     let y = Tensor::new_with_grad([1., -2., 1.]);
-    let y_cloned = y.clone();
-    let z = Tensor::new_with_grad(vec![-3., 1., 3.]);
-    let z_cloned = z.clone();
+    let z: t!(f64, (3)) = Tensor::new_with_grad(vec![-3., 1., 3.]);
 
-    let x_plus_yy = x + y.clone() + y;
+    let x_plus_yy = x + &y + &y;
     println!("x_plus_yy = {:?}", x_plus_yy);
     let zrelu = z.relu();
     println!("zrelu = {:?}", zrelu);
@@ -43,8 +42,8 @@ fn simple_computation() {
 
     // sum[(x + y + y) * z.relu())
     // sum[([2, 2, 2] + [1, -2, 1] + [1, -2, 1]) * [-3, 1, 3].relu()]
-    println!("z_grad = {:?}", z_cloned.grad_to_string());
-    println!("y_grad = {:?}", y_cloned.grad_to_string());
+    println!("z_grad = {:?}", z.grad_to_string());
+    println!("y_grad = {:?}", y.grad_to_string());
     println!("##############################\n\n");
 }
 
@@ -61,7 +60,7 @@ fn shapes() {
     let x: Tensor<_, s!(2, 2, 12)> = x.reshape();
     let x: Tensor<_, s!(12, 4)> = x.reshape();
     let y = Tensor::new([[1; 7]; 4]);
-    let m: t!(i32, (12, 7)) = x.matmul(y);
+    let _m: t!(i32, (12, 7)) = x.matmul(y);
 }
 
 build_mod! {Model inputs=[x: t!(f64, (4, 3)), y: t!(f64, (4,7))], outputs=[loss: t!(f64, (1))]}
@@ -69,20 +68,19 @@ build_mod! {Model inputs=[x: t!(f64, (4, 3)), y: t!(f64, (4,7))], outputs=[loss:
 fn simple_training() {
     println!("##### Simple Training #####");
     let x = Tensor::new([[1.0; 3]; 4]);
-    let x_clone = x.clone();
+    let x_clone = x.shallow_clone();
 
     let rng = rand::thread_rng();
     let y: Tensor<f64, s!(4, 7)> = randn(1.0, 1.0, rng);
     println!("Random Tensor {:?}", y);
-    let y_clone = y.clone();
+    let y_clone = y.shallow_clone();
 
     let w = Tensor::new_with_grad([[0.5; 7]; 3]);
-    let w_clone = w.clone();
+    let w_clone = w.shallow_clone();
     let y_hat = x.matmul(w);
-    let diff = y - y_hat;
-    let loss = (diff.clone() * diff.clone()).reduce_sum();
+    let loss = mse(y_hat, y, Sum);
 
-    let traced_model = Model::new(x_clone.clone(), y_clone, loss.clone());
+    let traced_model = Model::new(x_clone.shallow_clone(), y_clone, loss.clone());
     let mut opt = GradientDescent { lr: 0.01 };
 
     for i in 1..10 {
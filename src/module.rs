@@ -6,3 +6,12 @@ pub trait Module {
     fn forward(i: Self::Input) -> Self::Output;
     fn consume_grad<Opt: Optimizer>(optim: &mut Opt);
 }
+
+/// Whether layers which behave differently during training, like batch norm, are training or
+/// being evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    Train,
+    Eval,
+}
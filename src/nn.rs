@@ -0,0 +1,338 @@
+//! Layers which own their learnable params. Params are passed to ops as shallow clones, so the
+//! updates made by `consume_grad` are seen when a traced graph is recomputed.
+use crate::dtype::FloatDtype;
+use crate::module::Mode;
+use crate::ops::norm::NormStats;
+use crate::ops::vec::float_const;
+use crate::optim::Optimizer;
+use crate::shape::{ChannelDim, Dims, LastDim, I};
+use crate::tensor::Tensor;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+const DEFAULT_EPS: f64 = 1e-5;
+
+fn ones<T: FloatDtype, const N: usize>() -> Tensor<T, (I<N>,)> {
+    Tensor::new(vec![T::one(); N])
+}
+
+fn zeros<T: FloatDtype, const N: usize>() -> Tensor<T, (I<N>,)> {
+    Tensor::new(vec![T::zero(); N])
+}
+
+/// Normalises over the last axis, of size `D`, with a learnable per feature scale and shift.
+#[derive(Debug)]
+pub struct LayerNorm<T: FloatDtype, const D: usize> {
+    pub weight: Tensor<T, (I<D>,)>,
+    pub bias: Tensor<T, (I<D>,)>,
+    pub eps: T,
+}
+
+impl<T: FloatDtype, const D: usize> LayerNorm<T, D> {
+    pub fn new() -> Self {
+        Self {
+            weight: Tensor::new_with_grad(ones()),
+            bias: Tensor::new_with_grad(zeros()),
+            eps: float_const(DEFAULT_EPS),
+        }
+    }
+
+    pub fn forward<S: LastDim<D>>(&self, x: Tensor<T, S>) -> Tensor<T, S> {
+        x.layer_norm(
+            self.weight.shallow_clone(),
+            self.bias.shallow_clone(),
+            self.eps,
+        )
+    }
+
+    pub fn consume_grad<Opt: Optimizer>(&self, optim: &mut Opt) {
+        self.weight.consume_grad(optim);
+        self.bias.consume_grad(optim);
+    }
+}
+
+impl<T: FloatDtype, const D: usize> Default for LayerNorm<T, D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Divides by the root mean square over the last axis, of size `D`, with a learnable per
+/// feature scale.
+#[derive(Debug)]
+pub struct RMSNorm<T: FloatDtype, const D: usize> {
+    pub weight: Tensor<T, (I<D>,)>,
+    pub eps: T,
+}
+
+impl<T: FloatDtype, const D: usize> RMSNorm<T, D> {
+    pub fn new() -> Self {
+        Self {
+            weight: Tensor::new_with_grad(ones()),
+            eps: float_const(DEFAULT_EPS),
+        }
+    }
+
+    pub fn forward<S: LastDim<D>>(&self, x: Tensor<T, S>) -> Tensor<T, S> {
+        x.rms_norm(self.weight.shallow_clone(), self.eps)
+    }
+
+    pub fn consume_grad<Opt: Optimizer>(&self, optim: &mut Opt) {
+        self.weight.consume_grad(optim);
+    }
+}
+
+impl<T: FloatDtype, const D: usize> Default for RMSNorm<T, D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Normalises `groups` groups of the `C` channels of each sample of `(N, C, ...)` inputs, with a
+/// learnable per channel scale and shift.
+#[derive(Debug)]
+pub struct GroupNorm<T: FloatDtype, const C: usize> {
+    pub groups: usize,
+    pub weight: Tensor<T, (I<C>,)>,
+    pub bias: Tensor<T, (I<C>,)>,
+    pub eps: T,
+}
+
+impl<T: FloatDtype, const C: usize> GroupNorm<T, C> {
+    /// Panics if `groups` doesn't divide `C`.
+    pub fn new(groups: usize) -> Self {
+        assert!(
+            groups > 0 && C.is_multiple_of(groups),
+            "{} channels cannot be split into {} groups",
+            C,
+            groups
+        );
+        Self {
+            groups,
+            weight: Tensor::new_with_grad(ones()),
+            bias: Tensor::new_with_grad(zeros()),
+            eps: float_const(DEFAULT_EPS),
+        }
+    }
+
+    pub fn forward<S: ChannelDim<C>>(&self, x: Tensor<T, S>) -> Tensor<T, S> {
+        x.group_norm(
+            self.groups,
+            self.weight.shallow_clone(),
+            self.bias.shallow_clone(),
+            self.eps,
+        )
+    }
+
+    pub fn consume_grad<Opt: Optimizer>(&self, optim: &mut Opt) {
+        self.weight.consume_grad(optim);
+        self.bias.consume_grad(optim);
+    }
+}
+
+/// Normalises each of the `C` channels of `(N, C, ...)` inputs, with a learnable per channel
+/// scale and shift. In train mode it uses the stats of the batch and folds them into running
+/// stats, which it uses instead in eval mode. The mode is shared with the graphs built by
+/// `forward`, so `eval` also applies when a traced graph is recomputed.
+#[derive(Debug)]
+pub struct BatchNorm<T: FloatDtype, const C: usize> {
+    pub weight: Tensor<T, (I<C>,)>,
+    pub bias: Tensor<T, (I<C>,)>,
+    pub running_mean: Tensor<T, (I<C>,)>,
+    pub running_var: Tensor<T, (I<C>,)>,
+    pub momentum: T,
+    pub eps: T,
+    mode: Rc<Cell<Mode>>,
+}
+
+impl<T: FloatDtype, const C: usize> BatchNorm<T, C> {
+    pub fn new() -> Self {
+        Self {
+            weight: Tensor::new_with_grad(ones()),
+            bias: Tensor::new_with_grad(zeros()),
+            running_mean: zeros(),
+            running_var: ones(),
+            momentum: float_const(0.1),
+            eps: float_const(DEFAULT_EPS),
+            mode: Default::default(),
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode.get()
+    }
+
+    pub fn train(&mut self) {
+        self.mode.set(Mode::Train);
+    }
+
+    pub fn eval(&mut self) {
+        self.mode.set(Mode::Eval);
+    }
+
+    /// In train mode, each forward pass (and each recompute of a graph containing it) updates
+    /// the running stats.
+    pub fn forward<S: ChannelDim<C>>(&self, x: Tensor<T, S>) -> Tensor<T, S> {
+        let stats = NormStats::Running {
+            running_mean: self.running_mean.shallow_clone(),
+            running_var: self.running_var.shallow_clone(),
+            momentum: self.momentum,
+            mode: Rc::clone(&self.mode),
+        };
+        x.batch_norm_with_stats(
+            self.weight.shallow_clone(),
+            self.bias.shallow_clone(),
+            self.eps,
+            stats,
+        )
+    }
+
+    pub fn consume_grad<Opt: Optimizer>(&self, optim: &mut Opt) {
+        self.weight.consume_grad(optim);
+        self.bias.consume_grad(optim);
+    }
+}
+
+impl<T: FloatDtype, const C: usize> Default for BatchNorm<T, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Zeroes elements with probability `p` and scales the rest by `1 / (1 - p)` in train mode, and
+/// is the identity in eval mode. Masks are drawn from an rng seeded on creation, so runs are
+/// reproducible from the seed. Like `BatchNorm`, the mode is shared with the graphs built by
+/// `forward`.
+#[derive(Debug)]
+pub struct Dropout {
+    pub p: f64,
+    mode: Rc<Cell<Mode>>,
+    rng: RefCell<StdRng>,
+}
+
+impl Dropout {
+    /// Panics unless `0 <= p <= 1`.
+    pub fn new(p: f64, seed: u64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&p),
+            "Dropout probability {} is not between 0 and 1",
+            p
+        );
+        Self {
+            p,
+            mode: Default::default(),
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode.get()
+    }
+
+    pub fn train(&mut self) {
+        self.mode.set(Mode::Train);
+    }
+
+    pub fn eval(&mut self) {
+        self.mode.set(Mode::Eval);
+    }
+
+    pub fn forward<T: FloatDtype, S: Dims>(&self, x: Tensor<T, S>) -> Tensor<T, S> {
+        x.dropout_with_mode(self.p, &mut *self.rng.borrow_mut(), Rc::clone(&self.mode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optim::GradientDescent;
+
+    #[test]
+    fn test_batch_norm_modes() {
+        let mut bn = BatchNorm::<f64, 2>::new();
+        let x: Tensor<f64, (I<2>, I<2>)> = Tensor::new([[1.0, 10.0], [3.0, 30.0]]);
+        let y = bn.forward(x.clone());
+        for (a, b) in y.borrow_value().iter().zip([-1.0, -1.0, 1.0, 1.0]) {
+            assert!((a - b).abs() < 1e-4);
+        }
+        // The running variance is unbiased, so 2 and 200 for the two channels
+        let expected_mean = [0.2, 2.0];
+        let expected_var = [0.9 + 0.2, 0.9 + 20.0];
+        for (a, b) in bn.running_mean.borrow_value().iter().zip(expected_mean) {
+            assert!((a - b).abs() < 1e-12);
+        }
+        for (a, b) in bn.running_var.borrow_value().iter().zip(expected_var) {
+            assert!((a - b).abs() < 1e-12);
+        }
+
+        bn.eval();
+        let y = bn.forward(x);
+        let expected = (1.0 - 0.2) / (1.1 + 1e-5f64).sqrt();
+        assert!((y.borrow_value()[0] - expected).abs() < 1e-12);
+        assert_eq!(bn.running_mean.borrow_value()[0], 0.2);
+    }
+
+    #[test]
+    fn test_traced_batch_norm_eval() {
+        crate::build_mod! {Model inputs=[x: crate::t!(f64, (2, 2))], outputs=[y: crate::t!(f64, (2, 2))]}
+        let mut bn = BatchNorm::<f64, 2>::new();
+        let x: Tensor<f64, (I<2>, I<2>)> = Tensor::new([[1.0, 10.0], [3.0, 30.0]]);
+        let model = Model::new(x.shallow_clone(), bn.forward(x));
+        let running_mean = bn.running_mean.borrow_value().to_vec();
+
+        bn.eval();
+        model.recompute(vec![5.0, 50.0, 7.0, 70.0]);
+        assert_eq!(*bn.running_mean.borrow_value(), running_mean);
+        // Normalised with the running stats rather than the stats of the batch
+        let expected = (5.0 - 0.2) / (1.1 + 1e-5f64).sqrt();
+        assert!((model.y.borrow_value()[0] - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_norm_consume_grad() {
+        let ln = LayerNorm::<f64, 2>::new();
+        let x: Tensor<f64, (I<1>, I<2>)> = Tensor::new([[1.0, 3.0]]);
+        let y = ln.forward(x);
+        (y * Tensor::new([[1.0, 2.0]])).reduce_sum().backward();
+        ln.consume_grad(&mut GradientDescent { lr: 1.0 });
+        assert_eq!(*ln.bias.borrow_value(), [-1.0, -2.0]);
+    }
+
+    #[test]
+    fn test_dropout_modes() {
+        let x = || Tensor::<f64, (I<32>,)>::new(vec![1.0; 32]);
+        let mut dropout = Dropout::new(0.5, 11);
+        let a = dropout.forward(x()).borrow_value().to_vec();
+        let b = dropout.forward(x()).borrow_value().to_vec();
+        // Each forward pass draws a new mask, but the sequence of masks follows from the seed
+        assert_ne!(a, b);
+        assert_eq!(
+            Dropout::new(0.5, 11).forward(x()).borrow_value().to_vec(),
+            a
+        );
+
+        dropout.eval();
+        assert_eq!(*dropout.forward(x()).borrow_value(), [1.0; 32]);
+    }
+
+    #[test]
+    fn test_traced_dropout_eval() {
+        crate::build_mod! {Model inputs=[x: crate::t!(f64, (32))], outputs=[y: crate::t!(f64, (32))]}
+        let mut dropout = Dropout::new(0.5, 5);
+        let x: Tensor<f64, (I<32>,)> = Tensor::new(vec![1.0; 32]);
+        let model = Model::new(x.shallow_clone(), dropout.forward(x));
+        assert!(model.y.borrow_value().contains(&0.0));
+
+        dropout.eval();
+        model.recompute(vec![3.0; 32]);
+        assert_eq!(*model.y.borrow_value(), [3.0; 32]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Dropout probability 1.5 is not between 0 and 1")]
+    fn test_dropout_checks_p() {
+        Dropout::new(1.5, 0);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::ops::float::impl_float_unary_op;
+use crate::ops::grad::{
+    el_elu_grad, el_gelu_grad, el_gelu_tanh_grad, el_hardtanh_grad, el_leaky_relu_grad,
+    el_mish_grad, el_selu_grad, el_sigmoid_grad, el_silu_grad, el_softplus_grad,
+};
+use crate::ops::vec::{
+    el_elu, el_gelu, el_gelu_tanh, el_hardtanh, el_leaky_relu, el_mish, el_mul, el_selu,
+    el_sigmoid, el_silu, el_softplus,
+};
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{dtype::FloatDtype, ops::Op, shape::Dims, tensor::Tensor};
+use std::borrow::Cow;
+use std::rc::Rc;
+
+impl_float_unary_op!(ElSigmoidStruct, el_sigmoid, el_sigmoid_grad);
+impl_float_unary_op!(ElGeluStruct, el_gelu, el_gelu_grad);
+impl_float_unary_op!(ElGeluTanhStruct, el_gelu_tanh, el_gelu_tanh_grad);
+impl_float_unary_op!(ElSiluStruct, el_silu, el_silu_grad);
+impl_float_unary_op!(ElLeakyReLUStruct, T, el_leaky_relu, el_leaky_relu_grad);
+impl_float_unary_op!(ElEluStruct, T, el_elu, el_elu_grad);
+impl_float_unary_op!(ElSeluStruct, el_selu, el_selu_grad);
+impl_float_unary_op!(ElSoftplusStruct, el_softplus, el_softplus_grad);
+impl_float_unary_op!(ElHardtanhStruct, (T, T), el_hardtanh, el_hardtanh_grad);
+impl_float_unary_op!(ElMishStruct, el_mish, el_mish_grad);
+
+impl<T: FloatDtype, S: Dims> Tensor<T, S> {
+    /// `1 / (1 + e^-x)`.
+    pub fn sigmoid(self) -> Self {
+        ElSigmoidStruct(self).forward()
+    }
+
+    /// `x * Phi(x)`, where `Phi` is the standard normal CDF.
+    pub fn gelu(self) -> Self {
+        ElGeluStruct(self).forward()
+    }
+
+    /// The tanh approximation of `gelu`.
+    pub fn gelu_tanh(self) -> Self {
+        ElGeluTanhStruct(self).forward()
+    }
+
+    /// `x * sigmoid(x)`, also known as swish.
+    pub fn silu(self) -> Self {
+        ElSiluStruct(self).forward()
+    }
+
+    /// `x` for `x > 0`, else `alpha * x`.
+    pub fn leaky_relu(self, alpha: T) -> Self {
+        ElLeakyReLUStruct(self, alpha).forward()
+    }
+
+    /// `x` for `x > 0`, else `alpha * (e^x - 1)`.
+    pub fn elu(self, alpha: T) -> Self {
+        ElEluStruct(self, alpha).forward()
+    }
+
+    /// `elu` with the fixed scale and alpha of the self-normalizing network paper.
+    pub fn selu(self) -> Self {
+        ElSeluStruct(self).forward()
+    }
+
+    /// `ln(1 + e^x)`, computed without overflow for large `x`.
+    pub fn softplus(self) -> Self {
+        ElSoftplusStruct(self).forward()
+    }
+
+    /// Clamp to `[min, max]`.
+    pub fn hardtanh(self, min: T, max: T) -> Self {
+        assert!(min <= max, "hardtanh min must not be greater than max");
+        ElHardtanhStruct(self, (min, max)).forward()
+    }
+
+    /// `x * tanh(softplus(x))`.
+    pub fn mish(self) -> Self {
+        ElMishStruct(self).forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+    use crate::test_util::{assert_close, check_grad};
+
+    #[test]
+    fn test_activations() {
+        let x = Tensor::new([-2.0, 0.0, 3.0]);
+        assert_close(
+            &x.clone().sigmoid().borrow_value(),
+            &[0.119_202_922, 0.5, 0.952_574_127],
+        );
+        assert_close(
+            &x.clone().gelu().borrow_value(),
+            &[-0.045_500_264, 0.0, 2.995_950_004],
+        );
+        assert_close(
+            &x.clone().gelu_tanh().borrow_value(),
+            &[-0.045_402_306, 0.0, 2.996_362_743],
+        );
+        assert_close(&x.clone().leaky_relu(0.1).borrow_value(), &[-0.2, 0.0, 3.0]);
+        assert_close(
+            &x.clone().hardtanh(-1.0, 1.0).borrow_value(),
+            &[-1.0, 0.0, 1.0],
+        );
+        assert_close(
+            &x.clone().selu().borrow_value(),
+            &[-1.520_166_468, 0.0, 3.152_102_962],
+        );
+        assert_close(
+            &x.mish().borrow_value(),
+            &[-0.252_501_113, 0.0, 2.986_535_3],
+        );
+
+        // No overflow for large inputs
+        let x = Tensor::new([-1000.0, 1000.0]);
+        assert_close(&x.clone().sigmoid().borrow_value(), &[0.0, 1.0]);
+        assert_close(&x.softplus().borrow_value(), &[0.0, 1000.0]);
+    }
+
+    #[test]
+    fn test_relu_grad_at_zero() {
+        let x = Tensor::new_with_grad([-1.0, 0.0, 1.0]);
+        x.clone().relu().reduce_sum().backward();
+        assert_eq!(x.borrow_grad().as_deref(), Some(&[0.0, 0.0, 1.0][..]));
+    }
+
+    #[test]
+    fn test_activation_grads() {
+        // Away from the kinks at 0 and +-1
+        type T4 = Tensor<f64, (I<4>,)>;
+        let ops: [fn(T4) -> T4; 11] = [
+            |t| t.sigmoid(),
+            |t| t.gelu(),
+            |t| t.gelu_tanh(),
+            |t| t.silu(),
+            |t| t.leaky_relu(0.1),
+            |t| t.elu(0.8),
+            |t| t.selu(),
+            |t| t.softplus(),
+            |t| t.hardtanh(-1.0, 1.0),
+            |t| t.mish(),
+            |t| t.tanh(),
+        ];
+        for op in ops {
+            check_grad(op, &[-1.3, -0.4, 0.6, 1.9]);
+        }
+    }
+}
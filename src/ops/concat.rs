@@ -0,0 +1,266 @@
+use crate::ops::index::NarrowStruct;
+use crate::ops::vec::{concat, split_parts};
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{
+    dtype::NumDtype,
+    ops::Op,
+    shape::{Chunk, Concat, InsertAxis, Shape, SplitAt},
+    tensor::Tensor,
+};
+use std::rc::Rc;
+
+pub trait Concatenates<T: NumDtype, S1: Shape, S2: Shape> {
+    /// Concatenate `self` and `other` along `AXIS`, e.g. `a.concat::<1>(b)`.
+    fn concat<const AXIS: usize>(
+        self,
+        other: Tensor<T, S2>,
+    ) -> Tensor<T, <S1 as Concat<S2, AXIS>>::Output>
+    where
+        S1: Concat<S2, AXIS>;
+}
+
+pub trait Stacks<T: NumDtype, S: Shape, const N: usize> {
+    /// Stack `N` tensors along a new axis inserted at `AXIS`, e.g. `[a, b, c].stack::<0>()`.
+    fn stack<const AXIS: usize>(self) -> Tensor<T, <S as InsertAxis<AXIS, N>>::Output>
+    where
+        S: InsertAxis<AXIS, N>;
+}
+
+#[derive(Debug)]
+pub struct ConcatStruct<T: NumDtype, S1: Shape, S2: Shape, const AXIS: usize>(
+    Tensor<T, S1>,
+    Tensor<T, S2>,
+);
+
+#[derive(Debug)]
+pub struct StackStruct<T: NumDtype, S: Shape, const N: usize, const AXIS: usize>([Tensor<T, S>; N]);
+
+// Concat
+impl<T: NumDtype, S1: Shape, S2: Shape, const AXIS: usize> ConcatStruct<T, S1, S2, AXIS> {
+    fn outer() -> usize {
+        S1::shape()[..AXIS].iter().product()
+    }
+
+    fn compute(&self) -> Vec<T> {
+        concat(
+            &[&self.0.borrow_value(), &self.1.borrow_value()],
+            Self::outer(),
+        )
+    }
+}
+
+impl<T: NumDtype, S1: Shape, S2: Shape, const AXIS: usize> Op for ConcatStruct<T, S1, S2, AXIS>
+where
+    S1: Concat<S2, AXIS>,
+{
+    type Produces = Tensor<T, <S1 as Concat<S2, AXIS>>::Output>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = concat(a, b, axis)
+        // d_da, d_db = split(d_dt, axis)
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let mut parts = split_parts(d_dt, Self::outer(), &[S1::NUM_ELS, S2::NUM_ELS]);
+            let d_db = parts.pop().unwrap();
+            let d_da = parts.pop().unwrap();
+            self.0.update_grad(d_da);
+            self.1.update_grad(d_db);
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let data = TensorData::new(
+            self.compute(),
+            self.0.requires_grad() || self.1.requires_grad(),
+        );
+        unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![
+            TensorBox::new(self.0.id, &self.0),
+            TensorBox::new(self.1.id, &self.1),
+        ]
+    }
+}
+
+impl<T: NumDtype, S1: Shape, S2: Shape> Concatenates<T, S1, S2> for Tensor<T, S1> {
+    fn concat<const AXIS: usize>(
+        self,
+        other: Tensor<T, S2>,
+    ) -> Tensor<T, <S1 as Concat<S2, AXIS>>::Output>
+    where
+        S1: Concat<S2, AXIS>,
+    {
+        ConcatStruct::<T, S1, S2, AXIS>(self, other).forward()
+    }
+}
+
+// Stack
+impl<T: NumDtype, S: Shape, const N: usize, const AXIS: usize> StackStruct<T, S, N, AXIS> {
+    fn outer() -> usize {
+        S::shape()[..AXIS].iter().product()
+    }
+
+    fn compute(&self) -> Vec<T> {
+        let values: Vec<_> = self.0.iter().map(|t| t.borrow_value()).collect();
+        let parts: Vec<&[T]> = values.iter().map(|v| &v[..]).collect();
+        concat(&parts, Self::outer())
+    }
+}
+
+impl<T: NumDtype, S: Shape, const N: usize, const AXIS: usize> Op for StackStruct<T, S, N, AXIS>
+where
+    S: InsertAxis<AXIS, N>,
+{
+    type Produces = Tensor<T, <S as InsertAxis<AXIS, N>>::Output>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = stack([a_0, .., a_n], axis)
+        // d_da_i = d_dt[.., i, ..]
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let parts = split_parts(d_dt, Self::outer(), &[S::NUM_ELS; N]);
+            for (a, d_da) in self.0.iter().zip(parts) {
+                a.update_grad(d_da);
+            }
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let requires_grad = self.0.iter().any(|t| t.requires_grad());
+        let data = TensorData::new(self.compute(), requires_grad);
+        unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        self.0
+            .iter()
+            .map(|t| TensorBox::new(t.id, t as &dyn TensorTrait))
+            .collect()
+    }
+}
+
+impl<T: NumDtype, S: Shape, const N: usize> Stacks<T, S, N> for [Tensor<T, S>; N] {
+    fn stack<const AXIS: usize>(self) -> Tensor<T, <S as InsertAxis<AXIS, N>>::Output>
+    where
+        S: InsertAxis<AXIS, N>,
+    {
+        StackStruct::<T, S, N, AXIS>(self).forward()
+    }
+}
+
+// Split and chunk are built from narrows of the same tensor, so their grads accumulate back into
+// the source through `NarrowStruct`.
+impl<T: NumDtype, S: Shape> Tensor<T, S> {
+    /// Split along `AXIS` into `..AT` and `AT..`.
+    #[allow(clippy::type_complexity)]
+    pub fn split<const AXIS: usize, const AT: usize>(
+        self,
+    ) -> (
+        Tensor<T, <S as SplitAt<AXIS, AT>>::Left>,
+        Tensor<T, <S as SplitAt<AXIS, AT>>::Right>,
+    )
+    where
+        S: SplitAt<AXIS, AT>,
+    {
+        let len = S::shape()[AXIS] - AT;
+        (
+            NarrowStruct::new(self.shallow_clone(), AXIS, 0, AT).forward(),
+            NarrowStruct::new(self, AXIS, AT, len).forward(),
+        )
+    }
+
+    /// Split along `AXIS` into `N` equally sized chunks.
+    pub fn chunk<const AXIS: usize, const N: usize>(
+        self,
+    ) -> [Tensor<T, <S as Chunk<AXIS, N>>::Output>; N]
+    where
+        S: Chunk<AXIS, N>,
+    {
+        let len = S::shape()[AXIS] / N;
+        std::array::from_fn(|i| {
+            NarrowStruct::new(self.shallow_clone(), AXIS, i * len, len).forward()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Concatenates, Stacks};
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_concat_grad() {
+        let a = Tensor::new_with_grad([[1.0, 2.0], [3.0, 4.0]]);
+        let b = Tensor::new_with_grad([[5.0], [6.0]]);
+        let c: Tensor<f64, (I<2>, I<3>)> = a.clone().concat::<1>(b.clone());
+        assert_eq!(*c.borrow_value(), [1.0, 2.0, 5.0, 3.0, 4.0, 6.0]);
+
+        let w = Tensor::new([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        (c * w).reduce_sum().backward();
+        assert_eq!(a.borrow_grad().as_deref(), Some(&[1.0, 2.0, 4.0, 5.0][..]));
+        assert_eq!(b.borrow_grad().as_deref(), Some(&[3.0, 6.0][..]));
+    }
+
+    #[test]
+    fn test_stack_grad() {
+        let a = Tensor::new_with_grad([1.0, 2.0]);
+        let b = Tensor::new_with_grad([3.0, 4.0]);
+        let s: Tensor<f64, (I<2>, I<2>)> = [a.clone(), b.clone()].stack::<1>();
+        assert_eq!(*s.borrow_value(), [1.0, 3.0, 2.0, 4.0]);
+
+        (s * Tensor::new([[1.0, 2.0], [3.0, 4.0]]))
+            .reduce_sum()
+            .backward();
+        assert_eq!(a.borrow_grad().as_deref(), Some(&[1.0, 3.0][..]));
+        assert_eq!(b.borrow_grad().as_deref(), Some(&[2.0, 4.0][..]));
+    }
+
+    #[test]
+    fn test_split_and_chunk() {
+        let x = Tensor::new_with_grad([[0.0, 1.0, 2.0, 3.0], [4.0, 5.0, 6.0, 7.0]]);
+        let (l, r) = x.clone().split::<1, 1>();
+        let r: Tensor<f64, (I<2>, I<3>)> = r;
+        assert_eq!(*l.borrow_value(), [0.0, 4.0]);
+        assert_eq!(*r.borrow_value(), [1.0, 2.0, 3.0, 5.0, 6.0, 7.0]);
+
+        let [c0, c1] = x.clone().chunk::<1, 2>();
+        assert_eq!(*c1.borrow_value(), [2.0, 3.0, 6.0, 7.0]);
+        (c0 - c1).reduce_sum().backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0, -1.0][..])
+        );
+    }
+
+    #[test]
+    fn test_nchw_axis_ops() {
+        // shape = (1, 2, 1, 2)
+        let x = Tensor::new_with_grad([[[[1.0, 2.0]], [[3.0, 4.0]]]]);
+        let c: Tensor<f64, (I<1>, I<4>, I<1>, I<2>)> = x.clone().concat::<1>(x.clone());
+        let [a, b] = c.chunk::<1, 2>();
+        let (l, r) = a.split::<3, 1>();
+        assert_eq!(*l.borrow_value(), [1.0, 3.0]);
+        assert_eq!(*r.borrow_value(), [2.0, 4.0]);
+
+        let s: Tensor<f64, (I<1>, I<1>, I<2>)> = b.narrow::<1, 1, 1>().squeeze::<2>();
+        assert_eq!(*s.borrow_value(), [3.0, 4.0]);
+        let u: Tensor<f64, (I<1>, I<1>, I<1>, I<2>)> = s.unsqueeze::<0>();
+        let t: Tensor<f64, (I<1>, I<1>, I<2>)> = u.sum_axis::<1>();
+        t.reduce_sum().backward();
+        assert_eq!(x.borrow_grad().as_deref(), Some(&[0.0, 0.0, 1.0, 1.0][..]));
+    }
+}
@@ -0,0 +1,279 @@
+use crate::ops::grad::conv2d_grad;
+use crate::ops::vec::{conv2d, ConvGeometry};
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{
+    dtype::NumDtype,
+    ops::Op,
+    shape::{Conv1d, Conv2d, Shape},
+    tensor::Tensor,
+};
+use std::{marker::PhantomData, rc::Rc};
+
+pub trait Convolves<T: NumDtype, S: Shape, Sw: Shape> {
+    /// Convolve `(N, C, L)` inputs with `(O, C / GROUPS, K)` weights, e.g.
+    /// `x.conv1d::<1, 0, 1, 1>(w)` for stride 1, no padding, no dilation and a single group. The
+    /// output length is checked at compile time.
+    fn conv1d<
+        const STRIDE: usize,
+        const PADDING: usize,
+        const DILATION: usize,
+        const GROUPS: usize,
+    >(
+        self,
+        w: Tensor<T, Sw>,
+    ) -> Tensor<T, <S as Conv1d<Sw, STRIDE, PADDING, DILATION, GROUPS>>::Output>
+    where
+        S: Conv1d<Sw, STRIDE, PADDING, DILATION, GROUPS>;
+
+    /// Convolve `(N, C, H, W)` inputs with `(O, C / GROUPS, KH, KW)` weights, with the same
+    /// stride, padding and dilation along both spatial axes.
+    fn conv2d<
+        const STRIDE: usize,
+        const PADDING: usize,
+        const DILATION: usize,
+        const GROUPS: usize,
+    >(
+        self,
+        w: Tensor<T, Sw>,
+    ) -> Tensor<T, <S as Conv2d<Sw, STRIDE, PADDING, DILATION, GROUPS>>::Output>
+    where
+        S: Conv2d<Sw, STRIDE, PADDING, DILATION, GROUPS>;
+}
+
+/// Grouped convolution via im2col. 1d convolutions are run as 2d ones with a height of 1.
+#[derive(Debug)]
+pub struct ConvStruct<T: NumDtype, S: Shape, Sw: Shape, So: Shape> {
+    x: Tensor<T, S>,
+    w: Tensor<T, Sw>,
+    geometry: ConvGeometry,
+    _shape: PhantomData<So>,
+}
+
+impl<T: NumDtype, S: Shape, Sw: Shape, So: Shape> ConvStruct<T, S, Sw, So> {
+    fn compute(&self) -> Vec<T> {
+        conv2d(
+            &self.x.borrow_value(),
+            &self.w.borrow_value(),
+            &self.geometry,
+        )
+    }
+}
+
+impl<T: NumDtype, S: Shape, Sw: Shape, So: Shape> Op for ConvStruct<T, S, Sw, So> {
+    type Produces = Tensor<T, So>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = conv(x, w)
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let (d_dx, d_dw) = conv2d_grad(
+                &self.x.borrow_value(),
+                &self.w.borrow_value(),
+                d_dt,
+                &self.geometry,
+            );
+            self.x.update_grad(d_dx);
+            self.w.update_grad(d_dw);
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let data = TensorData::new(
+            self.compute(),
+            self.x.requires_grad() || self.w.requires_grad(),
+        );
+        unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![
+            TensorBox::new(self.x.id, &self.x),
+            TensorBox::new(self.w.id, &self.w),
+        ]
+    }
+}
+
+impl<T: NumDtype, S: Shape, Sw: Shape> Convolves<T, S, Sw> for Tensor<T, S> {
+    fn conv1d<
+        const STRIDE: usize,
+        const PADDING: usize,
+        const DILATION: usize,
+        const GROUPS: usize,
+    >(
+        self,
+        w: Tensor<T, Sw>,
+    ) -> Tensor<T, <S as Conv1d<Sw, STRIDE, PADDING, DILATION, GROUPS>>::Output>
+    where
+        S: Conv1d<Sw, STRIDE, PADDING, DILATION, GROUPS>,
+    {
+        let (x_shape, w_shape) = (S::shape(), Sw::shape());
+        let out_shape = <S as Conv1d<Sw, STRIDE, PADDING, DILATION, GROUPS>>::Output::shape();
+        let geometry = ConvGeometry {
+            batch: x_shape[0],
+            in_channels: x_shape[1],
+            out_channels: w_shape[0],
+            groups: GROUPS,
+            in_size: (1, x_shape[2]),
+            kernel: (1, w_shape[2]),
+            out_size: (1, out_shape[2]),
+            stride: (1, STRIDE),
+            padding: (0, PADDING),
+            dilation: (1, DILATION),
+        };
+        ConvStruct {
+            x: self,
+            w,
+            geometry,
+            _shape: PhantomData,
+        }
+        .forward()
+    }
+
+    fn conv2d<
+        const STRIDE: usize,
+        const PADDING: usize,
+        const DILATION: usize,
+        const GROUPS: usize,
+    >(
+        self,
+        w: Tensor<T, Sw>,
+    ) -> Tensor<T, <S as Conv2d<Sw, STRIDE, PADDING, DILATION, GROUPS>>::Output>
+    where
+        S: Conv2d<Sw, STRIDE, PADDING, DILATION, GROUPS>,
+    {
+        let (x_shape, w_shape) = (S::shape(), Sw::shape());
+        let out_shape = <S as Conv2d<Sw, STRIDE, PADDING, DILATION, GROUPS>>::Output::shape();
+        let geometry = ConvGeometry {
+            batch: x_shape[0],
+            in_channels: x_shape[1],
+            out_channels: w_shape[0],
+            groups: GROUPS,
+            in_size: (x_shape[2], x_shape[3]),
+            kernel: (w_shape[2], w_shape[3]),
+            out_size: (out_shape[2], out_shape[3]),
+            stride: (STRIDE, STRIDE),
+            padding: (PADDING, PADDING),
+            dilation: (DILATION, DILATION),
+        };
+        ConvStruct {
+            x: self,
+            w,
+            geometry,
+            _shape: PhantomData,
+        }
+        .forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Convolves;
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+    use crate::test_util::check_grad;
+
+    /// Direct convolution of a single `(C, H, W)` image, to check the im2col version against.
+    #[allow(clippy::too_many_arguments)]
+    fn naive_conv2d(
+        x: &[f64],
+        w: &[f64],
+        (c, h, wd): (usize, usize, usize),
+        (o, kh, kw): (usize, usize, usize),
+        (oh, ow): (usize, usize),
+        stride: usize,
+        padding: usize,
+        dilation: usize,
+        groups: usize,
+    ) -> Vec<f64> {
+        let (cg, og) = (c / groups, o / groups);
+        let mut out = vec![0.0; o * oh * ow];
+        for oc in 0..o {
+            let g = oc / og;
+            for (i, j) in (0..oh).flat_map(|i| (0..ow).map(move |j| (i, j))) {
+                let mut acc = 0.0;
+                for (ci, ki, kj) in (0..cg)
+                    .flat_map(|ci| (0..kh).flat_map(move |ki| (0..kw).map(move |kj| (ci, ki, kj))))
+                {
+                    let y = (i * stride + ki * dilation) as isize - padding as isize;
+                    let z = (j * stride + kj * dilation) as isize - padding as isize;
+                    if y >= 0 && z >= 0 && (y as usize) < h && (z as usize) < wd {
+                        let xi = ((g * cg + ci) * h + y as usize) * wd + z as usize;
+                        acc += x[xi] * w[((oc * cg + ci) * kh + ki) * kw + kj];
+                    }
+                }
+                out[(oc * oh + i) * ow + j] = acc;
+            }
+        }
+        out
+    }
+
+    fn values<const N: usize>(seed: f64) -> Vec<f64> {
+        (0..N).map(|i| ((i as f64 + seed) * 0.37).sin()).collect()
+    }
+
+    #[test]
+    fn test_conv2d() {
+        let x: Tensor<f64, (I<2>, I<4>, I<5>, I<5>)> = Tensor::new(values::<200>(0.0));
+        let w: Tensor<f64, (I<6>, I<2>, I<3>, I<3>)> = Tensor::new(values::<108>(1.0));
+        // Stride 2, padding 1, dilation 1, 2 groups
+        let y: Tensor<f64, (I<2>, I<6>, I<3>, I<3>)> = x.clone().conv2d::<2, 1, 1, 2>(w.clone());
+        // Stride 1, padding 2, dilation 2, 1 group
+        let w1: Tensor<f64, (I<3>, I<4>, I<3>, I<3>)> = Tensor::new(values::<108>(2.0));
+        let z: Tensor<f64, (I<2>, I<3>, I<5>, I<5>)> = x.clone().conv2d::<1, 2, 2, 1>(w1.clone());
+
+        let (x, w, w1) = (x.borrow_value(), w.borrow_value(), w1.borrow_value());
+        let (y, z) = (y.borrow_value(), z.borrow_value());
+        for n in 0..2 {
+            let img = &x[n * 100..(n + 1) * 100];
+            let expected = naive_conv2d(img, &w, (4, 5, 5), (6, 3, 3), (3, 3), 2, 1, 1, 2);
+            let out = &y[n * 54..(n + 1) * 54];
+            assert!(out
+                .iter()
+                .zip(&expected)
+                .all(|(a, b)| (a - b).abs() < 1e-12));
+
+            let expected = naive_conv2d(img, &w1, (4, 5, 5), (3, 3, 3), (5, 5), 1, 2, 2, 1);
+            let out = &z[n * 75..(n + 1) * 75];
+            assert!(out
+                .iter()
+                .zip(&expected)
+                .all(|(a, b)| (a - b).abs() < 1e-12));
+        }
+    }
+
+    #[test]
+    fn test_conv1d() {
+        let x = Tensor::new([[[1.0, 2.0, 3.0, 4.0, 5.0]]]);
+        let w = Tensor::new([[[1.0, 0.0, -1.0]], [[1.0, 1.0, 1.0]]]);
+        let y: Tensor<f64, (I<1>, I<2>, I<3>)> = x.clone().conv1d::<2, 1, 1, 1>(w);
+        assert_eq!(*y.borrow_value(), [-2.0, -2.0, 4.0, 3.0, 9.0, 9.0]);
+    }
+
+    #[test]
+    fn test_conv_grads() {
+        // With respect to both the input and the weights
+        type X = Tensor<f64, (I<1>, I<4>, I<4>, I<4>)>;
+        type W = Tensor<f64, (I<4>, I<2>, I<2>, I<2>)>;
+        let (xv, wv) = (values::<64>(0.5), values::<32>(1.5));
+        check_grad(|x: X| x.conv2d::<2, 1, 2, 2>(W::new(wv.clone())), &xv);
+        check_grad(|w: W| X::new(xv.clone()).conv2d::<2, 1, 2, 2>(w), &wv);
+    }
+
+    #[test]
+    fn test_conv_bias() {
+        let x = Tensor::new([[[[1.0, 2.0], [3.0, 4.0]]]]);
+        let w = Tensor::new([[[[1.0]]], [[[2.0]]]]);
+        let b: Tensor<f64, (I<2>, I<1>, I<1>)> = Tensor::new(vec![10.0, 20.0]);
+        let y = x.conv2d::<1, 0, 1, 1>(w) + b;
+        assert_eq!(
+            *y.borrow_value(),
+            [11.0, 12.0, 13.0, 14.0, 22.0, 24.0, 26.0, 28.0]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::ops::vec::el_mul;
+use crate::random::dropout_mask;
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{dtype::FloatDtype, module::Mode, ops::Op, shape::Dims, tensor::Tensor};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Zeroes elements with probability `p` and scales the rest by `1 / (1 - p)`. The op owns an rng
+/// seeded from the one it was created with, so each recompute samples a new mask while runs stay
+/// reproducible from the original seed. Backward uses the mask of the current value. `mode` is
+/// read on each compute, and in eval mode the op is the identity.
+#[derive(Debug)]
+pub struct DropoutStruct<T: FloatDtype, S: Dims> {
+    a: Tensor<T, S>,
+    p: f64,
+    rng: RefCell<StdRng>,
+    mask: RefCell<Vec<T>>,
+    mode: Rc<Cell<Mode>>,
+}
+
+impl<T: FloatDtype, S: Dims> DropoutStruct<T, S> {
+    fn compute(&self) -> Vec<T> {
+        let a = self.a.borrow_value();
+        let mask = match self.mode.get() {
+            Mode::Train => dropout_mask(a.len(), self.p, &mut *self.rng.borrow_mut()),
+            Mode::Eval => vec![T::one(); a.len()],
+        };
+        let value = el_mul(&a, &mask);
+        *self.mask.borrow_mut() = mask;
+        value
+    }
+}
+
+impl<T: FloatDtype, S: Dims> Op for DropoutStruct<T, S> {
+    type Produces = Tensor<T, S>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = a * mask
+        // d_da = d_dt * mask
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let d_da = el_mul(d_dt, &self.mask.borrow());
+            self.a.update_grad(d_da);
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let data = TensorData::new(self.compute(), self.a.requires_grad());
+        let shape = self.a.shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.a.id, &self.a)]
+    }
+}
+
+impl<T: FloatDtype, S: Dims> Tensor<T, S> {
+    /// Zero each element with probability `p` and scale the rest by `1 / (1 - p)`, with a mask
+    /// sampled from `rng`. Panics unless `0 <= p <= 1`. See `nn::Dropout` for a layer which is
+    /// the identity in eval mode.
+    pub fn dropout<R: Rng>(self, p: f64, rng: R) -> Self {
+        self.dropout_with_mode(p, rng, Default::default())
+    }
+
+    pub(crate) fn dropout_with_mode<R: Rng>(
+        self,
+        p: f64,
+        mut rng: R,
+        mode: Rc<Cell<Mode>>,
+    ) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&p),
+            "Dropout probability {} is not between 0 and 1",
+            p
+        );
+        DropoutStruct {
+            a: self,
+            p,
+            rng: RefCell::new(StdRng::seed_from_u64(rng.gen())),
+            mask: RefCell::new(Vec::new()),
+            mode,
+        }
+        .forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_dropout() {
+        let x: Tensor<f64, (I<1000>,)> = Tensor::new_with_grad(vec![2.0; 1000]);
+        let y = x.clone().dropout(0.25, StdRng::seed_from_u64(7));
+        let kept = y.borrow_value().iter().filter(|v| **v != 0.0).count();
+        assert!((700..800).contains(&kept), "{} kept", kept);
+        assert!(y
+            .borrow_value()
+            .iter()
+            .all(|v| *v == 0.0 || (v - 2.0 / 0.75).abs() < 1e-12));
+
+        // The same seed gives the same mask
+        let z =
+            Tensor::<f64, (I<1000>,)>::new(vec![2.0; 1000]).dropout(0.25, StdRng::seed_from_u64(7));
+        assert_eq!(*y.borrow_value(), *z.borrow_value());
+
+        y.clone().reduce_sum().backward();
+        let grad = x.borrow_grad().unwrap().to_vec();
+        for (g, v) in grad.iter().zip(y.borrow_value().iter()) {
+            assert_eq!(*g, v / 2.0);
+        }
+    }
+
+    #[test]
+    fn test_dropout_recompute() {
+        let x: Tensor<f64, (I<64>,)> = Tensor::new_with_grad(vec![1.0; 64]);
+        let y = x.clone().dropout(0.5, StdRng::seed_from_u64(3));
+        let first = y.borrow_value().to_vec();
+        y.recompute();
+        let second = y.borrow_value().to_vec();
+        assert_ne!(first, second);
+        // Backward uses the mask of the recomputed value
+        y.clone().reduce_sum().backward();
+        assert_eq!(x.borrow_grad().unwrap().to_vec(), second);
+
+        let y = x.dropout(0.0, StdRng::seed_from_u64(3));
+        assert_eq!(*y.borrow_value(), [1.0; 64]);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::ops::grad::{
+    el_cos_grad, el_exp_grad, el_ln_grad, el_log1p_grad, el_powf_grad, el_powi_grad, el_rsqrt_grad,
+    el_sin_grad, el_sqrt_grad, el_tan_grad, el_tanh_grad,
+};
+use crate::ops::vec::{
+    el_cos, el_exp, el_ln, el_log1p, el_mul, el_powf, el_powi, el_rsqrt, el_sin, el_sqrt, el_tan,
+    el_tanh,
+};
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{dtype::FloatDtype, ops::Op, shape::Dims, tensor::Tensor};
+use std::borrow::Cow;
+use std::rc::Rc;
+
+macro_rules! impl_float_unary_op {
+    ($s:ident, $f:expr, $df:expr) => {
+        #[derive(Debug)]
+        pub struct $s<T: FloatDtype, S: Dims>(Tensor<T, S>);
+
+        impl<T: FloatDtype, S: Dims> $s<T, S> {
+            fn compute(&self, a: &[T]) -> Vec<T> {
+                $f(a)
+            }
+
+            fn grad<'a>(&self, a: &'a [T]) -> Cow<'a, [T]> {
+                $df(a)
+            }
+        }
+
+        impl_float_unary_op!(@op $s);
+    };
+    // Ops with a scalar parameter, e.g. the exponent of `powf`
+    ($s:ident, $p:ty, $f:expr, $df:expr) => {
+        #[derive(Debug)]
+        pub struct $s<T: FloatDtype, S: Dims>(Tensor<T, S>, $p);
+
+        impl<T: FloatDtype, S: Dims> $s<T, S> {
+            fn compute(&self, a: &[T]) -> Vec<T> {
+                $f(a, self.1)
+            }
+
+            fn grad<'a>(&self, a: &'a [T]) -> Cow<'a, [T]> {
+                $df(a, self.1)
+            }
+        }
+
+        impl_float_unary_op!(@op $s);
+    };
+    (@op $s:ident) => {
+        impl<T: FloatDtype, S: Dims> Op for $s<T, S> {
+            type Produces = Tensor<T, S>;
+
+            fn propogate_grad(&self, t: &Self::Produces) {
+                // t = f(a)
+                // d_da = d_dt * f'(a)
+                if let Some(d_dt) = t.data.grad_ref().as_ref() {
+                    let d_da = {
+                        let a = self.0.borrow_value();
+                        el_mul(d_dt, &self.grad(&a))
+                    };
+                    self.0.update_grad(d_da);
+                } else {
+                    panic!("Attempted to propogate grad, but no grad value exists.")
+                }
+            }
+
+            fn recompute(&self, t: &Self::Produces) {
+                let data = self.compute(&self.0.borrow_value());
+                t.data.replace(data)
+            }
+
+            fn forward(self) -> Self::Produces {
+                let value = self.compute(&self.0.borrow_value());
+                let data = TensorData::new(value, self.0.requires_grad());
+                let shape = self.0.shape.clone();
+                unsafe {
+                    Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape)
+                }
+            }
+
+            fn operands(&self) -> Vec<TensorBox<'_>> {
+                vec![TensorBox::new(self.0.id, &self.0)]
+            }
+        }
+    };
+}
+
+pub(crate) use impl_float_unary_op;
+
+impl_float_unary_op!(ElExpStruct, el_exp, el_exp_grad);
+impl_float_unary_op!(ElLnStruct, el_ln, el_ln_grad);
+impl_float_unary_op!(ElLog1pStruct, el_log1p, el_log1p_grad);
+impl_float_unary_op!(ElSqrtStruct, el_sqrt, el_sqrt_grad);
+impl_float_unary_op!(ElRsqrtStruct, el_rsqrt, el_rsqrt_grad);
+impl_float_unary_op!(ElPowfStruct, T, el_powf, el_powf_grad);
+impl_float_unary_op!(ElPowiStruct, i32, el_powi, el_powi_grad);
+impl_float_unary_op!(ElSinStruct, el_sin, el_sin_grad);
+impl_float_unary_op!(ElCosStruct, el_cos, el_cos_grad);
+impl_float_unary_op!(ElTanStruct, el_tan, el_tan_grad);
+impl_float_unary_op!(ElTanhStruct, el_tanh, el_tanh_grad);
+
+impl<T: FloatDtype, S: Dims> Tensor<T, S> {
+    pub fn exp(self) -> Self {
+        ElExpStruct(self).forward()
+    }
+
+    /// Natural logarithm.
+    pub fn ln(self) -> Self {
+        ElLnStruct(self).forward()
+    }
+
+    /// `ln(1 + x)`, accurate for small `x`.
+    pub fn log1p(self) -> Self {
+        ElLog1pStruct(self).forward()
+    }
+
+    pub fn sqrt(self) -> Self {
+        ElSqrtStruct(self).forward()
+    }
+
+    /// `1 / sqrt(x)`.
+    pub fn rsqrt(self) -> Self {
+        ElRsqrtStruct(self).forward()
+    }
+
+    /// Raise each element to the float power `p`.
+    pub fn powf(self, p: T) -> Self {
+        ElPowfStruct(self, p).forward()
+    }
+
+    /// Raise each element to the integer power `n`.
+    pub fn powi(self, n: i32) -> Self {
+        ElPowiStruct(self, n).forward()
+    }
+
+    pub fn sin(self) -> Self {
+        ElSinStruct(self).forward()
+    }
+
+    pub fn cos(self) -> Self {
+        ElCosStruct(self).forward()
+    }
+
+    pub fn tan(self) -> Self {
+        ElTanStruct(self).forward()
+    }
+
+    pub fn tanh(self) -> Self {
+        ElTanhStruct(self).forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+    use crate::test_util::{assert_close, check_grad};
+
+    #[test]
+    fn test_float_ops() {
+        let x = Tensor::new([1.0, 4.0]);
+        assert_close(&x.clone().sqrt().borrow_value(), &[1.0, 2.0]);
+        assert_close(&x.clone().rsqrt().borrow_value(), &[1.0, 0.5]);
+        assert_close(&x.clone().powi(2).borrow_value(), &[1.0, 16.0]);
+        assert_close(&x.clone().powf(0.5).borrow_value(), &[1.0, 2.0]);
+        assert_close(&x.clone().ln().exp().borrow_value(), &[1.0, 4.0]);
+        assert_close(&x.log1p().borrow_value(), &[2f64.ln(), 5f64.ln()]);
+    }
+
+    #[test]
+    fn test_float_grads() {
+        type T3 = Tensor<f64, (I<3>,)>;
+        let ops: [fn(T3) -> T3; 11] = [
+            |t| t.exp(),
+            |t| t.ln(),
+            |t| t.log1p(),
+            |t| t.sqrt(),
+            |t| t.rsqrt(),
+            |t| t.powf(1.5),
+            |t| t.powi(3),
+            |t| t.sin(),
+            |t| t.cos(),
+            |t| t.tan(),
+            |t| t.tanh(),
+        ];
+        for op in ops {
+            check_grad(op, &[0.3, 0.7, 1.1]);
+        }
+    }
+}
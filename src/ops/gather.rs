@@ -0,0 +1,329 @@
+use crate::ops::vec::{el_add, gather, index_add, index_select, scatter_add};
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{
+    dtype::{IntDtype, NumDtype},
+    ops::Op,
+    shape::{Dims, GatherIndex, ResizeAxis, Shape, I},
+    tensor::Tensor,
+};
+use std::rc::Rc;
+
+pub trait Gathers<T: NumDtype, S: Shape, Ix: IntDtype, Si: Shape> {
+    /// Take `out[.., j, ..] = self[.., idx[.., j, ..], ..]` along `AXIS`, e.g.
+    /// `logits.gather::<1>(targets)` picks one logit per row.
+    fn gather<const AXIS: usize>(self, idx: Tensor<Ix, Si>) -> Tensor<T, Si>
+    where
+        S: GatherIndex<Si, AXIS>;
+
+    /// Add `src[.., j, ..]` into `self[.., idx[.., j, ..], ..]` along `AXIS`. Repeated indices
+    /// accumulate.
+    fn scatter_add<const AXIS: usize>(
+        self,
+        idx: Tensor<Ix, Si>,
+        src: Tensor<T, Si>,
+    ) -> Tensor<T, S>
+    where
+        S: GatherIndex<Si, AXIS>;
+}
+
+pub trait IndexSelects<T: NumDtype, S: Shape, Ix: IntDtype, const K: usize> {
+    /// Select the `K` slices `idx` along `AXIS`, e.g. `embeddings.index_select::<0>(tokens)`.
+    fn index_select<const AXIS: usize>(
+        self,
+        idx: Tensor<Ix, (I<K>,)>,
+    ) -> Tensor<T, <S as ResizeAxis<AXIS, K>>::Output>
+    where
+        S: ResizeAxis<AXIS, K>;
+}
+
+#[derive(Debug)]
+pub struct GatherStruct<T: NumDtype, S: Dims, Ix: NumDtype, Si: Dims> {
+    pub(crate) data: Tensor<T, S>,
+    pub(crate) idx: Tensor<Ix, Si>,
+    pub(crate) axis: usize,
+}
+
+#[derive(Debug)]
+pub struct ScatterAddStruct<T: NumDtype, S: Dims, Ix: NumDtype, Si: Dims> {
+    pub(crate) data: Tensor<T, S>,
+    pub(crate) idx: Tensor<Ix, Si>,
+    pub(crate) src: Tensor<T, Si>,
+    pub(crate) axis: usize,
+}
+
+/// `So` is the shape of `data` with axis `axis` resized to the number of indices.
+#[derive(Debug)]
+pub struct IndexSelectStruct<T: NumDtype, S: Dims, Ix: NumDtype, Si: Dims, So: Dims> {
+    pub(crate) data: Tensor<T, S>,
+    pub(crate) idx: Tensor<Ix, Si>,
+    pub(crate) axis: usize,
+    pub(crate) out_shape: So,
+}
+
+/// Read an index tensor as `usize`s, panicking on indices outside `0..n`.
+pub(crate) fn indices<Ix: IntDtype, Si: Dims>(idx: &Tensor<Ix, Si>, n: usize) -> Vec<usize> {
+    idx.borrow_value()
+        .iter()
+        .map(|i| match i.to_usize() {
+            Some(j) if j < n => j,
+            _ => panic!("Index {:?} is out of bounds for axis with size {}", i, n),
+        })
+        .collect()
+}
+
+// Gather
+impl<T: NumDtype, S: Dims, Ix: IntDtype, Si: Dims> GatherStruct<T, S, Ix, Si> {
+    fn indices(&self) -> Vec<usize> {
+        indices(&self.idx, self.data.shape.dims()[self.axis])
+    }
+
+    fn compute(&self) -> Vec<T> {
+        let a = self.data.borrow_value();
+        let k = self.idx.shape.dims()[self.axis];
+        gather(&a, &self.data.shape.dims(), &self.indices(), k, self.axis)
+    }
+}
+
+impl<T: NumDtype, S: Dims, Ix: IntDtype, Si: Dims> Op for GatherStruct<T, S, Ix, Si> {
+    type Produces = Tensor<T, Si>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = gather(a, idx, axis)
+        // d_da = scatter_add(zeros, idx, d_dt, axis)
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let k = self.idx.shape.dims()[self.axis];
+            let d_da = scatter_add(d_dt, &self.data.shape.dims(), &self.indices(), k, self.axis);
+            self.data.update_grad(d_da);
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let data = TensorData::new(self.compute(), self.data.requires_grad());
+        let shape = self.idx.shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![
+            TensorBox::new(self.data.id, &self.data),
+            TensorBox::new(self.idx.id, &self.idx),
+        ]
+    }
+}
+
+// Scatter add
+impl<T: NumDtype, S: Dims, Ix: IntDtype, Si: Dims> ScatterAddStruct<T, S, Ix, Si> {
+    fn indices(&self) -> Vec<usize> {
+        indices(&self.idx, self.data.shape.dims()[self.axis])
+    }
+
+    fn compute(&self) -> Vec<T> {
+        let src = self.src.borrow_value();
+        let k = self.idx.shape.dims()[self.axis];
+        let scattered = scatter_add(&src, &self.data.shape.dims(), &self.indices(), k, self.axis);
+        el_add(&self.data.borrow_value(), &scattered)
+    }
+}
+
+impl<T: NumDtype, S: Dims, Ix: IntDtype, Si: Dims> Op for ScatterAddStruct<T, S, Ix, Si> {
+    type Produces = Tensor<T, S>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = a + scatter_add(zeros, idx, src, axis)
+        // d_da = d_dt
+        // d_dsrc = gather(d_dt, idx, axis)
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let k = self.idx.shape.dims()[self.axis];
+            let d_dsrc = gather(d_dt, &t.shape.dims(), &self.indices(), k, self.axis);
+            self.data.update_grad(d_dt.to_vec());
+            self.src.update_grad(d_dsrc);
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let data = TensorData::new(
+            self.compute(),
+            self.data.requires_grad() || self.src.requires_grad(),
+        );
+        let shape = self.data.shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![
+            TensorBox::new(self.data.id, &self.data),
+            TensorBox::new(self.idx.id, &self.idx),
+            TensorBox::new(self.src.id, &self.src),
+        ]
+    }
+}
+
+impl<T: NumDtype, S: Shape, Ix: IntDtype, Si: Shape> Gathers<T, S, Ix, Si> for Tensor<T, S> {
+    fn gather<const AXIS: usize>(self, idx: Tensor<Ix, Si>) -> Tensor<T, Si>
+    where
+        S: GatherIndex<Si, AXIS>,
+    {
+        GatherStruct {
+            data: self,
+            idx,
+            axis: AXIS,
+        }
+        .forward()
+    }
+
+    fn scatter_add<const AXIS: usize>(self, idx: Tensor<Ix, Si>, src: Tensor<T, Si>) -> Tensor<T, S>
+    where
+        S: GatherIndex<Si, AXIS>,
+    {
+        ScatterAddStruct {
+            data: self,
+            idx,
+            src,
+            axis: AXIS,
+        }
+        .forward()
+    }
+}
+
+// Index select
+impl<T: NumDtype, S: Dims, Ix: IntDtype, Si: Dims, So: Dims> IndexSelectStruct<T, S, Ix, Si, So> {
+    fn indices(&self) -> Vec<usize> {
+        indices(&self.idx, self.data.shape.dims()[self.axis])
+    }
+
+    fn compute(&self) -> Vec<T> {
+        let a = self.data.borrow_value();
+        index_select(&a, &self.data.shape.dims(), &self.indices(), self.axis)
+    }
+}
+
+impl<T: NumDtype, S: Dims, Ix: IntDtype, Si: Dims, So: Dims> Op
+    for IndexSelectStruct<T, S, Ix, Si, So>
+{
+    type Produces = Tensor<T, So>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = a[.., idx, ..]
+        // d_da = index_add(zeros, idx, d_dt)
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let d_da = index_add(d_dt, &self.data.shape.dims(), &self.indices(), self.axis);
+            self.data.update_grad(d_da);
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let data = TensorData::new(self.compute(), self.data.requires_grad());
+        let shape = self.out_shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![
+            TensorBox::new(self.data.id, &self.data),
+            TensorBox::new(self.idx.id, &self.idx),
+        ]
+    }
+}
+
+impl<T: NumDtype, S: Shape, Ix: IntDtype, const K: usize> IndexSelects<T, S, Ix, K>
+    for Tensor<T, S>
+{
+    fn index_select<const AXIS: usize>(
+        self,
+        idx: Tensor<Ix, (I<K>,)>,
+    ) -> Tensor<T, <S as ResizeAxis<AXIS, K>>::Output>
+    where
+        S: ResizeAxis<AXIS, K>,
+    {
+        IndexSelectStruct {
+            data: self,
+            idx,
+            axis: AXIS,
+            out_shape: Default::default(),
+        }
+        .forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Gathers, IndexSelects};
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_gather_grad() {
+        let logits = Tensor::new_with_grad([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let targets = Tensor::new([[2], [0]]);
+        let picked: Tensor<f64, (I<2>, I<1>)> = logits.clone().gather::<1>(targets);
+        assert_eq!(*picked.borrow_value(), [3.0, 4.0]);
+
+        picked.reduce_sum().backward();
+        assert_eq!(
+            logits.borrow_grad().as_deref(),
+            Some(&[0.0, 0.0, 1.0, 1.0, 0.0, 0.0][..])
+        );
+    }
+
+    #[test]
+    fn test_scatter_add_grad() {
+        let x = Tensor::new_with_grad([0.0, 0.0, 0.0]);
+        let src = Tensor::new_with_grad([1.0, 2.0, 3.0, 4.0]);
+        let y = x
+            .clone()
+            .scatter_add::<0>(Tensor::new([0, 2, 0, 1]), src.clone());
+        assert_eq!(*y.borrow_value(), [4.0, 4.0, 2.0]);
+
+        (y * Tensor::new([1.0, 2.0, 3.0])).reduce_sum().backward();
+        assert_eq!(x.borrow_grad().as_deref(), Some(&[1.0, 2.0, 3.0][..]));
+        assert_eq!(
+            src.borrow_grad().as_deref(),
+            Some(&[1.0, 3.0, 1.0, 2.0][..])
+        );
+    }
+
+    #[test]
+    fn test_index_select_grad() {
+        let emb = Tensor::new_with_grad([[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]]);
+        let tokens: Tensor<i64, (I<3>,)> = Tensor::new([2, 0, 2]);
+        let e: Tensor<f64, (I<3>, I<2>)> = emb.clone().index_select::<0>(tokens);
+        assert_eq!(*e.borrow_value(), [5.0, 6.0, 1.0, 2.0, 5.0, 6.0]);
+
+        e.reduce_sum().backward();
+        assert_eq!(
+            emb.borrow_grad().as_deref(),
+            Some(&[1.0, 1.0, 0.0, 0.0, 2.0, 2.0][..])
+        );
+
+        // Indices can be unsigned
+        let tokens: Tensor<usize, (I<2>,)> = Tensor::new([1, 0]);
+        let e: Tensor<f64, (I<2>, I<2>)> = emb.index_select::<0>(tokens);
+        assert_eq!(*e.borrow_value(), [3.0, 4.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_gather_out_of_bounds() {
+        let x = Tensor::new([1.0, 2.0]);
+        x.gather::<0>(Tensor::new([2]));
+    }
+}
@@ -1,32 +1,38 @@
-use super::vec::{el_bin, el_gt, el_inv, el_lt, el_neg, el_pos, ones_like};
-use crate::dtype::Dtype;
+use super::vec::{
+    arg_reduce_axis, axis_mask, axis_sizes, batch_transpose2d, bmm, col2im, cosine_similarity, dot,
+    el_bin, el_exp, el_gt, el_inv, el_lt, el_mul, el_neg, el_pos, el_sub, el_unary, erf,
+    float_const, im2col, masked_fill, matmul, neg_one, ones_like, reduce_to_shape, sigmoid,
+    softmax_axis, softplus, sum_axis, transpose2d, unreduce_axis, where_cond, ConvGeometry,
+    NormLayout, GELU_COEFF, SELU_ALPHA, SELU_LAMBDA,
+};
+use crate::dtype::{FloatDtype, NumDtype, SignedDtype};
 use std::borrow::Cow;
 
-pub(crate) fn el_add_grad<'a, T: Dtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+pub(crate) fn el_add_grad<'a, T: NumDtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
     // t = a + b
     (ones_like(a).into(), ones_like(b).into())
 }
 
-pub(crate) fn el_sub_grad<'a, T: Dtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+pub(crate) fn el_sub_grad<'a, T: NumDtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
     // t = a - b
-    (ones_like(a).into(), el_neg(&ones_like(b)).into())
+    (ones_like(a).into(), vec![neg_one(); b.len()].into())
 }
 
-pub(crate) fn el_mul_grad<'a, T: Dtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+pub(crate) fn el_mul_grad<'a, T: NumDtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
     // t = a * b
     (b.into(), a.into())
 }
 
-pub(crate) fn el_div_grad<'a, T: Dtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+pub(crate) fn el_div_grad<'a, T: NumDtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
     // t = a / b
     // dt_da = 1 / b
     // dt_db = -a / (b * b)
     let dt_da = el_inv(b).into();
-    let dt_db = el_bin(|(x, y)| -*x / (*y * *y), a, b).into();
+    let dt_db = el_bin(|(x, y)| neg_one::<T>() * *x / (*y * *y), a, b).into();
     (dt_da, dt_db)
 }
 
-pub(crate) fn el_max_grad<'a, T: Dtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+pub(crate) fn el_max_grad<'a, T: NumDtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
     // t = max(a, b)
     // dt_da = 1 if a > b else 0
     // dt_db = 1 if b > a else 0
@@ -35,7 +41,7 @@ pub(crate) fn el_max_grad<'a, T: Dtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>
     (dt_da, dt_db)
 }
 
-pub(crate) fn el_min_grad<'a, T: Dtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+pub(crate) fn el_min_grad<'a, T: NumDtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
     // t = min(a, b)
     // dt_da = 1 if a < b else 0
     // dt_db = 1 if b < a else 0
@@ -44,12 +50,628 @@ pub(crate) fn el_min_grad<'a, T: Dtype>(a: &'a [T], b: &'a [T]) -> (Cow<'a, [T]>
     (dt_da, dt_db)
 }
 
-pub(crate) fn reduce_sum_grad<T: Dtype>(a: &[T]) -> Cow<[T]> {
+pub(crate) fn reduce_sum_grad<T: NumDtype>(a: &[T]) -> Cow<'_, [T]> {
     // t = sum(a)
     ones_like(a).into()
 }
 
-pub(crate) fn el_relu_grad<T: Dtype>(a: &[T]) -> Cow<[T]> {
+pub(crate) fn masked_fill_grad<T: NumDtype>(d_dt: &[T], mask: &[bool]) -> Vec<T> {
+    // t = value if mask else a
+    // d_da = 0 if mask else d_dt
+    masked_fill(d_dt, mask, T::zero())
+}
+
+pub(crate) fn where_cond_grad<T: NumDtype>(d_dt: &[T], cond: &[bool]) -> (Vec<T>, Vec<T>) {
+    // t = a if cond else b
+    // d_da = d_dt if cond else 0
+    // d_db = 0 if cond else d_dt
+    let zeros = vec![T::zero(); d_dt.len()];
+    (
+        where_cond(cond, d_dt, &zeros),
+        where_cond(cond, &zeros, d_dt),
+    )
+}
+
+pub(crate) fn el_relu_grad<T: NumDtype>(a: &[T]) -> Cow<'_, [T]> {
     // t = relu(a)
+    // dt_da = 1 if a > 0, else 0 (including at 0)
     el_pos(a).into()
 }
+
+pub(crate) fn el_exp_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = exp(a)
+    // dt_da = exp(a)
+    el_unary(|x| x.exp(), a).into()
+}
+
+pub(crate) fn el_ln_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = ln(a)
+    // dt_da = 1 / a
+    el_inv(a).into()
+}
+
+pub(crate) fn el_log1p_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = ln(1 + a)
+    // dt_da = 1 / (1 + a)
+    el_unary(|x| (T::one() + *x).recip(), a).into()
+}
+
+pub(crate) fn el_sqrt_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = a^(1/2)
+    // dt_da = 1 / (2 * a^(1/2))
+    el_unary(|x| (x.sqrt() + x.sqrt()).recip(), a).into()
+}
+
+pub(crate) fn el_rsqrt_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = a^(-1/2)
+    // dt_da = -1 / (2 * a^(3/2))
+    el_unary(|x| -(x.sqrt() * *x + x.sqrt() * *x).recip(), a).into()
+}
+
+pub(crate) fn el_powf_grad<T: FloatDtype>(a: &[T], p: T) -> Cow<'_, [T]> {
+    // t = a^p
+    // dt_da = p * a^(p - 1)
+    el_unary(|x| p * x.powf(p - T::one()), a).into()
+}
+
+pub(crate) fn el_powi_grad<T: FloatDtype>(a: &[T], n: i32) -> Cow<'_, [T]> {
+    // t = a^n
+    // dt_da = n * a^(n - 1)
+    let n_t = T::from_i32(n).expect("Failed to cast exponent to dtype");
+    el_unary(|x| n_t * x.powi(n - 1), a).into()
+}
+
+pub(crate) fn el_sin_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = sin(a)
+    // dt_da = cos(a)
+    el_unary(|x| x.cos(), a).into()
+}
+
+pub(crate) fn el_cos_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = cos(a)
+    // dt_da = -sin(a)
+    el_unary(|x| -x.sin(), a).into()
+}
+
+pub(crate) fn el_tan_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = tan(a)
+    // dt_da = 1 + tan(a)^2
+    el_unary(|x| T::one() + x.tan() * x.tan(), a).into()
+}
+
+pub(crate) fn el_tanh_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = tanh(a)
+    // dt_da = 1 - tanh(a)^2
+    el_unary(|x| T::one() - x.tanh() * x.tanh(), a).into()
+}
+
+pub(crate) fn el_sigmoid_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = s(a) = 1 / (1 + e^-a)
+    // dt_da = s(a) * (1 - s(a))
+    el_unary(|x| sigmoid(*x) * (T::one() - sigmoid(*x)), a).into()
+}
+
+pub(crate) fn el_gelu_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = a * Phi(a)
+    // dt_da = Phi(a) + a * phi(a)
+    let half: T = float_const(0.5);
+    let frac_1_sqrt_2: T = float_const(std::f64::consts::FRAC_1_SQRT_2);
+    let frac_1_sqrt_2pi: T =
+        float_const(0.5 * std::f64::consts::FRAC_2_SQRT_PI * std::f64::consts::FRAC_1_SQRT_2);
+    el_unary(
+        |x| {
+            let cdf = half * (T::one() + erf(*x * frac_1_sqrt_2));
+            let pdf = frac_1_sqrt_2pi * (-half * *x * *x).exp();
+            cdf + *x * pdf
+        },
+        a,
+    )
+    .into()
+}
+
+pub(crate) fn el_gelu_tanh_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = a / 2 * (1 + tanh(u)), u = k * (a + c * a^3)
+    // dt_da = (1 + tanh(u)) / 2 + a / 2 * (1 - tanh(u)^2) * k * (1 + 3 * c * a^2)
+    let half: T = float_const(0.5);
+    let k: T = float_const((2.0 / std::f64::consts::PI).sqrt());
+    let c: T = float_const(GELU_COEFF);
+    let three: T = float_const(3.0);
+    el_unary(
+        |x| {
+            let th = (k * (*x + c * x.powi(3))).tanh();
+            half * (T::one() + th)
+                + half * *x * (T::one() - th * th) * k * (T::one() + three * c * *x * *x)
+        },
+        a,
+    )
+    .into()
+}
+
+pub(crate) fn el_silu_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = a * s(a)
+    // dt_da = s(a) * (1 + a * (1 - s(a)))
+    el_unary(
+        |x| {
+            let s = sigmoid(*x);
+            s * (T::one() + *x * (T::one() - s))
+        },
+        a,
+    )
+    .into()
+}
+
+pub(crate) fn el_leaky_relu_grad<T: FloatDtype>(a: &[T], alpha: T) -> Cow<'_, [T]> {
+    // t = a if a > 0, else alpha * a
+    // dt_da = 1 if a > 0, else alpha
+    el_unary(|x| if *x > T::zero() { T::one() } else { alpha }, a).into()
+}
+
+pub(crate) fn el_elu_grad<T: FloatDtype>(a: &[T], alpha: T) -> Cow<'_, [T]> {
+    // t = a if a > 0, else alpha * (e^a - 1)
+    // dt_da = 1 if a > 0, else alpha * e^a
+    el_unary(
+        |x| {
+            if *x > T::zero() {
+                T::one()
+            } else {
+                alpha * x.exp()
+            }
+        },
+        a,
+    )
+    .into()
+}
+
+pub(crate) fn el_selu_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = lambda * elu(a, alpha)
+    // dt_da = lambda * elu'(a, alpha)
+    let lambda: T = float_const(SELU_LAMBDA);
+    el_unary(|x| lambda * *x, &el_elu_grad(a, float_const(SELU_ALPHA))).into()
+}
+
+pub(crate) fn el_softplus_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = ln(1 + e^a)
+    // dt_da = s(a)
+    el_unary(|x| sigmoid(*x), a).into()
+}
+
+pub(crate) fn el_hardtanh_grad<T: FloatDtype>(a: &[T], (min, max): (T, T)) -> Cow<'_, [T]> {
+    // t = clamp(a, min, max)
+    // dt_da = 1 if min < a < max, else 0
+    el_unary(
+        |x| {
+            if *x > min && *x < max {
+                T::one()
+            } else {
+                T::zero()
+            }
+        },
+        a,
+    )
+    .into()
+}
+
+pub(crate) fn el_mish_grad<T: FloatDtype>(a: &[T]) -> Cow<'_, [T]> {
+    // t = a * tanh(softplus(a))
+    // dt_da = tanh(softplus(a)) + a * (1 - tanh(softplus(a))^2) * s(a)
+    el_unary(
+        |x| {
+            let th = softplus(*x).tanh();
+            th + *x * (T::one() - th * th) * sigmoid(*x)
+        },
+        a,
+    )
+    .into()
+}
+
+// Loss grads return (dt_dp, dt_dt) for predictions p and targets t.
+
+pub(crate) fn el_mse_loss_grad<'a, T: FloatDtype>(
+    p: &'a [T],
+    t: &'a [T],
+) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+    // l = (p - t)^2
+    // dl_dp = 2 * (p - t), dl_dt = -2 * (p - t)
+    let two: T = float_const(2.0);
+    let dl_dp = el_bin(|(p, t)| two * (*p - *t), p, t);
+    let dl_dt = el_neg(&dl_dp);
+    (dl_dp.into(), dl_dt.into())
+}
+
+pub(crate) fn el_l1_loss_grad<'a, T: FloatDtype>(
+    p: &'a [T],
+    t: &'a [T],
+) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+    // l = |p - t|
+    // dl_dp = sign(p - t), taken as 0 at p = t, dl_dt = -dl_dp
+    let dl_dp = el_bin(
+        |(p, t)| {
+            if p == t {
+                T::zero()
+            } else {
+                (*p - *t).signum()
+            }
+        },
+        p,
+        t,
+    );
+    let dl_dt = el_neg(&dl_dp);
+    (dl_dp.into(), dl_dt.into())
+}
+
+pub(crate) fn el_huber_loss_grad<'a, T: FloatDtype>(
+    p: &'a [T],
+    t: &'a [T],
+    delta: T,
+) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+    // l = 0.5 * (p - t)^2 for |p - t| <= delta, else delta * (|p - t| - 0.5 * delta)
+    // dl_dp = clamp(p - t, -delta, delta), dl_dt = -dl_dp
+    let dl_dp = el_bin(|(p, t)| (*p - *t).max(-delta).min(delta), p, t);
+    let dl_dt = el_neg(&dl_dp);
+    (dl_dp.into(), dl_dt.into())
+}
+
+pub(crate) fn el_smooth_l1_loss_grad<'a, T: FloatDtype>(
+    p: &'a [T],
+    t: &'a [T],
+    beta: T,
+) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+    // l = huber(p, t, beta) / beta
+    let (dl_dp, dl_dt) = el_huber_loss_grad(p, t, beta);
+    (
+        el_unary(|x| *x / beta, &dl_dp).into(),
+        el_unary(|x| *x / beta, &dl_dt).into(),
+    )
+}
+
+pub(crate) fn el_bce_loss_grad<'a, T: FloatDtype>(
+    p: &'a [T],
+    t: &'a [T],
+) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+    // l = -(t * ln(p) + (1 - t) * ln(1 - p))
+    // dl_dp = (p - t) / (p * (1 - p)), dl_dt = ln(1 - p) - ln(p)
+    let eps: T = float_const(1e-12);
+    let min_ln: T = float_const(-100.0);
+    let dl_dp = el_bin(|(p, t)| (*p - *t) / (*p * (T::one() - *p)).max(eps), p, t);
+    let dl_dt = el_unary(|p| (T::one() - *p).ln().max(min_ln) - p.ln().max(min_ln), p);
+    (dl_dp.into(), dl_dt.into())
+}
+
+pub(crate) fn el_bce_with_logits_loss_grad<'a, T: FloatDtype>(
+    x: &'a [T],
+    t: &'a [T],
+) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+    // l = softplus(x) - t * x
+    // dl_dx = s(x) - t, dl_dt = -x
+    let dl_dx = el_bin(|(x, t)| sigmoid(*x) - *t, x, t);
+    (dl_dx.into(), el_neg(x).into())
+}
+
+pub(crate) fn el_kl_div_loss_grad<'a, T: FloatDtype>(
+    x: &'a [T],
+    t: &'a [T],
+) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+    // l = t * (ln(t) - x)
+    // dl_dx = -t, dl_dt = ln(t) - x + 1, taken as 0 at t = 0
+    let dl_dt = el_bin(
+        |(x, t)| {
+            if *t > T::zero() {
+                t.ln() - *x + T::one()
+            } else {
+                T::zero()
+            }
+        },
+        x,
+        t,
+    );
+    (el_neg(t).into(), dl_dt.into())
+}
+
+pub(crate) fn el_hinge_loss_grad<'a, T: FloatDtype>(
+    p: &'a [T],
+    t: &'a [T],
+    margin: T,
+) -> (Cow<'a, [T]>, Cow<'a, [T]>) {
+    // l = max(0, margin - t * p)
+    // dl_dp = -t, dl_dt = -p where the margin is violated, else 0
+    let active = |p: &T, t: &T| margin - *t * *p > T::zero();
+    let dl_dp = el_bin(|(p, t)| if active(p, t) { -*t } else { T::zero() }, p, t);
+    let dl_dt = el_bin(|(p, t)| if active(p, t) { -*p } else { T::zero() }, p, t);
+    (dl_dp.into(), dl_dt.into())
+}
+
+/// Grads of the cosine similarity of each pair of rows of `a` and `b` with respect to `a` and
+/// `b`, where rows have length `d`.
+pub(crate) fn cosine_similarity_grad<T: FloatDtype>(
+    a: &[T],
+    b: &[T],
+    d: usize,
+) -> (Vec<T>, Vec<T>) {
+    // t = a.b / (|a| * |b|)
+    // dt_da = b / (|a| * |b|) - t * a / |a|^2
+    let eps: T = float_const(1e-8);
+    let cos = cosine_similarity(a, b, d);
+    let mut dt_da = Vec::with_capacity(a.len());
+    let mut dt_db = Vec::with_capacity(b.len());
+    for ((a, b), c) in a.chunks(d).zip(b.chunks(d)).zip(cos) {
+        let (aa, bb) = (dot(a, a), dot(b, b));
+        let norms = (aa * bb).sqrt().max(eps);
+        dt_da.extend(
+            a.iter()
+                .zip(b)
+                .map(|(x, y)| *y / norms - c * *x / aa.max(eps)),
+        );
+        dt_db.extend(
+            a.iter()
+                .zip(b)
+                .map(|(x, y)| *x / norms - c * *y / bb.max(eps)),
+        );
+    }
+    (dt_da, dt_db)
+}
+
+/// Grad of `nll_loss`, which scatters `-d_dt[n]` to `[n, idx[n]]` of an array with rows of
+/// length `c`.
+pub(crate) fn nll_loss_grad<T: SignedDtype>(d_dt: &[T], idx: &[usize], c: usize) -> Vec<T> {
+    // t[n] = -a[n, idx[n]]
+    // d_da[n, idx[n]] = -d_dt[n], 0 elsewhere
+    let mut d_da = vec![T::zero(); idx.len() * c];
+    for (n, i) in idx.iter().enumerate() {
+        d_da[n * c + i] = -d_dt[n];
+    }
+    d_da
+}
+
+// Axis reduction grads are returned with the shape of `a`, the op broadcasts d_dt to match.
+
+pub(crate) fn sum_axis_grad<'a, T: NumDtype>(
+    a: &'a [T],
+    _shape: &[usize],
+    _axis: usize,
+) -> Cow<'a, [T]> {
+    // t = sum(a, axis)
+    ones_like(a).into()
+}
+
+pub(crate) fn mean_axis_grad<'a, T: NumDtype>(
+    a: &'a [T],
+    shape: &[usize],
+    axis: usize,
+) -> Cow<'a, [T]> {
+    // t = sum(a, axis) / n
+    let n = T::from_usize(shape[axis]).expect("Failed to cast axis size to dtype");
+    vec![T::one() / n; a.len()].into()
+}
+
+pub(crate) fn prod_axis_grad<'a, T: NumDtype>(
+    a: &'a [T],
+    shape: &[usize],
+    axis: usize,
+) -> Cow<'a, [T]> {
+    // t = prod(a, axis)
+    // dt_da[k] = prod(a[j] for j != k), computed with prefix/suffix products to handle zeros
+    let (outer, n, inner) = axis_sizes(shape, axis);
+    let mut dt_da = vec![T::one(); a.len()];
+    for o in 0..outer {
+        for i in 0..inner {
+            let idx = |k: usize| o * n * inner + k * inner + i;
+            let mut prefix = T::one();
+            for k in 0..n {
+                dt_da[idx(k)] = prefix;
+                prefix = prefix * a[idx(k)];
+            }
+            let mut suffix = T::one();
+            for k in (0..n).rev() {
+                dt_da[idx(k)] = dt_da[idx(k)] * suffix;
+                suffix = suffix * a[idx(k)];
+            }
+        }
+    }
+    dt_da.into()
+}
+
+pub(crate) fn max_axis_grad<'a, T: NumDtype>(
+    a: &'a [T],
+    shape: &[usize],
+    axis: usize,
+) -> Cow<'a, [T]> {
+    // t = max(a, axis)
+    // dt_da = 1 at the (first) argmax of each slice, else 0
+    let args = arg_reduce_axis(|x, m| x > m, a, shape, axis);
+    axis_mask(&args, shape, axis).into()
+}
+
+pub(crate) fn min_axis_grad<'a, T: NumDtype>(
+    a: &'a [T],
+    shape: &[usize],
+    axis: usize,
+) -> Cow<'a, [T]> {
+    // t = min(a, axis)
+    // dt_da = 1 at the (first) argmin of each slice, else 0
+    let args = arg_reduce_axis(|x, m| x < m, a, shape, axis);
+    axis_mask(&args, shape, axis).into()
+}
+
+pub(crate) fn logsumexp_axis_grad<'a, T: FloatDtype>(
+    a: &'a [T],
+    shape: &[usize],
+    axis: usize,
+) -> Cow<'a, [T]> {
+    // t = ln(sum(e^a, axis))
+    // dt_da = softmax(a, axis)
+    softmax_axis(a, shape, axis).into()
+}
+
+// The Jacobians of softmax and log_softmax are dense along the axis, so these take d_dt and
+// return the Jacobian-vector product d_da directly. Both are given the output `t` of the op.
+
+pub(crate) fn softmax_axis_grad<T: FloatDtype>(
+    t: &[T],
+    d_dt: &[T],
+    shape: &[usize],
+    axis: usize,
+) -> Vec<T> {
+    // t = softmax(a, axis)
+    // d_da = t * (d_dt - sum(d_dt * t, axis))
+    let dot = sum_axis(&el_mul(d_dt, t), shape, axis);
+    el_mul(t, &el_sub(d_dt, &unreduce_axis(&dot, shape, axis)))
+}
+
+pub(crate) fn log_softmax_axis_grad<T: FloatDtype>(
+    t: &[T],
+    d_dt: &[T],
+    shape: &[usize],
+    axis: usize,
+) -> Vec<T> {
+    // t = log_softmax(a, axis)
+    // d_da = d_dt - e^t * sum(d_dt, axis)
+    let total = sum_axis(d_dt, shape, axis);
+    el_sub(
+        d_dt,
+        &el_mul(&el_exp(t), &unreduce_axis(&total, shape, axis)),
+    )
+}
+
+/// Grads of `bmm` with respect to `a` and `b`. `b` is either a stack of `batch` matrices or a
+/// single matrix which was broadcast across the batch.
+pub(crate) fn bmm_grad<T: NumDtype>(
+    a: &[T],
+    b: &[T],
+    d_dt: &[T],
+    (batch, n, m, o): (usize, usize, usize, usize),
+) -> (Vec<T>, Vec<T>) {
+    // t = bmm(a, b)            shape: (B, N, O)
+    // d_da = d_dt @ b^T        shape: (B, N, M)
+    // d_db = a^T @ d_dt        shape: (B, M, O), summed over B if b is shared
+    let shared = b.len() == m * o;
+    let b_t = if shared {
+        transpose2d(b, o)
+    } else {
+        batch_transpose2d(b, m, o)
+    };
+    let d_da = bmm(d_dt, &b_t, batch, n, o, m);
+    let d_db = bmm(&batch_transpose2d(a, n, m), d_dt, batch, m, n, o);
+    let d_db = if shared {
+        reduce_to_shape(d_db, &[batch, m, o], &[m, o])
+    } else {
+        d_db
+    };
+    (d_da, d_db)
+}
+
+/// Grads of a grouped 2d convolution with respect to the input `x` and weights `w`.
+pub(crate) fn conv2d_grad<T: NumDtype>(
+    x: &[T],
+    w: &[T],
+    d_dt: &[T],
+    g: &ConvGeometry,
+) -> (Vec<T>, Vec<T>) {
+    // t = w_g @ cols_g for each image and group g, where cols = im2col(x)
+    // d_dw_g = sum over images of d_dt_g @ cols_g^T
+    // d_dcols_g = w_g^T @ d_dt_g, and d_dx = col2im(d_dcols)
+    let (k, p) = (g.group_rows(), g.out_pixels());
+    let og = g.out_channels / g.groups;
+    let img_len = g.in_channels * g.in_size.0 * g.in_size.1;
+    let w_t: Vec<Vec<T>> = w.chunks(og * k).map(|w_g| transpose2d(w_g, k)).collect();
+    let mut d_dx = Vec::with_capacity(x.len());
+    let mut d_dw = vec![T::zero(); w.len()];
+    for (img, d_out) in x.chunks(img_len).zip(d_dt.chunks(g.out_channels * p)) {
+        let cols = im2col(img, g);
+        let mut d_cols = Vec::with_capacity(cols.len());
+        for (gi, (cols_g, d_out_g)) in cols.chunks(k * p).zip(d_out.chunks(og * p)).enumerate() {
+            let d_dw_g = matmul(d_out_g, &transpose2d(cols_g, p), og, p, k);
+            for (acc, d) in d_dw[gi * og * k..(gi + 1) * og * k].iter_mut().zip(d_dw_g) {
+                *acc = *acc + d;
+            }
+            d_cols.extend(matmul(&w_t[gi], d_out_g, k, og, p));
+        }
+        d_dx.extend(col2im(&d_cols, g));
+    }
+    (d_dx, d_dw)
+}
+
+/// Grad of `max_pool`, routing each element of `d_dt` to the input element its window took the
+/// max of.
+pub(crate) fn max_pool_grad<T: NumDtype>(d_dt: &[T], args: &[usize], len: usize) -> Vec<T> {
+    // t[w] = a[args[w]]
+    // d_da[args[w]] += d_dt[w]
+    let mut d_da = vec![T::zero(); len];
+    for (g, i) in d_dt.iter().zip(args) {
+        d_da[*i] = d_da[*i] + *g;
+    }
+    d_da
+}
+
+/// Grad of `avg_pool`, spreading each element of `d_dt` evenly over its window.
+pub(crate) fn avg_pool_grad<T: NumDtype>(
+    d_dt: &[T],
+    windows: &[Vec<usize>],
+    divisor: Option<usize>,
+    len: usize,
+) -> Vec<T> {
+    // t[w] = sum(a[windows[w]]) / n
+    // d_da[windows[w]] += d_dt[w] / n
+    let mut d_da = vec![T::zero(); len];
+    for (g, win) in d_dt.iter().zip(windows) {
+        let n = T::from_usize(divisor.unwrap_or(win.len()))
+            .expect("Failed to cast window size to dtype");
+        for i in win {
+            d_da[*i] = d_da[*i] + *g / n;
+        }
+    }
+    d_da
+}
+
+/// Grads of `norm_affine(normalize(a))` wrt `a`, the weight and the bias, given the normalised
+/// `x_hat` and the variance of each group. With `fixed_stats` the mean and variance are constants,
+/// as for batch norm in eval mode, rather than functions of `a`.
+pub(crate) fn norm_grad<T: FloatDtype>(
+    x_hat: &[T],
+    d_dt: &[T],
+    l: &NormLayout,
+    var: &[T],
+    eps: T,
+    weight: Option<&[T]>,
+    fixed_stats: bool,
+) -> (Vec<T>, Vec<T>, Vec<T>) {
+    // t = x_hat * w + b, x_hat = (a - mean) / sqrt(var + eps)
+    // d_db[c] = sum(d_dt), d_dw[c] = sum(d_dt * x_hat)
+    // d_da = (g - mean(g) - x_hat * mean(g * x_hat)) / sqrt(var + eps), for g = d_dt * w
+    let mut d_dw = vec![T::zero(); l.n_channels];
+    let mut d_db = vec![T::zero(); l.n_channels];
+    for (i, (g, x)) in d_dt.iter().zip(x_hat).enumerate() {
+        let c = l.channel(i);
+        d_dw[c] = d_dw[c] + *g * *x;
+        d_db[c] = d_db[c] + *g;
+    }
+    let d_dx_hat: Vec<T> = d_dt
+        .iter()
+        .enumerate()
+        .map(|(i, g)| weight.map_or(*g, |w| *g * w[l.channel(i)]))
+        .collect();
+    let rstd: Vec<T> = var.iter().map(|v| (*v + eps).sqrt().recip()).collect();
+    let d_da = if fixed_stats {
+        d_dx_hat
+            .iter()
+            .enumerate()
+            .map(|(i, g)| *g * rstd[l.group(i)])
+            .collect()
+    } else {
+        let g_mean = if l.centered {
+            l.group_mean(&d_dx_hat)
+        } else {
+            vec![T::zero(); l.n_groups]
+        };
+        let gx_mean = l.group_mean(&el_mul(&d_dx_hat, x_hat));
+        d_dx_hat
+            .iter()
+            .zip(x_hat)
+            .enumerate()
+            .map(|(i, (g, x))| {
+                let k = l.group(i);
+                (*g - g_mean[k] - *x * gx_mean[k]) * rstd[k]
+            })
+            .collect()
+    };
+    (d_da, d_dw, d_db)
+}
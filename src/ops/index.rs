@@ -0,0 +1,150 @@
+use crate::tensor::TensorBox;
+use crate::{
+    dtype::NumDtype,
+    ops::Op,
+    shape::{Dims, Narrow, ReduceAxis, Shape},
+    tensor::Tensor,
+};
+use std::rc::Rc;
+
+/// Strided view of `start..start + len` along `axis`. `So` is the output shape, which either
+/// keeps the axis with size `len` (narrow) or drops it when `len == 1` (index/select).
+#[derive(Debug)]
+pub struct NarrowStruct<T: NumDtype, S: Dims, So: Dims> {
+    data: Tensor<T, S>,
+    axis: usize,
+    start: usize,
+    len: usize,
+    out_shape: So,
+}
+
+impl<T: NumDtype, S: Dims, So: Shape> NarrowStruct<T, S, So> {
+    pub(crate) fn new(data: Tensor<T, S>, axis: usize, start: usize, len: usize) -> Self {
+        Self::with_shape(data, axis, start, len, Default::default())
+    }
+}
+
+impl<T: NumDtype, S: Dims, So: Dims> NarrowStruct<T, S, So> {
+    pub(crate) fn with_shape(
+        data: Tensor<T, S>,
+        axis: usize,
+        start: usize,
+        len: usize,
+        out_shape: So,
+    ) -> Self {
+        Self {
+            data,
+            axis,
+            start,
+            len,
+            out_shape,
+        }
+    }
+}
+
+impl<T: NumDtype, S: Dims, So: Dims> Op for NarrowStruct<T, S, So> {
+    type Produces = Tensor<T, So>;
+
+    fn propogate_grad(&self, _t: &Self::Produces) {
+        // Storage is shared with the operand, therefore no grad propogation occurs
+    }
+
+    fn recompute(&self, _t: &Self::Produces) {}
+
+    fn forward(self) -> Self::Produces {
+        let in_shape = self.data.shape.dims();
+        let mut layout = self
+            .data
+            .data
+            .layout(&in_shape)
+            .narrow(self.axis, self.start, self.len);
+        if self.out_shape.dims().len() < in_shape.len() {
+            layout = layout.remove_axis(self.axis);
+        }
+        let data = self.data.data.view(layout);
+        let shape = self.out_shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.data.id, &self.data)]
+    }
+}
+
+impl<T: NumDtype, S: Shape> Tensor<T, S> {
+    /// Take element `IDX` of the leading axis, dropping that axis.
+    pub fn index<const IDX: usize>(self) -> Tensor<T, <S as ReduceAxis<0>>::Reduced>
+    where
+        S: ReduceAxis<0> + Narrow<0, IDX, 1>,
+    {
+        NarrowStruct::new(self, 0, IDX, 1).forward()
+    }
+
+    /// Take `START..START + LEN` along `AXIS`. The range is bounds checked at compile time.
+    pub fn narrow<const AXIS: usize, const START: usize, const LEN: usize>(
+        self,
+    ) -> Tensor<T, <S as Narrow<AXIS, START, LEN>>::Output>
+    where
+        S: Narrow<AXIS, START, LEN>,
+    {
+        NarrowStruct::new(self, AXIS, START, LEN).forward()
+    }
+
+    /// Take element `idx` along `AXIS`, dropping that axis. Unlike `index`, `idx` is only known
+    /// at runtime and panics if out of bounds.
+    pub fn select<const AXIS: usize>(
+        self,
+        idx: usize,
+    ) -> Tensor<T, <S as ReduceAxis<AXIS>>::Reduced>
+    where
+        S: ReduceAxis<AXIS>,
+    {
+        assert!(
+            idx < S::shape()[AXIS],
+            "Index {} is out of bounds for axis {} with size {}",
+            idx,
+            AXIS,
+            S::shape()[AXIS]
+        );
+        NarrowStruct::new(self, AXIS, idx, 1).forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_index_and_narrow() {
+        let x = Tensor::new([[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11]]);
+        let row: Tensor<i32, (I<4>,)> = x.clone().index::<1>();
+        assert_eq!(*row.borrow_value(), [4, 5, 6, 7]);
+        let cols: Tensor<i32, (I<3>, I<2>)> = x.clone().narrow::<1, 2, 2>();
+        assert_eq!(*cols.borrow_value(), [2, 3, 6, 7, 10, 11]);
+        let col: Tensor<i32, (I<3>,)> = x.select::<1>(3);
+        assert_eq!(*col.borrow_value(), [3, 7, 11]);
+    }
+
+    #[test]
+    fn test_narrow_grad() {
+        let x = Tensor::new_with_grad([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let n = x.clone().narrow::<1, 1, 2>();
+        let s = x.clone().select::<0>(1);
+        (n.sum_axis::<1>() * s.narrow::<0, 0, 2>())
+            .reduce_sum()
+            .backward();
+        // sum_i(x[i, 1] + x[i, 2]) * x[1, i] for i in 0..2
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[0.0, 4.0, 4.0, 5.0, 5.0 + 11.0, 5.0][..])
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_select_out_of_bounds() {
+        let x = Tensor::new([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        x.select::<0>(2);
+    }
+}
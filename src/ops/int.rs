@@ -0,0 +1,215 @@
+use crate::ops::vec::{broadcast, el_map2, el_unary};
+use crate::tensor::TensorBox;
+use crate::tensor_data::TensorData;
+use crate::{
+    dtype::IntDtype,
+    ops::Op,
+    shape::{BroadcastTo, Dims},
+    tensor::Tensor,
+};
+use std::ops::{BitAnd, BitOr, BitXor, Shl, Shr};
+use std::rc::Rc;
+
+/// Elementwise ops which only exist for integers, e.g. floor division and the bitwise ops. These
+/// are piecewise constant or not differentiable, so like comparisons they never propogate grad.
+#[derive(Debug)]
+pub struct ElIntStruct<T: IntDtype, S1: Dims, S2: Dims>(
+    Tensor<T, S1>,
+    Tensor<T, S2>,
+    fn(T, T) -> T,
+);
+
+impl<T: IntDtype, S1: Dims, S2: Dims> ElIntStruct<T, S1, S2> {
+    fn compute(&self, out_shape: &[usize]) -> Vec<T> {
+        let a = self.0.borrow_value();
+        let b = self.1.borrow_value();
+        el_map2(
+            self.2,
+            &broadcast(&a, &self.0.shape.dims(), out_shape),
+            &broadcast(&b, &self.1.shape.dims(), out_shape),
+        )
+    }
+}
+
+impl<T: IntDtype, S1: Dims, S2: Dims> Op for ElIntStruct<T, S1, S2>
+where
+    S1: BroadcastTo<S2>,
+{
+    type Produces = Tensor<T, <S1 as BroadcastTo<S2>>::Output>;
+
+    fn propogate_grad(&self, _t: &Self::Produces) {
+        // t = f(a, b) is piecewise constant
+        // Don't propogate grad
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute(&t.shape.dims()))
+    }
+
+    fn forward(self) -> Self::Produces {
+        let shape = self.0.shape.broadcast_shape(&self.1.shape);
+        let data = TensorData::new(self.compute(&shape.dims()), false);
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![
+            TensorBox::new(self.0.id, &self.0),
+            TensorBox::new(self.1.id, &self.1),
+        ]
+    }
+}
+
+/// Elementwise integer ops of a single tensor, i.e. shifts by a constant and bitwise not.
+#[derive(Debug)]
+pub struct ElIntUnaryStruct<T: IntDtype, S: Dims>(Tensor<T, S>, usize, fn(T, usize) -> T);
+
+impl<T: IntDtype, S: Dims> ElIntUnaryStruct<T, S> {
+    fn compute(&self) -> Vec<T> {
+        el_unary(|a: &T| self.2(*a, self.1), &self.0.borrow_value())
+    }
+}
+
+impl<T: IntDtype, S: Dims> Op for ElIntUnaryStruct<T, S> {
+    type Produces = Tensor<T, S>;
+
+    fn propogate_grad(&self, _t: &Self::Produces) {
+        // t = f(a) isn't differentiable
+        // Don't propogate grad
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let data = TensorData::new(self.compute(), false);
+        let shape = self.0.shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.0.id, &self.0)]
+    }
+}
+
+macro_rules! impl_int_method {
+    ($tf:ident, $f:expr) => {
+        pub fn $tf<S2: Dims>(
+            &self,
+            other: Tensor<T, S2>,
+        ) -> Tensor<T, <S1 as BroadcastTo<S2>>::Output>
+        where
+            S1: BroadcastTo<S2>,
+        {
+            ElIntStruct(self.shallow_clone(), other, $f).forward()
+        }
+    };
+}
+
+// Division rounds towards negative infinity and remainders have the sign of the divisor, e.g.
+// `-7 // 2 = -4` and `-7 % 2 = 1`, rather than truncating like the `/` and `%` operators. Both
+// panic on division by zero. Similarly `<<` and `>>` panic unless the shift is less than the bit
+// width of the dtype, in release builds too, where the primitive shifts would mask it instead.
+impl<T: IntDtype, S1: Dims> Tensor<T, S1> {
+    impl_int_method!(floor_div, |a, b| a.div_floor(&b));
+    impl_int_method!(remainder, |a, b| a.mod_floor(&b));
+
+    pub fn bitwise_not(&self) -> Tensor<T, S1> {
+        ElIntUnaryStruct(self.shallow_clone(), 0, |a, _| !a).forward()
+    }
+}
+
+macro_rules! impl_int_op {
+    ($t:ident, $tf:ident, $f:expr) => {
+        impl<T: IntDtype, S1: Dims, S2: Dims> $t<Tensor<T, S2>> for Tensor<T, S1>
+        where
+            S1: BroadcastTo<S2>,
+        {
+            type Output = Tensor<T, <S1 as BroadcastTo<S2>>::Output>;
+            fn $tf(self, other: Tensor<T, S2>) -> Self::Output {
+                ElIntStruct(self, other, $f).forward()
+            }
+        }
+    };
+}
+
+impl_int_op!(BitAnd, bitand, |a, b| a & b);
+impl_int_op!(BitOr, bitor, |a, b| a | b);
+impl_int_op!(BitXor, bitxor, |a, b| a ^ b);
+
+fn check_shift<T: IntDtype>(n: usize) {
+    let bits = T::zero().count_zeros() as usize;
+    assert!(
+        n < bits,
+        "Cannot shift by {}, as the dtype has {} bits",
+        n,
+        bits
+    );
+}
+
+impl<T: IntDtype, S: Dims> Shl<usize> for Tensor<T, S> {
+    type Output = Self;
+    fn shl(self, n: usize) -> Self {
+        check_shift::<T>(n);
+        ElIntUnaryStruct(self, n, |a, n| a << n).forward()
+    }
+}
+
+impl<T: IntDtype, S: Dims> Shr<usize> for Tensor<T, S> {
+    type Output = Self;
+    fn shr(self, n: usize) -> Self {
+        check_shift::<T>(n);
+        ElIntUnaryStruct(self, n, |a, n| a >> n).forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_floor_div_and_remainder() {
+        let a: Tensor<i32, (I<2>, I<2>)> = Tensor::new([[7, -7], [6, -1]]);
+        let b: Tensor<i32, (I<2>,)> = Tensor::new([2, -3]);
+        assert_eq!(*a.floor_div(b.clone()).borrow_value(), [3, 2, 3, 0]);
+        assert_eq!(*a.remainder(b).borrow_value(), [1, -1, 0, -1]);
+
+        let x: Tensor<u8, (I<3>,)> = Tensor::new([200, 7, 9]);
+        let y = x.floor_div(Tensor::new([3]));
+        assert_eq!(*y.borrow_value(), [66, 2, 3]);
+        x.replace_data_with(vec![30, 1, 2]);
+        y.recompute();
+        assert_eq!(*y.borrow_value(), [10, 0, 0]);
+    }
+
+    #[test]
+    fn test_bitwise_ops() {
+        let a: Tensor<u8, (I<3>,)> = Tensor::new([0b1100, 0b1010, 0xff]);
+        let b: Tensor<u8, (I<3>,)> = Tensor::new([0b1010, 0b0110, 0x0f]);
+        assert_eq!(
+            *(a.clone() & b.clone()).borrow_value(),
+            [0b1000, 0b0010, 0x0f]
+        );
+        assert_eq!(
+            *(a.clone() | b.clone()).borrow_value(),
+            [0b1110, 0b1110, 0xff]
+        );
+        assert_eq!(*(a.clone() ^ b).borrow_value(), [0b0110, 0b1100, 0xf0]);
+        assert_eq!(*a.bitwise_not().borrow_value(), [0xf3, 0xf5, 0x00]);
+        assert_eq!(*(a.clone() << 2).borrow_value(), [0b110000, 0b101000, 0xfc]);
+        assert_eq!(*(a >> 2).borrow_value(), [0b11, 0b10, 0x3f]);
+
+        let i: Tensor<i64, (I<2>,)> = Tensor::new([-8, 5]);
+        assert_eq!(*(i.clone() >> 1).borrow_value(), [-4, 2]);
+        assert_eq!(*i.bitwise_not().borrow_value(), [7, -6]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot shift by 8, as the dtype has 8 bits")]
+    fn test_shift_overflow() {
+        let a: Tensor<u8, (I<1>,)> = Tensor::new([1]);
+        let _ = a << 8;
+    }
+}
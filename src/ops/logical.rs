@@ -0,0 +1,319 @@
+use crate::ops::grad::{masked_fill_grad, where_cond_grad};
+use crate::ops::vec::{broadcast, el_map2, masked_fill, where_cond};
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{
+    dtype::{Dtype, NumDtype},
+    ops::Op,
+    shape::{BroadcastTo, Dims},
+    tensor::Tensor,
+};
+use std::ops::Not;
+use std::rc::Rc;
+
+/// Elementwise ops with a `bool` output, i.e. comparisons and the logical ops on masks. These are
+/// kept in the graph so that recomputing it also recomputes masks, but never propogate grad.
+#[derive(Debug)]
+pub struct ElBoolStruct<T: Dtype, S1: Dims, S2: Dims>(
+    Tensor<T, S1>,
+    Tensor<T, S2>,
+    fn(T, T) -> bool,
+);
+
+impl<T: Dtype, S1: Dims, S2: Dims> ElBoolStruct<T, S1, S2> {
+    fn compute(&self, out_shape: &[usize]) -> Vec<bool> {
+        let a = self.0.borrow_value();
+        let b = self.1.borrow_value();
+        el_map2(
+            self.2,
+            &broadcast(&a, &self.0.shape.dims(), out_shape),
+            &broadcast(&b, &self.1.shape.dims(), out_shape),
+        )
+    }
+}
+
+impl<T: Dtype, S1: Dims, S2: Dims> Op for ElBoolStruct<T, S1, S2>
+where
+    S1: BroadcastTo<S2>,
+{
+    type Produces = Tensor<bool, <S1 as BroadcastTo<S2>>::Output>;
+
+    fn propogate_grad(&self, _t: &Self::Produces) {
+        // t = f(a, b) is piecewise constant
+        // Don't propogate grad
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute(&t.shape.dims()))
+    }
+
+    fn forward(self) -> Self::Produces {
+        let shape = self.0.shape.broadcast_shape(&self.1.shape);
+        let data = TensorData::new(self.compute(&shape.dims()), false);
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![
+            TensorBox::new(self.0.id, &self.0),
+            TensorBox::new(self.1.id, &self.1),
+        ]
+    }
+}
+
+#[derive(Debug)]
+pub struct ElNotStruct<S: Dims>(Tensor<bool, S>);
+
+impl<S: Dims> Op for ElNotStruct<S> {
+    type Produces = Tensor<bool, S>;
+
+    fn propogate_grad(&self, _t: &Self::Produces) {
+        // t = !a
+        // Don't propogate grad
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data
+            .replace(self.0.borrow_value().iter().map(|x| !x).collect())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let value = self.0.borrow_value().iter().map(|x| !x).collect();
+        let data = TensorData::new(value, false);
+        let shape = self.0.shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.0.id, &self.0)]
+    }
+}
+
+/// Fills `value` where a mask, broadcast to the shape of the input, is true.
+#[derive(Debug)]
+pub struct MaskedFillStruct<T: NumDtype, S: Dims, Sm: Dims>(Tensor<T, S>, Tensor<bool, Sm>, T);
+
+impl<T: NumDtype, S: Dims, Sm: Dims> MaskedFillStruct<T, S, Sm> {
+    fn mask(&self) -> Vec<bool> {
+        let mask = self.1.borrow_value();
+        broadcast(&mask, &self.1.shape.dims(), &self.0.shape.dims()).into_owned()
+    }
+
+    fn compute(&self) -> Vec<T> {
+        masked_fill(&self.0.borrow_value(), &self.mask(), self.2)
+    }
+}
+
+impl<T: NumDtype, S: Dims, Sm: Dims> Op for MaskedFillStruct<T, S, Sm> {
+    type Produces = Tensor<T, S>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = value if mask else a
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            self.0.update_grad(masked_fill_grad(d_dt, &self.mask()));
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let data = TensorData::new(self.compute(), self.0.requires_grad());
+        let shape = self.0.shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![
+            TensorBox::new(self.0.id, &self.0),
+            TensorBox::new(self.1.id, &self.1),
+        ]
+    }
+}
+
+/// Selects from `a` where the condition is true and from `b` elsewhere, routing grads to the
+/// selected branch.
+#[derive(Debug)]
+pub struct WhereStruct<T: NumDtype, S: Dims>(Tensor<bool, S>, Tensor<T, S>, Tensor<T, S>);
+
+impl<T: NumDtype, S: Dims> WhereStruct<T, S> {
+    fn compute(&self) -> Vec<T> {
+        where_cond(
+            &self.0.borrow_value(),
+            &self.1.borrow_value(),
+            &self.2.borrow_value(),
+        )
+    }
+}
+
+impl<T: NumDtype, S: Dims> Op for WhereStruct<T, S> {
+    type Produces = Tensor<T, S>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = a if cond else b
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let (d_da, d_db) = where_cond_grad(d_dt, &self.0.borrow_value());
+            self.1.update_grad(d_da);
+            self.2.update_grad(d_db);
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let data = TensorData::new(
+            self.compute(),
+            self.1.requires_grad() || self.2.requires_grad(),
+        );
+        let shape = self.0.shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![
+            TensorBox::new(self.0.id, &self.0),
+            TensorBox::new(self.1.id, &self.1),
+            TensorBox::new(self.2.id, &self.2),
+        ]
+    }
+}
+
+macro_rules! impl_bool_method {
+    ($t:ty, $tf:ident, $f:expr) => {
+        pub fn $tf<S2: Dims>(
+            &self,
+            other: Tensor<$t, S2>,
+        ) -> Tensor<bool, <S1 as BroadcastTo<S2>>::Output>
+        where
+            S1: BroadcastTo<S2>,
+        {
+            ElBoolStruct(self.shallow_clone(), other, $f).forward()
+        }
+    };
+}
+
+// Elementwise comparisons, broadcasting the operands. These are methods rather than
+// `PartialOrd`/`PartialEq` impls, as those have to return a single `bool`.
+impl<T: Dtype, S1: Dims> Tensor<T, S1> {
+    impl_bool_method!(T, gt, |a, b| a > b);
+    impl_bool_method!(T, ge, |a, b| a >= b);
+    impl_bool_method!(T, lt, |a, b| a < b);
+    impl_bool_method!(T, le, |a, b| a <= b);
+    impl_bool_method!(T, eq, |a, b| a == b);
+    impl_bool_method!(T, ne, |a, b| a != b);
+}
+
+impl<S1: Dims> Tensor<bool, S1> {
+    impl_bool_method!(bool, logical_and, |a, b| a && b);
+    impl_bool_method!(bool, logical_or, |a, b| a || b);
+    impl_bool_method!(bool, logical_xor, |a, b| a ^ b);
+
+    pub fn logical_not(&self) -> Self {
+        ElNotStruct(self.shallow_clone()).forward()
+    }
+
+    /// `a` where this is true, else `b`. Grads only flow to the selected branch.
+    pub fn where_cond<T: NumDtype>(&self, a: Tensor<T, S1>, b: Tensor<T, S1>) -> Tensor<T, S1> {
+        assert_eq!(a.shape, self.shape, "Shapes of where operands do not match");
+        assert_eq!(b.shape, self.shape, "Shapes of where operands do not match");
+        WhereStruct(self.shallow_clone(), a, b).forward()
+    }
+}
+
+impl<S: Dims> Not for Tensor<bool, S> {
+    type Output = Self;
+    fn not(self) -> Self {
+        ElNotStruct(self).forward()
+    }
+}
+
+impl<T: NumDtype, S: Dims> Tensor<T, S> {
+    /// Replace elements with `value` where `mask`, which is broadcast to this shape, is true.
+    pub fn masked_fill<Sm>(&self, mask: Tensor<bool, Sm>, value: T) -> Self
+    where
+        Sm: BroadcastTo<S, Output = S>,
+    {
+        MaskedFillStruct(self.shallow_clone(), mask, value).forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_comparisons() {
+        let x = Tensor::new([[1.0, 2.0], [3.0, 4.0]]);
+        let t = Tensor::new([2.0, 3.0]);
+        assert_eq!(*x.gt(t.clone()).borrow_value(), [false, false, true, true]);
+        assert_eq!(*x.ge(t.clone()).borrow_value(), [false, false, true, true]);
+        assert_eq!(*x.lt(t.clone()).borrow_value(), [true, true, false, false]);
+        assert_eq!(*x.le(t.clone()).borrow_value(), [true, true, false, false]);
+        assert_eq!(
+            *x.eq(t.clone()).borrow_value(),
+            [false, false, false, false]
+        );
+        assert_eq!(*x.ne(t).borrow_value(), [true, true, true, true]);
+
+        let a = Tensor::new([true, true, false, false]);
+        let b = Tensor::new([true, false, true, false]);
+        assert_eq!(
+            *a.logical_and(b.clone()).borrow_value(),
+            [true, false, false, false]
+        );
+        assert_eq!(
+            *a.logical_or(b.clone()).borrow_value(),
+            [true, true, true, false]
+        );
+        assert_eq!(
+            *a.logical_xor(b.clone()).borrow_value(),
+            [false, true, true, false]
+        );
+        assert_eq!(*a.logical_not().borrow_value(), [false, false, true, true]);
+        assert_eq!(*(!b).borrow_value(), [false, true, false, true]);
+    }
+
+    #[test]
+    fn test_masked_fill_and_where() {
+        let x = Tensor::new_with_grad([[1.0, -2.0], [-3.0, 4.0]]);
+        let y = Tensor::new_with_grad([[10.0, 20.0], [30.0, 40.0]]);
+        let zero: Tensor<f64, (I<1>,)> = Tensor::new([0.0]);
+        let pos = x.gt(zero);
+
+        let filled = x.masked_fill(Tensor::new([true, false]), 9.0);
+        assert_eq!(*filled.borrow_value(), [9.0, -2.0, 9.0, 4.0]);
+        filled.reduce_sum().backward();
+        assert_eq!(*x.borrow_grad().unwrap(), [0.0, 1.0, 0.0, 1.0]);
+
+        // Start from fresh grads
+        let x = Tensor::new_with_grad([[1.0, -2.0], [-3.0, 4.0]]);
+        let selected = pos.where_cond(x.clone(), y.clone());
+        assert_eq!(*selected.borrow_value(), [1.0, 20.0, 30.0, 4.0]);
+        (selected * Tensor::new([[1.0, 2.0], [3.0, 4.0]]))
+            .reduce_sum()
+            .backward();
+        assert_eq!(*x.borrow_grad().unwrap(), [1.0, 0.0, 0.0, 4.0]);
+        assert_eq!(*y.borrow_grad().unwrap(), [0.0, 2.0, 3.0, 0.0]);
+    }
+
+    #[test]
+    fn test_recompute_mask() {
+        let x: Tensor<f64, (I<3>,)> = Tensor::new([1.0, -1.0, 2.0]);
+        let x_clone = x.shallow_clone();
+        let relu = x
+            .gt(Tensor::new([0.0]))
+            .where_cond(x, Tensor::new([0.0; 3]));
+        assert_eq!(*relu.borrow_value(), [1.0, 0.0, 2.0]);
+        x_clone.replace_data_with(vec![-5.0, 3.0, 0.5]);
+        relu.recompute();
+        assert_eq!(*relu.borrow_value(), [0.0, 3.0, 0.5]);
+    }
+}
@@ -1,7 +1,29 @@
-mod grad;
-mod tensor;
+mod activation;
+mod concat;
+mod conv;
+mod dropout;
+mod float;
+pub(crate) mod gather;
+pub(crate) mod grad;
+pub(crate) mod index;
+mod int;
+mod logical;
+pub(crate) mod norm;
+pub(crate) mod pad;
+mod permute;
+mod pool;
+pub(crate) mod reduce;
+mod scalar;
+pub(crate) mod softmax;
+pub(crate) mod tensor;
 pub(crate) mod vec;
 
+pub use concat::{Concatenates, Stacks};
+pub use conv::Convolves;
+pub use gather::{Gathers, IndexSelects};
+pub use pad::PadMode;
+pub use tensor::{Max, Min};
+
 use crate::tensor::TensorBox;
 
 pub(crate) trait Op: std::fmt::Debug {
@@ -9,5 +31,5 @@ pub(crate) trait Op: std::fmt::Debug {
     fn propogate_grad(&self, t: &Self::Produces);
     fn recompute(&self, t: &Self::Produces);
     fn forward(self) -> Self::Produces;
-    fn operands(&self) -> Vec<TensorBox>;
+    fn operands(&self) -> Vec<TensorBox<'_>>;
 }
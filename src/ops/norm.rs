@@ -0,0 +1,343 @@
+use crate::ops::grad::norm_grad;
+use crate::ops::vec::{norm_affine, norm_stats, normalize, NormLayout};
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{
+    dtype::FloatDtype,
+    module::Mode,
+    ops::Op,
+    shape::{ChannelDim, LastDim, Shape, I},
+    tensor::Tensor,
+};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Where a normalisation op gets the mean and variance of each group from.
+#[derive(Debug)]
+pub(crate) enum NormStats<T: FloatDtype, const C: usize> {
+    /// The stats of the input.
+    Input,
+    /// Running stats, e.g. of batch norm. In train mode the stats of the input are used, and
+    /// folded into the running stats on each forward pass as
+    /// `running = (1 - momentum) * running + momentum * stat`. In eval mode the running stats are
+    /// used as they are. `mode` is shared with the layer and read on each compute, so switching
+    /// the layer's mode also switches graphs which were traced with it. The running variance is
+    /// unbiased. Only valid for layouts with a group per channel.
+    Running {
+        running_mean: Tensor<T, (I<C>,)>,
+        running_var: Tensor<T, (I<C>,)>,
+        momentum: T,
+        mode: Rc<Cell<Mode>>,
+    },
+}
+
+impl<T: FloatDtype, const C: usize> NormStats<T, C> {
+    fn is_fixed(&self) -> bool {
+        matches!(self, NormStats::Running { mode, .. } if mode.get() == Mode::Eval)
+    }
+}
+
+/// Normalises each group of `x` by its mean and variance, then applies a per channel affine
+/// transform. The backward is fused rather than built from the elementwise ops.
+#[derive(Debug)]
+pub struct NormStruct<T: FloatDtype, S: Shape, const C: usize> {
+    x: Tensor<T, S>,
+    weight: Tensor<T, (I<C>,)>,
+    bias: Option<Tensor<T, (I<C>,)>>,
+    layout: NormLayout,
+    eps: T,
+    stats: NormStats<T, C>,
+}
+
+impl<T: FloatDtype, S: Shape, const C: usize> NormStruct<T, S, C> {
+    fn stats(&self) -> (Vec<T>, Vec<T>) {
+        match &self.stats {
+            NormStats::Running {
+                running_mean,
+                running_var,
+                ..
+            } if self.stats.is_fixed() => (
+                running_mean.borrow_value().to_vec(),
+                running_var.borrow_value().to_vec(),
+            ),
+            _ => norm_stats(&self.x.borrow_value(), &self.layout),
+        }
+    }
+
+    fn x_hat(&self, mean: &[T], var: &[T]) -> Vec<T> {
+        normalize(&self.x.borrow_value(), &self.layout, mean, var, self.eps)
+    }
+
+    fn compute(&self) -> Vec<T> {
+        let (mean, var) = self.stats();
+        // Fold the stats of the input into the running stats in train mode
+        match &self.stats {
+            NormStats::Running {
+                running_mean,
+                running_var,
+                momentum,
+                ..
+            } if !self.stats.is_fixed() => {
+                let n = self.layout.group_size();
+                let correction = if n > 1 {
+                    T::from_usize(n).unwrap() / T::from_usize(n - 1).unwrap()
+                } else {
+                    T::one()
+                };
+                let update = |r: &Tensor<T, (I<C>,)>, s: &[T], k: T| {
+                    let new: Vec<T> = r
+                        .borrow_value()
+                        .iter()
+                        .zip(s)
+                        .map(|(r, s)| (T::one() - *momentum) * *r + *momentum * *s * k)
+                        .collect();
+                    r.replace_data_with(new);
+                };
+                update(running_mean, &mean, T::one());
+                update(running_var, &var, correction);
+            }
+            _ => {}
+        }
+        let x_hat = self.x_hat(&mean, &var);
+        let bias = self.bias.as_ref().map(|b| b.borrow_value());
+        norm_affine(
+            &x_hat,
+            &self.layout,
+            Some(&self.weight.borrow_value()),
+            bias.as_deref(),
+        )
+    }
+}
+
+impl<T: FloatDtype, S: Shape, const C: usize> Op for NormStruct<T, S, C> {
+    type Produces = Tensor<T, S>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = (x - mean) / sqrt(var + eps) * weight + bias
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let (mean, var) = self.stats();
+            let (d_dx, d_dw, d_db) = norm_grad(
+                &self.x_hat(&mean, &var),
+                d_dt,
+                &self.layout,
+                &var,
+                self.eps,
+                Some(&self.weight.borrow_value()),
+                self.stats.is_fixed(),
+            );
+            self.x.update_grad(d_dx);
+            self.weight.update_grad(d_dw);
+            if let Some(b) = &self.bias {
+                b.update_grad(d_db);
+            }
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let requires_grad = self.x.requires_grad()
+            || self.weight.requires_grad()
+            || self.bias.as_ref().is_some_and(|b| b.requires_grad());
+        let data = TensorData::new(self.compute(), requires_grad);
+        unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        let mut operands = vec![
+            TensorBox::new(self.x.id, &self.x),
+            TensorBox::new(self.weight.id, &self.weight),
+        ];
+        if let Some(b) = &self.bias {
+            operands.push(TensorBox::new(b.id, b));
+        }
+        operands
+    }
+}
+
+impl<T: FloatDtype, S: Shape> Tensor<T, S> {
+    /// Normalise over the last axis to zero mean and unit variance, then scale by `weight` and
+    /// shift by `bias`.
+    pub fn layer_norm<const D: usize>(
+        self,
+        weight: Tensor<T, (I<D>,)>,
+        bias: Tensor<T, (I<D>,)>,
+        eps: T,
+    ) -> Self
+    where
+        S: LastDim<D>,
+    {
+        NormStruct {
+            layout: NormLayout::last_axis(S::NUM_ELS, D, true),
+            x: self,
+            weight,
+            bias: Some(bias),
+            eps,
+            stats: NormStats::Input,
+        }
+        .forward()
+    }
+
+    /// Divide by the root mean square over the last axis, then scale by `weight`.
+    pub fn rms_norm<const D: usize>(self, weight: Tensor<T, (I<D>,)>, eps: T) -> Self
+    where
+        S: LastDim<D>,
+    {
+        NormStruct {
+            layout: NormLayout::last_axis(S::NUM_ELS, D, false),
+            x: self,
+            weight,
+            bias: None,
+            eps,
+            stats: NormStats::Input,
+        }
+        .forward()
+    }
+
+    /// Split the channels of `(N, C, ...)` inputs into `groups` groups, normalise each group of
+    /// each sample, then apply a per channel `weight` and `bias`. Panics if `groups` doesn't
+    /// divide `C`.
+    pub fn group_norm<const C: usize>(
+        self,
+        groups: usize,
+        weight: Tensor<T, (I<C>,)>,
+        bias: Tensor<T, (I<C>,)>,
+        eps: T,
+    ) -> Self
+    where
+        S: ChannelDim<C>,
+    {
+        assert!(
+            groups > 0 && C.is_multiple_of(groups),
+            "{} channels cannot be split into {} groups",
+            C,
+            groups
+        );
+        NormStruct {
+            layout: NormLayout::channel_groups(S::shape(), groups),
+            x: self,
+            weight,
+            bias: Some(bias),
+            eps,
+            stats: NormStats::Input,
+        }
+        .forward()
+    }
+
+    /// Normalise each channel of `(N, C, ...)` inputs over the batch and any other axes, then
+    /// apply a per channel `weight` and `bias`. This uses the stats of the batch; the
+    /// `nn::BatchNorm` layer also tracks running stats for eval mode.
+    pub fn batch_norm<const C: usize>(
+        self,
+        weight: Tensor<T, (I<C>,)>,
+        bias: Tensor<T, (I<C>,)>,
+        eps: T,
+    ) -> Self
+    where
+        S: ChannelDim<C>,
+    {
+        self.batch_norm_with_stats(weight, bias, eps, NormStats::Input)
+    }
+
+    pub(crate) fn batch_norm_with_stats<const C: usize>(
+        self,
+        weight: Tensor<T, (I<C>,)>,
+        bias: Tensor<T, (I<C>,)>,
+        eps: T,
+        stats: NormStats<T, C>,
+    ) -> Self
+    where
+        S: ChannelDim<C>,
+    {
+        NormStruct {
+            layout: NormLayout::per_channel(S::shape()),
+            x: self,
+            weight,
+            bias: Some(bias),
+            eps,
+            stats,
+        }
+        .forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+    use crate::test_util::check_grad;
+
+    #[test]
+    fn test_layer_norm() {
+        let x = Tensor::new([[1.0, 2.0, 3.0, 4.0], [1e8, 1e8, 1e8 + 2.0, 1e8 + 2.0]]);
+        let y = x.layer_norm(
+            Tensor::new([1.0, 1.0, 1.0, 2.0]),
+            Tensor::new([0.0, 0.0, 0.0, 1.0]),
+            0.0,
+        );
+        let s = 5f64.sqrt();
+        let expected = [
+            -3.0 / s,
+            -1.0 / s,
+            1.0 / s,
+            6.0 / s + 1.0,
+            -1.0,
+            -1.0,
+            1.0,
+            3.0,
+        ];
+        for (a, b) in y.borrow_value().iter().zip(expected) {
+            assert!((a - b).abs() < 1e-12, "{} != {}", a, b);
+        }
+
+        let y = Tensor::new([[3.0, 4.0]]).rms_norm(Tensor::new([1.0, 2.0]), 0.0);
+        let r = 12.5f64.sqrt();
+        assert_eq!(*y.borrow_value(), [3.0 / r, 8.0 / r]);
+    }
+
+    #[test]
+    fn test_batch_and_group_norm() {
+        type T3 = Tensor<f64, (I<2>, I<2>, I<2>)>;
+        let x: T3 = Tensor::new([[[1.0, 3.0], [0.0, 10.0]], [[5.0, 7.0], [20.0, 30.0]]]);
+        let ones = || Tensor::new([1.0, 1.0]);
+        let zeros = || Tensor::new([0.0, 0.0]);
+        // Channel 0 holds 1, 3, 5 and 7, with a mean of 4 and a variance of 5
+        let y = x.clone().batch_norm(ones(), zeros(), 0.0);
+        assert!((y.borrow_value()[0] + 3.0 / 5f64.sqrt()).abs() < 1e-12);
+        // A group per channel normalises each channel of each sample separately
+        let y = x.clone().group_norm(2, ones(), zeros(), 0.0);
+        assert_eq!(y.borrow_value()[..4], [-1.0, 1.0, -1.0, 1.0]);
+        // A single group normalises each sample as a whole
+        let y = x.group_norm(1, ones(), Tensor::new([0.0, 1.0]), 0.0);
+        let s = 15.25f64.sqrt();
+        assert!((y.borrow_value()[3] - (10.0 - 3.5) / s - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_norm_grads() {
+        // With respect to the input, weights and bias
+        type T23 = Tensor<f64, (I<2>, I<3>)>;
+        type T3 = Tensor<f64, (I<3>,)>;
+        let ops: [fn(T23, T3, T3) -> T23; 4] = [
+            |x, w, b| x.layer_norm(w, b, 1e-3),
+            |x, w, _| x.rms_norm(w, 1e-3),
+            |x, w, b| x.batch_norm(w, b, 1e-3),
+            |x, w, b| x.group_norm(1, w, b, 1e-3),
+        ];
+        let x = [0.5, -0.2, 1.4, 2.2, 0.0, -1.1];
+        let w = [0.8, -1.5, 1.2];
+        let b = [0.1, 0.4, -0.3];
+        for (i, op) in ops.into_iter().enumerate() {
+            check_grad(|xt| op(xt, T3::new(w), T3::new(b)), &x);
+            check_grad(|wt| op(T23::new(x.to_vec()), wt, T3::new(b)), &w);
+            // rms_norm has no bias
+            if i != 1 {
+                check_grad(|bt| op(T23::new(x.to_vec()), T3::new(w), bt), &b);
+            }
+        }
+    }
+}
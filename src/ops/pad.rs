@@ -0,0 +1,202 @@
+use crate::ops::vec::{pad, unpad};
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{
+    dtype::NumDtype,
+    ops::Op,
+    shape::{Dims, Pad, Shape},
+    tensor::Tensor,
+};
+use std::rc::Rc;
+
+/// How `pad` fills the new positions, shown for `[1, 2, 3]` padded by 2 on each side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PadMode<T> {
+    /// Fill with a value, e.g. `[0, 0, 1, 2, 3, 0, 0]`.
+    Constant(T),
+    /// Mirror the source without repeating the edge, e.g. `[3, 2, 1, 2, 3, 2, 1]`.
+    Reflect,
+    /// Repeat the edge, e.g. `[1, 1, 1, 2, 3, 3, 3]`.
+    Replicate,
+    /// Wrap around, e.g. `[2, 3, 1, 2, 3, 1, 2]`.
+    Circular,
+}
+
+use PadMode::*;
+
+impl<T: NumDtype> PadMode<T> {
+    /// The source position along an axis of size `n` for each output position, or `None` where
+    /// the output is a constant.
+    fn source_indices(&self, n: usize, before: usize, after: usize) -> Vec<Option<usize>> {
+        match self {
+            Reflect => assert!(
+                before < n && after < n,
+                "Reflect padding must be smaller than the padded axis of size {}",
+                n
+            ),
+            Circular => assert!(
+                before <= n && after <= n,
+                "Circular padding must be at most the size of the padded axis of size {}",
+                n
+            ),
+            Constant(_) | Replicate => {}
+        }
+        let n = n as isize;
+        (-(before as isize)..n + after as isize)
+            .map(|i| {
+                let j = match self {
+                    Constant(_) if !(0..n).contains(&i) => return None,
+                    Constant(_) => i,
+                    Reflect if i < 0 => -i,
+                    Reflect if i >= n => 2 * (n - 1) - i,
+                    Reflect => i,
+                    Replicate => i.clamp(0, n - 1),
+                    Circular => i.rem_euclid(n),
+                };
+                Some(j as usize)
+            })
+            .collect()
+    }
+
+    fn fill(&self) -> T {
+        match self {
+            Constant(v) => *v,
+            _ => T::zero(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PadStruct<T: NumDtype, S: Dims, So: Dims> {
+    data: Tensor<T, S>,
+    axis: usize,
+    idx: Vec<Option<usize>>,
+    fill: T,
+    out_shape: So,
+}
+
+impl<T: NumDtype, S: Dims, So: Dims> PadStruct<T, S, So> {
+    pub(crate) fn new(
+        data: Tensor<T, S>,
+        axis: usize,
+        before: usize,
+        after: usize,
+        mode: PadMode<T>,
+        out_shape: So,
+    ) -> Self {
+        let idx = mode.source_indices(data.shape.dims()[axis], before, after);
+        Self {
+            data,
+            axis,
+            idx,
+            fill: mode.fill(),
+            out_shape,
+        }
+    }
+
+    fn compute(&self) -> Vec<T> {
+        let a = self.data.borrow_value();
+        pad(&a, &self.data.shape.dims(), self.axis, &self.idx, self.fill)
+    }
+}
+
+impl<T: NumDtype, S: Dims, So: Dims> Op for PadStruct<T, S, So> {
+    type Produces = Tensor<T, So>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = pad(a)
+        // d_da = d_dt summed into the source position of each output position
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let d_da = unpad(d_dt, &self.data.shape.dims(), self.axis, &self.idx);
+            self.data.update_grad(d_da);
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let data = TensorData::new(self.compute(), self.data.requires_grad());
+        let shape = self.out_shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.data.id, &self.data)]
+    }
+}
+
+impl<T: NumDtype, S: Shape> Tensor<T, S> {
+    /// Pad `AXIS` with `BEFORE` elements before and `AFTER` elements after the data, e.g.
+    /// `x.pad::<1, 2, 2>(PadMode::Reflect)`. Panics if reflect or circular padding is larger
+    /// than the axis.
+    pub fn pad<const AXIS: usize, const BEFORE: usize, const AFTER: usize>(
+        self,
+        mode: PadMode<T>,
+    ) -> Tensor<T, <S as Pad<AXIS, BEFORE, AFTER>>::Output>
+    where
+        S: Pad<AXIS, BEFORE, AFTER>,
+    {
+        PadStruct::new(self, AXIS, BEFORE, AFTER, mode, Default::default()).forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PadMode;
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_pad_modes() {
+        let x = Tensor::new([1, 2, 3]);
+        let pad = |mode| -> Vec<i32> {
+            let p: Tensor<i32, (I<7>,)> = x.clone().pad::<0, 2, 2>(mode);
+            let value = p.borrow_value().to_vec();
+            value
+        };
+        assert_eq!(pad(PadMode::Constant(0)), [0, 0, 1, 2, 3, 0, 0]);
+        assert_eq!(pad(PadMode::Reflect), [3, 2, 1, 2, 3, 2, 1]);
+        assert_eq!(pad(PadMode::Replicate), [1, 1, 1, 2, 3, 3, 3]);
+        assert_eq!(pad(PadMode::Circular), [2, 3, 1, 2, 3, 1, 2]);
+    }
+
+    #[test]
+    fn test_pad_grad() {
+        let x = Tensor::new_with_grad([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let p: Tensor<f64, (I<2>, I<5>)> = x.clone().pad::<1, 1, 1>(PadMode::Replicate);
+        assert_eq!(
+            *p.borrow_value(),
+            [1.0, 1.0, 2.0, 3.0, 3.0, 4.0, 4.0, 5.0, 6.0, 6.0]
+        );
+
+        p.reduce_sum().backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[2.0, 1.0, 2.0, 2.0, 1.0, 2.0][..])
+        );
+    }
+
+    #[test]
+    fn test_pad_nchw() {
+        // "Same" padding of the spatial axes of an NCHW input
+        let x = Tensor::new([[[[1, 2], [3, 4]]]]);
+        let p: Tensor<i32, (I<1>, I<1>, I<4>, I<4>)> = x
+            .pad::<2, 1, 1>(PadMode::Constant(0))
+            .pad::<3, 1, 1>(PadMode::Constant(0));
+        assert_eq!(
+            *p.borrow_value(),
+            [0, 0, 0, 0, 0, 1, 2, 0, 0, 3, 4, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_reflect_pad_too_large() {
+        let x = Tensor::new([1.0, 2.0]);
+        x.pad::<0, 2, 0>(PadMode::Reflect);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::tensor::TensorBox;
+use crate::{
+    dtype::NumDtype,
+    ops::Op,
+    shape::{Axes, Permute, Shape, I},
+    tensor::Tensor,
+};
+use std::{marker::PhantomData, rc::Rc};
+
+#[derive(Debug)]
+pub struct TransposeStruct<T: NumDtype, const N: usize, const M: usize>(Tensor<T, (I<N>, I<M>)>);
+
+#[derive(Debug)]
+pub struct PermuteStruct<T: NumDtype, S: Shape, P: Axes>(Tensor<T, S>, PhantomData<P>);
+
+// Transpose and permute are strided views sharing storage (and therefore grad) with the
+// operand, so no grad propogation occurs.
+impl<T: NumDtype, const N: usize, const M: usize> Op for TransposeStruct<T, N, M> {
+    type Produces = Tensor<T, (I<M>, I<N>)>;
+
+    fn propogate_grad(&self, _t: &Self::Produces) {}
+
+    fn recompute(&self, _t: &Self::Produces) {}
+
+    fn forward(self) -> Self::Produces {
+        let layout = self.0.data.layout(&[N, M]).permute(&[1, 0]);
+        let data = self.0.data.view(layout);
+        unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.0.id, &self.0)]
+    }
+}
+
+impl<T: NumDtype, S: Shape, P: Axes> Op for PermuteStruct<T, S, P>
+where
+    S: Permute<P>,
+{
+    type Produces = Tensor<T, <S as Permute<P>>::Output>;
+
+    fn propogate_grad(&self, _t: &Self::Produces) {}
+
+    fn recompute(&self, _t: &Self::Produces) {}
+
+    fn forward(self) -> Self::Produces {
+        let layout = self.0.data.layout(S::shape()).permute(P::axes());
+        let data = self.0.data.view(layout);
+        unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.0.id, &self.0)]
+    }
+}
+
+impl<T: NumDtype, const N: usize, const M: usize> Tensor<T, (I<N>, I<M>)> {
+    pub fn transpose(self) -> Tensor<T, (I<M>, I<N>)> {
+        TransposeStruct(self).forward()
+    }
+}
+
+impl<T: NumDtype, S: Shape> Tensor<T, S> {
+    /// Reorder axes so that output axis `i` is input axis `P::axes()[i]`, e.g.
+    /// `x.permute::<Axes3<2, 0, 1>>()` turns shape `(A, B, C)` into `(C, A, B)`.
+    pub fn permute<P: Axes>(self) -> Tensor<T, <S as Permute<P>>::Output>
+    where
+        S: Permute<P>,
+    {
+        PermuteStruct(self, PhantomData).forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::{Axes3, Axes4, I};
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_transpose_matmul() {
+        let x = Tensor::new_with_grad([[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]]);
+        let y = Tensor::new([[1.0], [0.0], [2.0]]);
+        let z: Tensor<f64, (I<2>, I<1>)> = x.clone().transpose().matmul(y);
+        assert_eq!(*z.borrow_value(), [11.0, 14.0]);
+
+        z.reduce_sum().backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[1.0, 1.0, 0.0, 0.0, 2.0, 2.0][..])
+        );
+    }
+
+    #[test]
+    fn test_permute_grad() {
+        let x = Tensor::new_with_grad([[[0.0, 1.0, 2.0], [3.0, 4.0, 5.0]]]); // shape = (1, 2, 3)
+        let p: Tensor<f64, (I<3>, I<1>, I<2>)> = x.clone().permute::<Axes3<2, 0, 1>>();
+        assert_eq!(*p.borrow_value(), [0.0, 3.0, 1.0, 4.0, 2.0, 5.0]);
+
+        let w = Tensor::new([[[1.0, 2.0]], [[3.0, 4.0]], [[5.0, 6.0]]]);
+        (p * w).reduce_sum().backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[1.0, 3.0, 5.0, 2.0, 4.0, 6.0][..])
+        );
+    }
+
+    #[test]
+    fn test_permute_nchw_to_nhwc() {
+        // shape = (1, 2, 2, 2), with channels [0, 1, 2, 3] and [4, 5, 6, 7]
+        let x = Tensor::new([[[[0, 1], [2, 3]], [[4, 5], [6, 7]]]]);
+        let p: Tensor<i32, (I<1>, I<2>, I<2>, I<2>)> = x.permute::<Axes4<0, 2, 3, 1>>();
+        assert_eq!(*p.borrow_value(), [0, 4, 1, 5, 2, 6, 3, 7]);
+    }
+}
@@ -0,0 +1,324 @@
+use crate::ops::grad::{avg_pool_grad, max_pool_grad};
+use crate::ops::vec::{
+    adaptive_pool_windows, avg_pool, max_pool, max_pool_args, pool_windows, ConvGeometry,
+};
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{
+    dtype::NumDtype,
+    ops::Op,
+    shape::{Pool1d, Pool2d, Shape, I},
+    tensor::Tensor,
+};
+use std::{marker::PhantomData, rc::Rc};
+
+// Pooling ops take the indices of the input elements in each window, one window per output
+// element, so the same ops cover 1d, 2d and adaptive pooling.
+
+#[derive(Debug)]
+pub struct MaxPoolStruct<T: NumDtype, S: Shape, So: Shape> {
+    data: Tensor<T, S>,
+    windows: Vec<Vec<usize>>,
+    _shape: PhantomData<So>,
+}
+
+/// `divisor` is the number of elements each window sum is divided by, or `None` to divide by the
+/// size of the window.
+#[derive(Debug)]
+pub struct AvgPoolStruct<T: NumDtype, S: Shape, So: Shape> {
+    data: Tensor<T, S>,
+    windows: Vec<Vec<usize>>,
+    divisor: Option<usize>,
+    _shape: PhantomData<So>,
+}
+
+impl<T: NumDtype, S: Shape, So: Shape> Op for MaxPoolStruct<T, S, So> {
+    type Produces = Tensor<T, So>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = max(a[window]) for each window
+        // d_da = d_dt at the (first) argmax of each window, else 0
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let args = max_pool_args(&self.data.borrow_value(), &self.windows);
+            self.data
+                .update_grad(max_pool_grad(d_dt, &args, S::NUM_ELS));
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        let data = max_pool(&self.data.borrow_value(), &self.windows);
+        t.data.replace(data)
+    }
+
+    fn forward(self) -> Self::Produces {
+        let value = max_pool(&self.data.borrow_value(), &self.windows);
+        let data = TensorData::new(value, self.data.requires_grad());
+        unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.data.id, &self.data)]
+    }
+}
+
+impl<T: NumDtype, S: Shape, So: Shape> Op for AvgPoolStruct<T, S, So> {
+    type Produces = Tensor<T, So>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = sum(a[window]) / n for each window
+        // d_da = d_dt / n spread over each window
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let d_da = avg_pool_grad(d_dt, &self.windows, self.divisor, S::NUM_ELS);
+            self.data.update_grad(d_da);
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        let data = avg_pool(&self.data.borrow_value(), &self.windows, self.divisor);
+        t.data.replace(data)
+    }
+
+    fn forward(self) -> Self::Produces {
+        let value = avg_pool(&self.data.borrow_value(), &self.windows, self.divisor);
+        let data = TensorData::new(value, self.data.requires_grad());
+        unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.data.id, &self.data)]
+    }
+}
+
+/// Windows of a pool over `(N, C, H, W)` inputs, where `kernel`, `stride` and `padding` are
+/// (height, width) pairs and `out_size` is the size of each output channel. 1d pools have a
+/// height of 1.
+fn windows(
+    in_shape: [usize; 4],
+    out_size: (usize, usize),
+    kernel: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+) -> Vec<Vec<usize>> {
+    pool_windows(&ConvGeometry {
+        batch: 1,
+        in_channels: in_shape[0] * in_shape[1],
+        out_channels: in_shape[0] * in_shape[1],
+        groups: 1,
+        in_size: (in_shape[2], in_shape[3]),
+        kernel,
+        out_size,
+        stride,
+        padding,
+        dilation: (1, 1),
+    })
+}
+
+impl<T: NumDtype, S: Shape> Tensor<T, S> {
+    fn pool1d_windows<So: Shape>(kernel: usize, stride: usize, padding: usize) -> Vec<Vec<usize>> {
+        let (s, o) = (S::shape(), So::shape());
+        windows(
+            [s[0], s[1], 1, s[2]],
+            (1, o[2]),
+            (1, kernel),
+            (1, stride),
+            (0, padding),
+        )
+    }
+
+    fn pool2d_windows<So: Shape>(kernel: usize, stride: usize, padding: usize) -> Vec<Vec<usize>> {
+        let (s, o) = (S::shape(), So::shape());
+        windows(
+            [s[0], s[1], s[2], s[3]],
+            (o[2], o[3]),
+            (kernel, kernel),
+            (stride, stride),
+            (padding, padding),
+        )
+    }
+
+    /// Max over windows of size `KERNEL` along the last axis of `(N, C, L)` inputs. Padding is
+    /// never taken as the max.
+    pub fn max_pool1d<const KERNEL: usize, const STRIDE: usize, const PADDING: usize>(
+        self,
+    ) -> Tensor<T, <S as Pool1d<KERNEL, STRIDE, PADDING>>::Output>
+    where
+        S: Pool1d<KERNEL, STRIDE, PADDING>,
+    {
+        MaxPoolStruct {
+            data: self,
+            windows: Self::pool1d_windows::<<S as Pool1d<KERNEL, STRIDE, PADDING>>::Output>(
+                KERNEL, STRIDE, PADDING,
+            ),
+            _shape: PhantomData,
+        }
+        .forward()
+    }
+
+    /// Max over `KERNEL x KERNEL` windows of `(N, C, H, W)` inputs. Padding is never taken as
+    /// the max.
+    pub fn max_pool2d<const KERNEL: usize, const STRIDE: usize, const PADDING: usize>(
+        self,
+    ) -> Tensor<T, <S as Pool2d<KERNEL, STRIDE, PADDING>>::Output>
+    where
+        S: Pool2d<KERNEL, STRIDE, PADDING>,
+    {
+        MaxPoolStruct {
+            data: self,
+            windows: Self::pool2d_windows::<<S as Pool2d<KERNEL, STRIDE, PADDING>>::Output>(
+                KERNEL, STRIDE, PADDING,
+            ),
+            _shape: PhantomData,
+        }
+        .forward()
+    }
+
+    /// Mean over windows of size `KERNEL` along the last axis of `(N, C, L)` inputs. Padding
+    /// counts as zeros, so every window is divided by `KERNEL`.
+    pub fn avg_pool1d<const KERNEL: usize, const STRIDE: usize, const PADDING: usize>(
+        self,
+    ) -> Tensor<T, <S as Pool1d<KERNEL, STRIDE, PADDING>>::Output>
+    where
+        S: Pool1d<KERNEL, STRIDE, PADDING>,
+    {
+        AvgPoolStruct {
+            data: self,
+            windows: Self::pool1d_windows::<<S as Pool1d<KERNEL, STRIDE, PADDING>>::Output>(
+                KERNEL, STRIDE, PADDING,
+            ),
+            divisor: Some(KERNEL),
+            _shape: PhantomData,
+        }
+        .forward()
+    }
+
+    /// Mean over `KERNEL x KERNEL` windows of `(N, C, H, W)` inputs. Padding counts as zeros, so
+    /// every window is divided by `KERNEL * KERNEL`.
+    pub fn avg_pool2d<const KERNEL: usize, const STRIDE: usize, const PADDING: usize>(
+        self,
+    ) -> Tensor<T, <S as Pool2d<KERNEL, STRIDE, PADDING>>::Output>
+    where
+        S: Pool2d<KERNEL, STRIDE, PADDING>,
+    {
+        AvgPoolStruct {
+            data: self,
+            windows: Self::pool2d_windows::<<S as Pool2d<KERNEL, STRIDE, PADDING>>::Output>(
+                KERNEL, STRIDE, PADDING,
+            ),
+            divisor: Some(KERNEL * KERNEL),
+            _shape: PhantomData,
+        }
+        .forward()
+    }
+}
+
+impl<T: NumDtype, const N: usize, const C: usize, const H: usize, const W: usize>
+    Tensor<T, (I<N>, I<C>, I<H>, I<W>)>
+{
+    /// Mean over `OH x OW` near-equal regions of each channel, whatever the input size.
+    pub fn adaptive_avg_pool2d<const OH: usize, const OW: usize>(
+        self,
+    ) -> Tensor<T, (I<N>, I<C>, I<OH>, I<OW>)> {
+        assert!(
+            OH > 0 && OW > 0,
+            "Adaptive pooling output must not be empty"
+        );
+        AvgPoolStruct {
+            data: self,
+            windows: adaptive_pool_windows(N * C, (H, W), (OH, OW)),
+            divisor: None,
+            _shape: PhantomData,
+        }
+        .forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_max_pool2d() {
+        let x = Tensor::new_with_grad([[[
+            [1.0, 5.0, 2.0, 0.0],
+            [7.0, 3.0, 3.0, 3.0],
+            [0.0, 9.0, 4.0, 4.0],
+            [2.0, 2.0, 8.0, 6.0],
+        ]]]);
+        let y: Tensor<f64, (I<1>, I<1>, I<2>, I<2>)> = x.clone().max_pool2d::<2, 2, 0>();
+        assert_eq!(*y.borrow_value(), [7.0, 3.0, 9.0, 8.0]);
+
+        // Each grad goes to the max of its window only, ties go to the first
+        (y * Tensor::new([[[[1.0, 2.0], [3.0, 4.0]]]]))
+            .reduce_sum()
+            .backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(
+                &[0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 2.0, 0.0, 0.0, 3.0, 0.0, 0.0, 0.0, 0.0, 4.0, 0.0][..]
+            )
+        );
+
+        // Padding is never the max, even of negative values
+        let x = Tensor::new([[[[-1.0, -2.0], [-3.0, -4.0]]]]);
+        let y: Tensor<f64, (I<1>, I<1>, I<3>, I<3>)> = x.max_pool2d::<2, 1, 1>();
+        assert_eq!(
+            *y.borrow_value(),
+            [-1.0, -1.0, -2.0, -1.0, -1.0, -2.0, -3.0, -3.0, -4.0]
+        );
+    }
+
+    #[test]
+    fn test_avg_pool() {
+        let x = Tensor::new_with_grad([[[[1.0, 2.0], [3.0, 4.0]]]]);
+        // Padding counts towards the mean
+        let y: Tensor<f64, (I<1>, I<1>, I<3>, I<3>)> = x.clone().avg_pool2d::<2, 1, 1>();
+        assert_eq!(
+            *y.borrow_value(),
+            [0.25, 0.75, 0.5, 1.0, 2.5, 1.5, 0.75, 1.75, 1.0]
+        );
+
+        y.reduce_sum().backward();
+        assert_eq!(x.borrow_grad().as_deref(), Some(&[1.0, 1.0, 1.0, 1.0][..]));
+
+        let x = Tensor::new_with_grad([[[1.0, 2.0, 3.0, 4.0, 5.0]], [[0.0, 2.0, 0.0, 2.0, 0.0]]]);
+        let y: Tensor<f64, (I<2>, I<1>, I<2>)> = x.clone().avg_pool1d::<3, 2, 0>();
+        assert_eq!(*y.borrow_value(), [2.0, 4.0, 2.0 / 3.0, 2.0 / 3.0]);
+        let z: Tensor<f64, (I<2>, I<1>, I<2>)> = x.clone().max_pool1d::<3, 2, 0>();
+        assert_eq!(*z.borrow_value(), [3.0, 5.0, 2.0, 2.0]);
+
+        // Overlapping windows accumulate their share of the grad
+        (y * Tensor::new([[[3.0, 6.0]], [[0.0, 0.0]]]))
+            .reduce_sum()
+            .backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[1.0, 1.0, 3.0, 2.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0][..])
+        );
+    }
+
+    #[test]
+    fn test_adaptive_avg_pool2d() {
+        let x = Tensor::new_with_grad([[
+            [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]],
+            [[0.0, 0.0, 6.0], [0.0, 0.0, 6.0]],
+        ]]);
+        let y = x.clone().adaptive_avg_pool2d::<1, 2>();
+        assert_eq!(*y.borrow_value(), [3.0, 4.0, 0.0, 3.0]);
+
+        // Global average pooling
+        let g = x.clone().adaptive_avg_pool2d::<1, 1>();
+        assert_eq!(*g.borrow_value(), [3.5, 2.0]);
+
+        y.reduce_sum().backward();
+        let (q, s) = (0.25, 0.5);
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[q, s, q, q, s, q, q, s, q, q, s, q][..])
+        );
+    }
+}
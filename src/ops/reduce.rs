@@ -0,0 +1,193 @@
+use crate::ops::grad::{
+    logsumexp_axis_grad, max_axis_grad, mean_axis_grad, min_axis_grad, prod_axis_grad,
+    sum_axis_grad,
+};
+use crate::ops::vec::{
+    broadcast, el_mul, logsumexp_axis, max_axis, mean_axis, min_axis, prod_axis, sum_axis,
+};
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{
+    dtype::{FloatDtype, NumDtype},
+    ops::Op,
+    shape::{Dims, ReduceAxis, Shape},
+    tensor::Tensor,
+};
+use std::rc::Rc;
+
+macro_rules! impl_reduce_axis_op {
+    ($s:ident, $tf:ident, $tf_keepdim:ident, $f:expr, $df:expr) => {
+        impl_reduce_axis_op!(NumDtype; $s, $tf, $tf_keepdim, $f, $df);
+    };
+    // Ops which need a narrower dtype bound, e.g. `FloatDtype`
+    ($bound:ident; $s:ident, $tf:ident, $tf_keepdim:ident, $f:expr, $df:expr) => {
+        #[derive(Debug)]
+        pub struct $s<T: $bound, S: Dims, So: Dims> {
+            pub(crate) data: Tensor<T, S>,
+            pub(crate) axis: usize,
+            pub(crate) out_shape: So,
+        }
+
+        impl<T: $bound, S: Dims, So: Dims> Op for $s<T, S, So> {
+            type Produces = Tensor<T, So>;
+
+            fn propogate_grad(&self, t: &Self::Produces) {
+                // t = f(a, axis)
+                if let Some(d_dt) = t.data.grad_ref().as_ref() {
+                    let shape = self.data.shape.dims();
+                    let d_da = {
+                        let a = self.data.borrow_value();
+                        let dt_da = $df(&a, &shape, self.axis);
+                        let mut keepdim_shape = shape.clone();
+                        keepdim_shape[self.axis] = 1;
+                        el_mul(&broadcast(d_dt, &keepdim_shape, &shape), &dt_da)
+                    };
+                    self.data.update_grad(d_da);
+                } else {
+                    panic!("Attempted to propogate grad, but no grad value exists.")
+                }
+            }
+
+            fn recompute(&self, t: &Self::Produces) {
+                let data = $f(&self.data.borrow_value(), &self.data.shape.dims(), self.axis);
+                t.data.replace(data)
+            }
+
+            fn forward(self) -> Self::Produces {
+                let value = $f(&self.data.borrow_value(), &self.data.shape.dims(), self.axis);
+                let data = TensorData::new(value, self.data.requires_grad());
+                let shape = self.out_shape.clone();
+                unsafe {
+                    Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape)
+                }
+            }
+
+            fn operands(&self) -> Vec<TensorBox<'_>> {
+                vec![TensorBox::new(self.data.id, &self.data)]
+            }
+        }
+
+        impl<T: $bound, S: Shape> Tensor<T, S> {
+            pub fn $tf<const AXIS: usize>(self) -> Tensor<T, <S as ReduceAxis<AXIS>>::Reduced>
+            where
+                S: ReduceAxis<AXIS>,
+            {
+                $s {
+                    data: self,
+                    axis: AXIS,
+                    out_shape: Default::default(),
+                }
+                .forward()
+            }
+
+            pub fn $tf_keepdim<const AXIS: usize>(
+                self,
+            ) -> Tensor<T, <S as ReduceAxis<AXIS>>::KeepDim>
+            where
+                S: ReduceAxis<AXIS>,
+            {
+                $s {
+                    data: self,
+                    axis: AXIS,
+                    out_shape: Default::default(),
+                }
+                .forward()
+            }
+        }
+    };
+}
+
+// Ops
+// `So` is the output shape, which is either `ReduceAxis::Reduced` or `ReduceAxis::KeepDim` for
+// typed tensors. Both have the same data layout so a single op covers both variants.
+
+impl_reduce_axis_op!(
+    SumAxisStruct,
+    sum_axis,
+    sum_axis_keepdim,
+    sum_axis,
+    sum_axis_grad
+);
+impl_reduce_axis_op!(
+    MeanAxisStruct,
+    mean_axis,
+    mean_axis_keepdim,
+    mean_axis,
+    mean_axis_grad
+);
+impl_reduce_axis_op!(
+    ProdAxisStruct,
+    prod_axis,
+    prod_axis_keepdim,
+    prod_axis,
+    prod_axis_grad
+);
+impl_reduce_axis_op!(
+    MaxAxisStruct,
+    max_axis,
+    max_axis_keepdim,
+    max_axis,
+    max_axis_grad
+);
+impl_reduce_axis_op!(
+    MinAxisStruct,
+    min_axis,
+    min_axis_keepdim,
+    min_axis,
+    min_axis_grad
+);
+impl_reduce_axis_op!(
+    FloatDtype;
+    LogSumExpAxisStruct,
+    logsumexp,
+    logsumexp_keepdim,
+    logsumexp_axis,
+    logsumexp_axis_grad
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_sum_axis() {
+        let x = Tensor::new_with_grad([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let s: Tensor<f64, (I<2>,)> = x.clone().sum_axis::<1>();
+        assert_eq!(*s.borrow_value(), [6.0, 15.0]);
+        let s: Tensor<f64, (I<1>, I<3>)> = x.clone().sum_axis_keepdim::<0>();
+        assert_eq!(*s.borrow_value(), [5.0, 7.0, 9.0]);
+
+        (s * Tensor::new([[1.0, 2.0, 3.0]])).reduce_sum().backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[1.0, 2.0, 3.0, 1.0, 2.0, 3.0][..])
+        );
+    }
+
+    #[test]
+    fn test_max_axis_grad() {
+        let x = Tensor::new_with_grad([[1.0, 7.0, 3.0], [4.0, 2.0, 4.0]]);
+        let m: Tensor<f64, (I<2>,)> = x.clone().max_axis::<1>();
+        assert_eq!(*m.borrow_value(), [7.0, 4.0]);
+
+        m.reduce_sum().backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[0.0, 1.0, 0.0, 1.0, 0.0, 0.0][..])
+        );
+    }
+
+    #[test]
+    fn test_prod_axis_grad() {
+        let x = Tensor::new_with_grad([[2.0, 0.0, 3.0], [1.0, 2.0, 4.0]]);
+        let p = x.clone().prod_axis::<1>();
+        assert_eq!(*p.borrow_value(), [0.0, 8.0]);
+
+        p.reduce_sum().backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[0.0, 6.0, 0.0, 8.0, 4.0, 2.0][..])
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use crate::ops::vec::{el_mul, el_neg, el_unary, neg_one};
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{
+    dtype::{NumDtype, SignedDtype},
+    ops::Op,
+    shape::Dims,
+    tensor::Tensor,
+};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::rc::Rc;
+
+// Ops between a tensor and a scalar `c`, which is a constant rather than a graph node. Grads are
+// negated with `neg_one` rather than `Neg`, so these are available for unsigned dtypes.
+macro_rules! impl_scalar_op {
+    ($s:ident, $f:expr, $df:expr) => {
+        #[derive(Debug)]
+        pub struct $s<T: NumDtype, S: Dims>(Tensor<T, S>, T);
+
+        impl<T: NumDtype, S: Dims> $s<T, S> {
+            fn compute(&self) -> Vec<T> {
+                el_unary(|a: &T| $f(*a, self.1), &self.0.borrow_value())
+            }
+        }
+
+        impl<T: NumDtype, S: Dims> Op for $s<T, S> {
+            type Produces = Tensor<T, S>;
+
+            fn propogate_grad(&self, t: &Self::Produces) {
+                // t = f(a, c)
+                // d_da = d_dt * f'(a, c)
+                if let Some(d_dt) = t.data.grad_ref().as_ref() {
+                    let d_da = {
+                        let dt_da = el_unary(|a: &T| $df(*a, self.1), &self.0.borrow_value());
+                        el_mul(d_dt, &dt_da)
+                    };
+                    self.0.update_grad(d_da);
+                } else {
+                    panic!("Attempted to propogate grad, but no grad value exists.")
+                }
+            }
+
+            fn recompute(&self, t: &Self::Produces) {
+                t.data.replace(self.compute())
+            }
+
+            fn forward(self) -> Self::Produces {
+                let data = TensorData::new(self.compute(), self.0.requires_grad());
+                let shape = self.0.shape.clone();
+                unsafe {
+                    Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape)
+                }
+            }
+
+            fn operands(&self) -> Vec<TensorBox<'_>> {
+                vec![TensorBox::new(self.0.id, &self.0)]
+            }
+        }
+    };
+}
+
+impl_scalar_op!(ScalarAddStruct, |a: T, c: T| a + c, |_, _| T::one());
+impl_scalar_op!(ScalarSubStruct, |a: T, c: T| a - c, |_, _| T::one());
+impl_scalar_op!(ScalarRSubStruct, |a: T, c: T| c - a, |_, _| neg_one());
+impl_scalar_op!(ScalarMulStruct, |a: T, c: T| a * c, |_, c: T| c);
+impl_scalar_op!(ScalarDivStruct, |a: T, c: T| a / c, |_, c: T| T::one() / c);
+impl_scalar_op!(
+    ScalarRDivStruct,
+    |a: T, c: T| c / a,
+    |a: T, c| neg_one::<T>() * c / (a * a)
+);
+
+#[derive(Debug)]
+pub struct ElNegStruct<T: SignedDtype, S: Dims>(Tensor<T, S>);
+
+impl<T: SignedDtype, S: Dims> Op for ElNegStruct<T, S> {
+    type Produces = Tensor<T, S>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = -a
+        // d_da = -d_dt
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            self.0.update_grad(el_neg(d_dt));
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(el_neg(&self.0.borrow_value()))
+    }
+
+    fn forward(self) -> Self::Produces {
+        let data = TensorData::new(el_neg(&self.0.borrow_value()), self.0.requires_grad());
+        let shape = self.0.shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.0.id, &self.0)]
+    }
+}
+
+impl<T: SignedDtype, S: Dims> Neg for Tensor<T, S> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        ElNegStruct(self).forward()
+    }
+}
+
+impl<T: NumDtype, S: Dims> Add<T> for Tensor<T, S> {
+    type Output = Self;
+    fn add(self, c: T) -> Self {
+        ScalarAddStruct(self, c).forward()
+    }
+}
+
+impl<T: NumDtype, S: Dims> Sub<T> for Tensor<T, S> {
+    type Output = Self;
+    fn sub(self, c: T) -> Self {
+        ScalarSubStruct(self, c).forward()
+    }
+}
+
+impl<T: NumDtype, S: Dims> Mul<T> for Tensor<T, S> {
+    type Output = Self;
+    fn mul(self, c: T) -> Self {
+        ScalarMulStruct(self, c).forward()
+    }
+}
+
+impl<T: NumDtype, S: Dims> Div<T> for Tensor<T, S> {
+    type Output = Self;
+    fn div(self, c: T) -> Self {
+        ScalarDivStruct(self, c).forward()
+    }
+}
+
+// `T op Tensor` can't be implemented for a generic `T`, as the impl would be for a foreign type,
+// so it is implemented for each primitive dtype.
+macro_rules! impl_scalar_lhs_ops {
+    ($($t:ty),*) => {
+        $(
+            impl<S: Dims> Add<Tensor<$t, S>> for $t {
+                type Output = Tensor<$t, S>;
+                fn add(self, t: Tensor<$t, S>) -> Self::Output {
+                    ScalarAddStruct(t, self).forward()
+                }
+            }
+
+            impl<S: Dims> Sub<Tensor<$t, S>> for $t {
+                type Output = Tensor<$t, S>;
+                fn sub(self, t: Tensor<$t, S>) -> Self::Output {
+                    ScalarRSubStruct(t, self).forward()
+                }
+            }
+
+            impl<S: Dims> Mul<Tensor<$t, S>> for $t {
+                type Output = Tensor<$t, S>;
+                fn mul(self, t: Tensor<$t, S>) -> Self::Output {
+                    ScalarMulStruct(t, self).forward()
+                }
+            }
+
+            impl<S: Dims> Div<Tensor<$t, S>> for $t {
+                type Output = Tensor<$t, S>;
+                fn div(self, t: Tensor<$t, S>) -> Self::Output {
+                    ScalarRDivStruct(t, self).forward()
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_lhs_ops!(f32, f64, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_scalar_ops() {
+        type T2 = Tensor<f64, (I<2>,)>;
+        let ops: [fn(T2) -> T2; 9] = [
+            |t| t + 2.0,
+            |t| t - 2.0,
+            |t| t * 3.0,
+            |t| t / 4.0,
+            |t| 2.0 + t,
+            |t| 1.0 - t,
+            |t| 3.0 * t,
+            |t| 4.0 / t,
+            |t| -t,
+        ];
+        let expected_values = [
+            [3.0, 0.0],
+            [-1.0, -4.0],
+            [3.0, -6.0],
+            [0.25, -0.5],
+            [3.0, 0.0],
+            [0.0, 3.0],
+            [3.0, -6.0],
+            [4.0, -2.0],
+            [-1.0, 2.0],
+        ];
+        let expected_grads = [
+            [1.0, 1.0],
+            [1.0, 1.0],
+            [3.0, 3.0],
+            [0.25, 0.25],
+            [1.0, 1.0],
+            [-1.0, -1.0],
+            [3.0, 3.0],
+            [-4.0, -1.0],
+            [-1.0, -1.0],
+        ];
+        for ((op, value), grad) in ops.iter().zip(expected_values).zip(expected_grads) {
+            let x: T2 = Tensor::new_with_grad([1.0, -2.0]);
+            let y = op(x.clone());
+            assert_eq!(*y.borrow_value(), value);
+            y.reduce_sum().backward();
+            assert_eq!(*x.borrow_grad().unwrap(), grad);
+        }
+
+        let i: Tensor<i32, (I<2>,)> = 10 - Tensor::new([1, 2]) * 2;
+        assert_eq!(*i.borrow_value(), [8, 6]);
+        let u: Tensor<u8, (I<2>,)> = 255u8 - Tensor::new([1, 2]) * 2 - 3;
+        assert_eq!(*u.borrow_value(), [250, 248]);
+    }
+
+    #[test]
+    fn test_assign_ops() {
+        let x = Tensor::new_with_grad([1.0, 2.0]);
+        let w = Tensor::new_with_grad([3.0, 4.0]);
+        let mut y = x.clone();
+        y *= 2.0;
+        y += w.clone();
+        y -= 1.0;
+        y *= w.clone();
+        y /= 2.0;
+        // y = ((2x + w - 1) * w) / 2
+        assert_eq!(*y.borrow_value(), [6.0, 14.0]);
+        assert_eq!(*x.borrow_value(), [1.0, 2.0]);
+        y.reduce_sum().backward();
+        assert_eq!(*x.borrow_grad().unwrap(), [3.0, 4.0]);
+        // d_dw = (2x + 2w - 1) / 2
+        assert_eq!(*w.borrow_grad().unwrap(), [3.5, 5.5]);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::ops::grad::{log_softmax_axis_grad, softmax_axis_grad};
+use crate::ops::vec::{log_softmax_axis, softmax_axis};
+use crate::tensor::{TensorBox, TensorTrait};
+use crate::tensor_data::TensorData;
+use crate::{
+    dtype::FloatDtype,
+    ops::Op,
+    shape::{Dims, ReduceAxis, Shape},
+    tensor::Tensor,
+};
+use std::rc::Rc;
+
+// Softmax and log_softmax are fused rather than built from exp, sum_axis and div, so that the
+// max of each slice can be subtracted before exponentiating. Their backward uses the output
+// `t` rather than recomputing it from the operand.
+macro_rules! impl_softmax_op {
+    ($s:ident, $tf:ident, $f:expr, $df:expr) => {
+        #[derive(Debug)]
+        pub struct $s<T: FloatDtype, S: Dims>(pub(crate) Tensor<T, S>, pub(crate) usize);
+
+        impl<T: FloatDtype, S: Dims> Op for $s<T, S> {
+            type Produces = Tensor<T, S>;
+
+            fn propogate_grad(&self, t: &Self::Produces) {
+                // t = f(a, axis)
+                if let Some(d_dt) = t.data.grad_ref().as_ref() {
+                    let d_da = $df(&t.borrow_value(), d_dt, &t.shape.dims(), self.1);
+                    self.0.update_grad(d_da);
+                } else {
+                    panic!("Attempted to propogate grad, but no grad value exists.")
+                }
+            }
+
+            fn recompute(&self, t: &Self::Produces) {
+                let data = $f(&self.0.borrow_value(), &t.shape.dims(), self.1);
+                t.data.replace(data)
+            }
+
+            fn forward(self) -> Self::Produces {
+                let value = $f(&self.0.borrow_value(), &self.0.shape.dims(), self.1);
+                let data = TensorData::new(value, self.0.requires_grad());
+                let shape = self.0.shape.clone();
+                unsafe {
+                    Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape)
+                }
+            }
+
+            fn operands(&self) -> Vec<TensorBox<'_>> {
+                vec![TensorBox::new(self.0.id, &self.0)]
+            }
+        }
+    };
+}
+
+impl_softmax_op!(SoftmaxStruct, softmax, softmax_axis, softmax_axis_grad);
+impl_softmax_op!(
+    LogSoftmaxStruct,
+    log_softmax,
+    log_softmax_axis,
+    log_softmax_axis_grad
+);
+
+impl<T: FloatDtype, S: Shape> Tensor<T, S> {
+    /// Normalize `exp(x)` to sum to 1 along `AXIS`.
+    pub fn softmax<const AXIS: usize>(self) -> Self
+    where
+        S: ReduceAxis<AXIS>,
+    {
+        SoftmaxStruct(self, AXIS).forward()
+    }
+
+    /// `ln(softmax(x))` along `AXIS`, i.e. `x - logsumexp(x)`.
+    pub fn log_softmax<const AXIS: usize>(self) -> Self
+    where
+        S: ReduceAxis<AXIS>,
+    {
+        LogSoftmaxStruct(self, AXIS).forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+    use crate::test_util::check_grad;
+
+    #[test]
+    fn test_large_logits() {
+        let x = Tensor::new([[1000.0, 1000.0], [-1000.0, 0.0]]);
+        let s = x.clone().softmax::<1>();
+        assert_eq!(*s.borrow_value(), [0.5, 0.5, 0.0, 1.0]);
+        let l = x.clone().log_softmax::<1>();
+        assert_eq!(
+            *l.borrow_value(),
+            [-(2f64.ln()), -(2f64.ln()), -1000.0, 0.0]
+        );
+        let m: Tensor<f64, (I<2>,)> = x.logsumexp::<0>();
+        assert_eq!(*m.borrow_value(), [1000.0, 1000.0]);
+    }
+
+    #[test]
+    fn test_softmax_grads() {
+        type T23 = Tensor<f64, (I<2>, I<3>)>;
+        let x = [0.5, -0.2, 1.4, 2.2, 0.0, -1.1];
+        check_grad(|t: T23| t.softmax::<1>(), &x);
+        check_grad(|t: T23| t.softmax::<0>(), &x);
+        check_grad(|t: T23| t.log_softmax::<1>(), &x);
+        check_grad(|t: T23| t.logsumexp_keepdim::<1>(), &x);
+        check_grad(|t: T23| t.logsumexp::<0>(), &x);
+    }
+}
@@ -5,52 +5,64 @@ use crate::ops::vec::{el_add, el_div, el_max, el_min, el_mul, el_relu, el_sub, m
 use crate::tensor::{TensorBox, TensorTrait};
 use crate::tensor_data::TensorData;
 use crate::{
-    dtype::Dtype,
+    dtype::NumDtype,
     ops::Op,
-    shape::{Shape, I},
+    shape::{BroadcastTo, Dim, Dims, I},
     tensor::Tensor,
 };
 use std::{
-    ops::{Add, Div, Mul, Sub},
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign},
     rc::Rc,
 };
 
-use super::grad::reduce_sum_grad;
-use super::vec::{expand_to_shape, transpose2d};
+use super::grad::{bmm_grad, reduce_sum_grad};
+use super::vec::{bmm, broadcast, expand_to_shape, reduce_to_shape, transpose2d};
 
 macro_rules! impl_bin_el_op {
     ($s:ident, $t:ident, $tf:ident, $f:expr, $df:expr) => {
-        impl<T: Dtype, S: Shape> Op for $s<T, S> {
-            type Produces = Tensor<T, S>;
+        impl<T: NumDtype, S1: Dims, S2: Dims> Op for $s<T, S1, S2>
+        where
+            S1: BroadcastTo<S2>,
+        {
+            type Produces = Tensor<T, <S1 as BroadcastTo<S2>>::Output>;
 
             fn propogate_grad(&self, t: &Self::Produces) {
-                // t = f(a, b)
+                // t = f(broadcast(a), broadcast(b))
                 if let Some(d_dt) = t.data.grad_ref().as_ref() {
+                    let out_shape = t.shape.dims();
+                    let (a_shape, b_shape) = (self.0.shape.dims(), self.1.shape.dims());
                     let (d_da, d_db) = {
                         let a = self.0.borrow_value();
+                        let a = broadcast(&a, &a_shape, &out_shape);
                         let b = self.1.borrow_value();
+                        let b = broadcast(&b, &b_shape, &out_shape);
                         let (dt_da, dt_db) = $df(&a, &b);
                         (el_mul(d_dt, &dt_da), el_mul(d_dt, &dt_db))
                     };
-                    self.0.update_grad(d_da);
-                    self.1.update_grad(d_db);
+                    // Sum the grad over any dims that were broadcast in the forward pass
+                    self.0
+                        .update_grad(reduce_to_shape(d_da, &out_shape, &a_shape));
+                    self.1
+                        .update_grad(reduce_to_shape(d_db, &out_shape, &b_shape));
                 } else {
                     panic!("Attempted to propogate grad, but no grad value exists.")
                 }
             }
 
             fn recompute(&self, t: &Self::Produces) {
-                let data = $f(&self.0.borrow_value(), &self.1.borrow_value()).into();
-                t.data.replace(data)
+                t.data.replace(self.compute(&t.shape.dims()))
             }
 
             fn forward(self) -> Self::Produces {
-                let value = $f(&self.0.borrow_value(), &self.1.borrow_value());
+                let shape = self.0.shape.broadcast_shape(&self.1.shape);
+                let value = self.compute(&shape.dims());
                 let data = TensorData::new(value, self.0.requires_grad() || self.1.requires_grad());
-                unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
+                unsafe {
+                    Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape)
+                }
             }
 
-            fn operands(&self) -> Vec<TensorBox> {
+            fn operands(&self) -> Vec<TensorBox<'_>> {
                 vec![
                     TensorBox::new(self.0.id, &self.0),
                     TensorBox::new(self.1.id, &self.1),
@@ -58,46 +70,104 @@ macro_rules! impl_bin_el_op {
             }
         }
 
-        impl<T: Dtype, S: Shape> $t for Tensor<T, S> {
-            type Output = Self;
-            fn $tf(self, other: Self) -> Self {
+        impl<T: NumDtype, S1: Dims, S2: Dims> $s<T, S1, S2> {
+            fn compute(&self, out_shape: &[usize]) -> Vec<T> {
+                let a = self.0.borrow_value();
+                let b = self.1.borrow_value();
+                $f(
+                    &broadcast(&a, &self.0.shape.dims(), out_shape),
+                    &broadcast(&b, &self.1.shape.dims(), out_shape),
+                )
+            }
+        }
+
+        impl<T: NumDtype, S1: Dims, S2: Dims> $t<Tensor<T, S2>> for Tensor<T, S1>
+        where
+            S1: BroadcastTo<S2>,
+        {
+            type Output = Tensor<T, <S1 as BroadcastTo<S2>>::Output>;
+            fn $tf(self, other: Tensor<T, S2>) -> Self::Output {
                 $s(self, other).forward()
             }
         }
+
+        // Operands taken by reference are shallow cloned, so the graph sees later writes to
+        // them, e.g. when a traced model replaces its inputs, just as if they had been moved in
+        impl<T: NumDtype, S1: Dims, S2: Dims> $t<&Tensor<T, S2>> for Tensor<T, S1>
+        where
+            S1: BroadcastTo<S2>,
+        {
+            type Output = Tensor<T, <S1 as BroadcastTo<S2>>::Output>;
+            fn $tf(self, other: &Tensor<T, S2>) -> Self::Output {
+                $s(self, other.shallow_clone()).forward()
+            }
+        }
+
+        impl<T: NumDtype, S1: Dims, S2: Dims> $t<Tensor<T, S2>> for &Tensor<T, S1>
+        where
+            S1: BroadcastTo<S2>,
+        {
+            type Output = Tensor<T, <S1 as BroadcastTo<S2>>::Output>;
+            fn $tf(self, other: Tensor<T, S2>) -> Self::Output {
+                $s(self.shallow_clone(), other).forward()
+            }
+        }
+
+        impl<T: NumDtype, S1: Dims, S2: Dims> $t<&Tensor<T, S2>> for &Tensor<T, S1>
+        where
+            S1: BroadcastTo<S2>,
+        {
+            type Output = Tensor<T, <S1 as BroadcastTo<S2>>::Output>;
+            fn $tf(self, other: &Tensor<T, S2>) -> Self::Output {
+                $s(self.shallow_clone(), other.shallow_clone()).forward()
+            }
+        }
     };
 }
 
 // Ops
 
 #[derive(Debug)]
-pub struct ElAddStruct<T: Dtype, S: Shape>(Tensor<T, S>, Tensor<T, S>);
+pub struct ElAddStruct<T: NumDtype, S1: Dims, S2: Dims>(Tensor<T, S1>, Tensor<T, S2>);
+
+#[derive(Debug)]
+pub struct ElSubStruct<T: NumDtype, S1: Dims, S2: Dims>(Tensor<T, S1>, Tensor<T, S2>);
 
 #[derive(Debug)]
-pub struct ElSubStruct<T: Dtype, S: Shape>(Tensor<T, S>, Tensor<T, S>);
+pub struct ElMulStruct<T: NumDtype, S1: Dims, S2: Dims>(Tensor<T, S1>, Tensor<T, S2>);
 
 #[derive(Debug)]
-pub struct ElMulStruct<T: Dtype, S: Shape>(Tensor<T, S>, Tensor<T, S>);
+pub struct ElDivStruct<T: NumDtype, S1: Dims, S2: Dims>(Tensor<T, S1>, Tensor<T, S2>);
 
 #[derive(Debug)]
-pub struct ElDivStruct<T: Dtype, S: Shape>(Tensor<T, S>, Tensor<T, S>);
+pub struct ElMaxStruct<T: NumDtype, S1: Dims, S2: Dims>(Tensor<T, S1>, Tensor<T, S2>);
 
 #[derive(Debug)]
-pub struct ElMaxStruct<T: Dtype, S: Shape>(Tensor<T, S>, Tensor<T, S>);
+pub struct ElMinStruct<T: NumDtype, S1: Dims, S2: Dims>(Tensor<T, S1>, Tensor<T, S2>);
 
 #[derive(Debug)]
-pub struct ElMinStruct<T: Dtype, S: Shape>(Tensor<T, S>, Tensor<T, S>);
+pub struct ElReLUStruct<T: NumDtype, S: Dims>(Tensor<T, S>);
 
 #[derive(Debug)]
-pub struct ElReLUStruct<T: Dtype, S: Shape>(Tensor<T, S>);
+pub struct DetachStruct<T: NumDtype, S: Dims>(Tensor<T, S>);
 
 #[derive(Debug)]
-pub struct DetachStruct<T: Dtype, S: Shape>(Tensor<T, S>);
+pub struct ReduceSumStruct<T: NumDtype, S: Dims>(Tensor<T, S>);
 
+/// Matmul of a `(N, M)` and a `(M, O)` tensor, with output shape `So`.
 #[derive(Debug)]
-pub struct ReduceSumStruct<T: Dtype, S: Shape>(Tensor<T, S>);
+pub struct MatmulStruct<T: NumDtype, S1: Dims, S2: Dims, So: Dims>(
+    pub(crate) Tensor<T, S1>,
+    pub(crate) Tensor<T, S2>,
+    pub(crate) So,
+);
 
 #[derive(Debug)]
-pub struct MatmulStruct<T: Dtype, S1: Shape, S2: Shape>(Tensor<T, S1>, Tensor<T, S2>);
+pub struct BmmStruct<T: NumDtype, S1: Dims, S2: Dims, So: Dims>(
+    pub(crate) Tensor<T, S1>,
+    pub(crate) Tensor<T, S2>,
+    pub(crate) So,
+);
 
 impl_bin_el_op!(ElAddStruct, Add, add, el_add, el_add_grad);
 impl_bin_el_op!(ElSubStruct, Sub, sub, el_sub, el_sub_grad);
@@ -106,20 +176,61 @@ impl_bin_el_op!(ElDivStruct, Div, div, el_div, el_div_grad);
 impl_bin_el_op!(ElMaxStruct, Max, max, el_max, el_max_grad);
 impl_bin_el_op!(ElMinStruct, Min, min, el_min, el_min_grad);
 
+// Assigning replaces the tensor with the output of a new op, whose operand is the old node, so
+// the graph up to it is kept. Clones taken before the assignment keep the old value. The rhs can
+// be anything the op takes, a tensor or a scalar, as long as the output has the shape of the
+// tensor being assigned to.
+macro_rules! impl_assign_op {
+    ($t:ident, $tf:ident, $o:ident, $f:ident) => {
+        impl<T: NumDtype, S: Dims, Rhs> $t<Rhs> for Tensor<T, S>
+        where
+            Self: $o<Rhs, Output = Self>,
+        {
+            fn $tf(&mut self, other: Rhs) {
+                *self = self.shallow_clone().$f(other);
+            }
+        }
+    };
+}
+
+impl_assign_op!(AddAssign, add_assign, Add, add);
+impl_assign_op!(SubAssign, sub_assign, Sub, sub);
+impl_assign_op!(MulAssign, mul_assign, Mul, mul);
+impl_assign_op!(DivAssign, div_assign, Div, div);
+
+/// Elementwise maximum. Like the arithmetic ops it broadcasts, and takes operands by value or by
+/// reference.
+///
+/// ```
+/// #![feature(generic_const_exprs)]
+/// use mlframework::{ops::Max, shape::I, Tensor};
+/// let a = Tensor::new([[1.0, 4.0], [5.0, 0.0]]);
+/// let b = Tensor::new([3.0, 2.0]);
+/// let _: Tensor<f64, (I<2>, I<2>)> = a.max(&b);
+/// ```
 pub trait Max<Rhs = Self> {
     type Output;
 
-    fn max(self, other: Self) -> Self;
+    fn max(self, other: Rhs) -> Self::Output;
 }
 
+/// Elementwise minimum, the counterpart of `Max`.
+///
+/// ```
+/// #![feature(generic_const_exprs)]
+/// use mlframework::{ops::Min, shape::I, Tensor};
+/// let a = Tensor::new([1.0, 4.0]);
+/// let b = Tensor::new([3.0, 2.0]);
+/// let _: Tensor<f64, (I<2>,)> = (&a).min(b);
+/// ```
 pub trait Min<Rhs = Self> {
     type Output;
 
-    fn min(self, other: Self) -> Self;
+    fn min(self, other: Rhs) -> Self::Output;
 }
 
 // ReLU
-impl<T: Dtype, S: Shape> Op for ElReLUStruct<T, S> {
+impl<T: NumDtype, S: Dims> Op for ElReLUStruct<T, S> {
     type Produces = Tensor<T, S>;
 
     fn propogate_grad(&self, t: &Self::Produces) {
@@ -144,19 +255,34 @@ impl<T: Dtype, S: Shape> Op for ElReLUStruct<T, S> {
 
     fn forward(self) -> Self::Produces {
         let data = TensorData::new(el_relu(&self.0.borrow_value()), self.0.requires_grad());
-        unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
+        let shape = self.0.shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
     }
 
-    fn operands(&self) -> Vec<TensorBox> {
+    fn operands(&self) -> Vec<TensorBox<'_>> {
         vec![TensorBox::new(self.0.id, &self.0)]
     }
 }
 
 // Matmul
-impl<const N: usize, const M: usize, const O: usize, T: Dtype> Op
-    for MatmulStruct<T, (I<N>, I<M>), (I<M>, I<O>)>
-{
-    type Produces = Tensor<T, (I<N>, I<O>)>;
+// Static dims are checked at compile time by requiring the inner dims to have the same type,
+// `Dyn` dims and `DynShape`s are checked at runtime when the op is created.
+impl<T: NumDtype, S1: Dims, S2: Dims, So: Dims> MatmulStruct<T, S1, S2, So> {
+    fn sizes(&self) -> (usize, usize, usize) {
+        let (a, b) = (self.0.shape.dims(), self.1.shape.dims());
+        (a[0], a[1], b[1])
+    }
+
+    fn compute(&self) -> Vec<T> {
+        let (n, m, o) = self.sizes();
+        let a = self.0.borrow_value(); // shape = (N, M)
+        let b = self.1.borrow_value(); // shape = (M, O)
+        matmul(&a, &b, n, m, o)
+    }
+}
+
+impl<T: NumDtype, S1: Dims, S2: Dims, So: Dims> Op for MatmulStruct<T, S1, S2, So> {
+    type Produces = Tensor<T, So>;
 
     fn propogate_grad(&self, t: &Self::Produces) {
         // t = matmul(a, b)     shape: (N, O)
@@ -165,11 +291,12 @@ impl<const N: usize, const M: usize, const O: usize, T: Dtype> Op
         // dt_db = a^T          shape: (M, N)
         // d_db = dt_db @ d_dt    shape: (M, O)
         if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let (n, m, o) = self.sizes();
             let (d_da, d_db) = {
                 let a = self.0.borrow_value();
                 let b = self.1.borrow_value();
-                let d_da = matmul(d_dt, &transpose2d(&b, O), N, O, M);
-                let d_db = matmul(&transpose2d(&a, M), d_dt, M, N, O);
+                let d_da = matmul(d_dt, &transpose2d(&b, o), n, o, m);
+                let d_db = matmul(&transpose2d(&a, m), d_dt, m, n, o);
                 (d_da, d_db)
             };
             self.0.update_grad(d_da);
@@ -180,26 +307,73 @@ impl<const N: usize, const M: usize, const O: usize, T: Dtype> Op
     }
 
     fn recompute(&self, t: &Self::Produces) {
-        let data = {
-            let a = self.0.borrow_value(); // shape = (N, M)
-            let b = self.1.borrow_value(); // shape = (M, O)
-            matmul(&a, &b, N, M, O)
-        };
-        t.data.replace(data)
+        t.data.replace(self.compute())
     }
 
     fn forward(self) -> Self::Produces {
-        let data = {
-            let a = self.0.borrow_value(); // shape = (N, M)
-            let b = self.1.borrow_value(); // shape = (M, O)
-            matmul(&a, &b, N, M, O)
-        };
-        let td = TensorData::new(data, self.0.requires_grad() || self.1.requires_grad());
+        let (a, b) = (self.0.shape.dims(), self.1.shape.dims());
+        assert_eq!(a[1], b[0], "Inner dims of matmul operands do not match");
+        let td = TensorData::new(
+            self.compute(),
+            self.0.requires_grad() || self.1.requires_grad(),
+        );
+        let shape = self.2.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(td, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![
+            TensorBox::new(self.0.id, &self.0),
+            TensorBox::new(self.1.id, &self.1),
+        ]
+    }
+}
+
+// Batched matmul
+// The lhs is `(B, N, M)` and the rhs is either a stack of matrices with the same batch dim, or a
+// single matrix which is broadcast across the batch.
+impl<T: NumDtype, S1: Dims, S2: Dims, So: Dims> BmmStruct<T, S1, S2, So> {
+    fn sizes(&self) -> (usize, usize, usize, usize) {
+        let a = self.0.shape.dims();
+        (a[0], a[1], a[2], *self.1.shape.dims().last().unwrap())
+    }
+
+    fn compute(&self) -> Vec<T> {
+        let (b, n, m, o) = self.sizes();
+        bmm(&self.0.borrow_value(), &self.1.borrow_value(), b, n, m, o)
+    }
+}
 
-        unsafe { Self::Produces::from_rc_td_and_op_unchecked(td, Rc::new(self)) }
+impl<T: NumDtype, S1: Dims, S2: Dims, So: Dims> Op for BmmStruct<T, S1, S2, So> {
+    type Produces = Tensor<T, So>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            let (d_da, d_db) = {
+                let (a, b) = (self.0.borrow_value(), self.1.borrow_value());
+                bmm_grad(&a, &b, d_dt, self.sizes())
+            };
+            self.0.update_grad(d_da);
+            self.1.update_grad(d_db);
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
     }
 
-    fn operands(&self) -> Vec<TensorBox> {
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.compute())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let td = TensorData::new(
+            self.compute(),
+            self.0.requires_grad() || self.1.requires_grad(),
+        );
+        let shape = self.2.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(td, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
         vec![
             TensorBox::new(self.0.id, &self.0),
             TensorBox::new(self.1.id, &self.1),
@@ -208,7 +382,7 @@ impl<const N: usize, const M: usize, const O: usize, T: Dtype> Op
 }
 
 // Detach
-impl<T: Dtype, S: Shape> Op for DetachStruct<T, S> {
+impl<T: NumDtype, S: Dims> Op for DetachStruct<T, S> {
     type Produces = Tensor<T, S>;
 
     fn propogate_grad(&self, _t: &Self::Produces) {
@@ -217,23 +391,24 @@ impl<T: Dtype, S: Shape> Op for DetachStruct<T, S> {
     }
 
     fn recompute(&self, t: &Self::Produces) {
-        let data = self.0.borrow_value().clone();
+        let data = self.0.borrow_value().to_vec();
         t.data.replace(data)
     }
 
     fn forward(self) -> Self::Produces {
-        let value = self.0.borrow_value().clone();
+        let value = self.0.borrow_value().to_vec();
         let data = TensorData::new(value, false);
-        unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
+        let shape = self.0.shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
     }
 
-    fn operands(&self) -> Vec<TensorBox> {
+    fn operands(&self) -> Vec<TensorBox<'_>> {
         vec![TensorBox::new(self.0.id, &self.0)]
     }
 }
 
 // Reduce sum
-impl<T: Dtype, S: Shape> Op for ReduceSumStruct<T, S> {
+impl<T: NumDtype, S: Dims> Op for ReduceSumStruct<T, S> {
     type Produces = Tensor<T, (I<1>,)>;
 
     fn propogate_grad(&self, t: &Self::Produces) {
@@ -262,23 +437,161 @@ impl<T: Dtype, S: Shape> Op for ReduceSumStruct<T, S> {
         unsafe { Self::Produces::from_rc_td_and_op_unchecked(data, Rc::new(self)) }
     }
 
-    fn operands(&self) -> Vec<TensorBox> {
+    fn operands(&self) -> Vec<TensorBox<'_>> {
         vec![TensorBox::new(self.0.id, &self.0)]
     }
 }
 
-impl<T: Dtype, S: Shape> Tensor<T, S> {
-    pub fn relu(self) -> Self {
-        ElReLUStruct(self).forward()
+impl<T: NumDtype, S: Dims> Tensor<T, S> {
+    pub fn relu(&self) -> Self {
+        ElReLUStruct(self.shallow_clone()).forward()
+    }
+
+    pub fn reduce_sum(&self) -> Tensor<T, (I<1>,)> {
+        ReduceSumStruct(self.shallow_clone()).forward()
+    }
+}
+
+impl<T: NumDtype, N: Dim, M: Dim> Tensor<T, (N, M)> {
+    pub fn matmul<O: Dim>(&self, other: Tensor<T, (M, O)>) -> Tensor<T, (N, O)> {
+        let shape = (self.shape.0, other.shape.1);
+        MatmulStruct(self.shallow_clone(), other, shape).forward()
+    }
+}
+
+impl<T: NumDtype, B: Dim, N: Dim, M: Dim> Tensor<T, (B, N, M)> {
+    /// Batched matmul of two stacks of matrices.
+    pub fn bmm<O: Dim>(self, other: Tensor<T, (B, M, O)>) -> Tensor<T, (B, N, O)> {
+        assert_eq!(
+            self.shape.0, other.shape.0,
+            "Batch dims of bmm operands do not match"
+        );
+        assert_eq!(
+            self.shape.2, other.shape.1,
+            "Inner dims of bmm operands do not match"
+        );
+        let shape = (self.shape.0, self.shape.1, other.shape.2);
+        BmmStruct(self, other, shape).forward()
     }
 
-    pub fn reduce_sum(self) -> Tensor<T, (I<1>,)> {
-        ReduceSumStruct(self).forward()
+    /// Matmul of each matrix in the batch with the same 2d `other`.
+    pub fn matmul<O: Dim>(&self, other: Tensor<T, (M, O)>) -> Tensor<T, (B, N, O)> {
+        assert_eq!(
+            self.shape.2, other.shape.0,
+            "Inner dims of matmul operands do not match"
+        );
+        let shape = (self.shape.0, self.shape.1, other.shape.1);
+        BmmStruct(self.shallow_clone(), other, shape).forward()
     }
 }
 
-impl<const N: usize, const M: usize, T: Dtype> Tensor<T, (I<N>, I<M>)> {
-    pub fn matmul<const O: usize>(self, other: Tensor<T, (I<M>, I<O>)>) -> Tensor<T, (I<N>, I<O>)> {
-        MatmulStruct(self, other).forward()
+#[cfg(test)]
+mod tests {
+    use crate::shape::{Dyn, I};
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_reference_ops() {
+        use crate::ops::tensor::{Max, Min};
+        let x = Tensor::new_with_grad([1.0, -2.0]);
+        let y = Tensor::new_with_grad([3.0, 4.0]);
+        let s = &x * &y + &x - x.relu() + (&y / y.clone()) + (&x).max(&y) + (&x).min(y.clone());
+        assert_eq!(*s.borrow_value(), [8.0, -7.0]);
+        s.reduce_sum().backward();
+        // d_dx = y + 1 - relu'(x) + (x < y), d_dy = x + (x < y)
+        assert_eq!(*x.borrow_grad().unwrap(), [4.0, 6.0]);
+        assert_eq!(*y.borrow_grad().unwrap(), [2.0, -1.0]);
+    }
+
+    #[test]
+    fn test_reference_ops_see_writes() {
+        let x = Tensor::new([[1.0, -2.0]]);
+        let w = Tensor::new([[1.0], [1.0]]);
+        let y = (&x + &x).relu().matmul(w);
+        x.replace_data_with(vec![3.0, 4.0]);
+        y.recompute();
+        assert_eq!(*y.borrow_value(), [14.0]);
+    }
+
+    #[test]
+    fn test_broadcast_add_bias() {
+        let x = Tensor::new_with_grad([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let b = Tensor::new_with_grad([10.0, 20.0, 30.0]);
+        let y: Tensor<f64, (I<2>, I<3>)> = x.clone() + b.clone();
+        assert_eq!(*y.borrow_value(), [11.0, 22.0, 33.0, 14.0, 25.0, 36.0]);
+
+        y.reduce_sum().backward();
+        assert_eq!(x.borrow_grad().as_deref(), Some(&[1.0; 6][..]));
+        assert_eq!(b.borrow_grad().as_deref(), Some(&[2.0; 3][..]));
+    }
+
+    #[test]
+    fn test_broadcast_mul_column() {
+        let x = Tensor::new_with_grad([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let c = Tensor::new_with_grad([[2.0], [3.0]]);
+        let y: Tensor<f64, (I<2>, I<3>)> = c.clone() * x.clone();
+        assert_eq!(*y.borrow_value(), [2.0, 4.0, 6.0, 12.0, 15.0, 18.0]);
+
+        y.reduce_sum().backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[2.0, 2.0, 2.0, 3.0, 3.0, 3.0][..])
+        );
+        assert_eq!(c.borrow_grad().as_deref(), Some(&[6.0, 15.0][..]));
+    }
+
+    #[test]
+    fn test_dyn_matmul() {
+        let x = Tensor::from_vec_and_shape_with_grad(vec![1.0; 12], (Dyn(4), I::<3>));
+        let w = Tensor::new_with_grad([[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]]);
+        let y: Tensor<f64, (Dyn, I<2>)> = x.clone().matmul(w.clone());
+        assert_eq!(y.shape(), &(Dyn(4), I));
+        assert_eq!(
+            *y.borrow_value(),
+            [9.0, 12.0, 9.0, 12.0, 9.0, 12.0, 9.0, 12.0]
+        );
+
+        let b = Tensor::new([1.0, -1.0]);
+        (y + b).reduce_sum().backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[3.0, 7.0, 11.0].repeat(4)[..])
+        );
+        assert_eq!(
+            w.borrow_grad().as_deref(),
+            Some(&[4.0, 4.0, 4.0, 4.0, 4.0, 4.0][..])
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_dyn_matmul_mismatch() {
+        let x = Tensor::from_vec_and_shape(vec![1.0; 6], (Dyn(2), Dyn(3)));
+        let y = Tensor::from_vec_and_shape(vec![1.0; 8], (Dyn(4), Dyn(2)));
+        x.matmul(y);
+    }
+
+    #[test]
+    fn test_bmm_grad() {
+        let a = Tensor::new_with_grad([[[1.0, 2.0]], [[3.0, 4.0]]]);
+        let b = Tensor::new_with_grad([[[1.0], [2.0]], [[3.0], [4.0]]]);
+        let c: Tensor<f64, (I<2>, I<1>, I<1>)> = a.clone().bmm(b.clone());
+        assert_eq!(*c.borrow_value(), [5.0, 25.0]);
+
+        c.reduce_sum().backward();
+        assert_eq!(a.borrow_grad().as_deref(), Some(&[1.0, 2.0, 3.0, 4.0][..]));
+        assert_eq!(b.borrow_grad().as_deref(), Some(&[1.0, 2.0, 3.0, 4.0][..]));
+    }
+
+    #[test]
+    fn test_bmm_broadcast_rhs() {
+        let a = Tensor::new_with_grad([[[1.0, 2.0]], [[3.0, 4.0]]]);
+        let w = Tensor::new_with_grad([[1.0], [2.0]]);
+        let c: Tensor<f64, (I<2>, I<1>, I<1>)> = a.clone().matmul(w.clone());
+        assert_eq!(*c.borrow_value(), [5.0, 11.0]);
+
+        c.reduce_sum().backward();
+        assert_eq!(a.borrow_grad().as_deref(), Some(&[1.0, 2.0, 1.0, 2.0][..]));
+        assert_eq!(w.borrow_grad().as_deref(), Some(&[4.0, 6.0][..]));
     }
 }
@@ -1,16 +1,22 @@
-use crate::dtype::Dtype;
+use crate::dtype::{Dtype, FloatDtype, NumDtype, SignedDtype};
+use std::borrow::Cow;
 
-pub(crate) fn ones_like<T: Dtype>(a: &[T]) -> Vec<T> {
+pub(crate) fn ones_like<T: NumDtype>(a: &[T]) -> Vec<T> {
     vec![T::one(); a.len()]
 }
-pub(crate) fn zeros_like<T: Dtype>(a: &[T]) -> Vec<T> {
+pub(crate) fn zeros_like<T: NumDtype>(a: &[T]) -> Vec<T> {
     vec![T::zero(); a.len()]
 }
-pub(crate) fn ones<T: Dtype>(n: usize) -> Vec<T> {
+pub(crate) fn ones<T: NumDtype>(n: usize) -> Vec<T> {
     vec![T::one(); n]
 }
+/// `-1` without `Neg`, so grads which negate can be written for unsigned dtypes. Unsigned tensors
+/// can't require grad, so this is never evaluated for them.
+pub(crate) fn neg_one<T: NumDtype>() -> T {
+    T::zero() - T::one()
+}
 
-pub(crate) fn dot<T: Dtype>(a: &[T], b: &[T]) -> T {
+pub(crate) fn dot<T: NumDtype>(a: &[T], b: &[T]) -> T {
     assert_eq!(a.len(), b.len());
     a.iter()
         .zip(b.iter())
@@ -20,7 +26,7 @@ pub(crate) fn dot<T: Dtype>(a: &[T], b: &[T]) -> T {
 /// Perform a 2d transpose on an array ref that represents an
 /// (m x n) matrix.
 pub(crate) fn transpose2d<T: Dtype>(a: &[T], n: usize) -> Vec<T> {
-    assert!(a.len() % n == 0);
+    assert!(a.len().is_multiple_of(n));
     let m = a.len() / n;
     let mut data = Vec::with_capacity(a.len());
     for i in 0..n {
@@ -48,19 +54,27 @@ fn test_transpose2d() {
     assert_eq!(v_transpose, target);
 }
 
+/// Row-major strides for an array of `shape`.
+pub(crate) fn strides(shape: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1; shape.len()];
+    for i in (0..shape.len().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * shape[i + 1];
+    }
+    strides
+}
+
 /// Perform a matmul op between two array refs that represent matrices with the shapes below
 /// a: (n, m)
 /// b: (m, o)
-pub(crate) fn matmul<T: Dtype>(a: &[T], b: &[T], n: usize, m: usize, o: usize) -> Vec<T> {
+pub(crate) fn matmul<T: NumDtype>(a: &[T], b: &[T], n: usize, m: usize, o: usize) -> Vec<T> {
     assert_eq!(n * m, a.len());
     assert_eq!(m * o, b.len());
+    let b_t = transpose2d(b, o); // shape = (O, M)
     a.chunks(m).fold(Vec::with_capacity(n * o), |v, a_row| {
-        let b_t = transpose2d(b, o); // shape = (O, M)
-        let v = b_t.chunks(m).fold(v, |mut v, b_col| {
+        b_t.chunks(m).fold(v, |mut v, b_col| {
             v.push(dot(a_row, b_col));
             v
-        });
-        v
+        })
     })
 }
 
@@ -87,12 +101,61 @@ fn test_matmul() {
     assert_eq!(a_mat_b, target);
 }
 
+/// Perform a batched matmul between array refs that represent stacks of matrices
+/// a: (batch, n, m)
+/// b: (batch, m, o), or (m, o) which is shared across the batch
+pub(crate) fn bmm<T: NumDtype>(
+    a: &[T],
+    b: &[T],
+    batch: usize,
+    n: usize,
+    m: usize,
+    o: usize,
+) -> Vec<T> {
+    assert_eq!(batch * n * m, a.len());
+    let b_stride = if b.len() == m * o { 0 } else { m * o };
+    // Written so that an empty batch doesn't underflow
+    assert_eq!(batch * b_stride + m * o - b_stride, b.len());
+    let mut data = Vec::with_capacity(batch * n * o);
+    for (i, a_mat) in a.chunks(n * m).enumerate() {
+        let b_mat = &b[i * b_stride..i * b_stride + m * o];
+        data.extend(matmul(a_mat, b_mat, n, m, o));
+    }
+    data
+}
+
+/// Transpose each matrix in a stack of (n x m) matrices.
+pub(crate) fn batch_transpose2d<T: NumDtype>(a: &[T], n: usize, m: usize) -> Vec<T> {
+    a.chunks(n * m)
+        .flat_map(|mat| transpose2d(mat, m))
+        .collect()
+}
+
+#[test]
+fn test_bmm() {
+    let (n, m, o): (usize, usize, usize) = (3, 4, 2);
+    let a: Vec<i32> = (0..(n * m) as i32).collect();
+    let b: Vec<i32> = (0..(m * o) as i32).collect();
+    let target = vec![28, 34, 76, 98, 124, 162];
+
+    let a2 = [a.clone(), a.clone()].concat();
+    let b2 = [b.clone(), b.clone()].concat();
+    assert_eq!(
+        bmm(&a2, &b2, 2, n, m, o),
+        [target.clone(), target.clone()].concat()
+    );
+    assert_eq!(bmm(&a2, &b, 2, n, m, o), [target.clone(), target].concat());
+
+    assert!(bmm::<i32>(&[], &[], 0, n, m, o).is_empty());
+    assert!(bmm(&[], &b, 0, n, m, o).is_empty());
+}
+
 pub(crate) fn expand_to_shape<T: Dtype>(a: &[T], len: usize) -> Vec<T> {
     assert_eq!(a.len(), 1);
     vec![a[0]; len]
 }
 
-pub(crate) fn el_bin<T: Dtype, F>(op: F, a: &[T], b: &[T]) -> Vec<T>
+pub(crate) fn el_bin<T: NumDtype, F>(op: F, a: &[T], b: &[T]) -> Vec<T>
 where
     F: Fn((&T, &T)) -> T,
 {
@@ -100,35 +163,35 @@ where
     a.iter().zip(b.iter()).map(op).collect()
 }
 
-pub(crate) fn el_mul<T: Dtype>(a: &[T], b: &[T]) -> Vec<T> {
+pub(crate) fn el_mul<T: NumDtype>(a: &[T], b: &[T]) -> Vec<T> {
     el_bin(|(x, y)| *x * *y, a, b)
 }
 
-pub(crate) fn el_add<T: Dtype>(a: &[T], b: &[T]) -> Vec<T> {
+pub(crate) fn el_add<T: NumDtype>(a: &[T], b: &[T]) -> Vec<T> {
     el_bin(|(x, y)| *x + *y, a, b)
 }
 
-pub(crate) fn el_sub<T: Dtype>(a: &[T], b: &[T]) -> Vec<T> {
+pub(crate) fn el_sub<T: NumDtype>(a: &[T], b: &[T]) -> Vec<T> {
     el_bin(|(x, y)| *x - *y, a, b)
 }
 
-pub(crate) fn el_div<T: Dtype>(a: &[T], b: &[T]) -> Vec<T> {
+pub(crate) fn el_div<T: NumDtype>(a: &[T], b: &[T]) -> Vec<T> {
     el_bin(|(x, y)| *x / *y, a, b)
 }
 
-pub(crate) fn el_max<T: Dtype>(a: &[T], b: &[T]) -> Vec<T> {
+pub(crate) fn el_max<T: NumDtype>(a: &[T], b: &[T]) -> Vec<T> {
     el_bin(|(x, y)| if *x >= *y { *x } else { *y }, a, b)
 }
 
-pub(crate) fn el_min<T: Dtype>(a: &[T], b: &[T]) -> Vec<T> {
+pub(crate) fn el_min<T: NumDtype>(a: &[T], b: &[T]) -> Vec<T> {
     el_bin(|(x, y)| if *x <= *y { *x } else { *y }, a, b)
 }
 
-pub(crate) fn el_gt<T: Dtype>(a: &[T], b: &[T]) -> Vec<T> {
+pub(crate) fn el_gt<T: NumDtype>(a: &[T], b: &[T]) -> Vec<T> {
     el_bin(|(x, y)| if *x >= *y { T::one() } else { T::zero() }, a, b)
 }
 
-pub(crate) fn el_lt<T: Dtype>(a: &[T], b: &[T]) -> Vec<T> {
+pub(crate) fn el_lt<T: NumDtype>(a: &[T], b: &[T]) -> Vec<T> {
     el_bin(|(x, y)| if *x <= *y { T::one() } else { T::zero() }, a, b)
 }
 
@@ -139,28 +202,1183 @@ where
     a.iter().map(op).collect()
 }
 
-pub(crate) fn el_neg<T: Dtype>(a: &[T]) -> Vec<T> {
+/// Elementwise `op(a, b)` into a possibly different dtype, e.g. `bool` for comparisons.
+pub(crate) fn el_map2<T1: Dtype, T2: Dtype, F>(op: F, a: &[T1], b: &[T1]) -> Vec<T2>
+where
+    F: Fn(T1, T1) -> T2,
+{
+    assert_eq!(a.len(), b.len());
+    a.iter().zip(b.iter()).map(|(x, y)| op(*x, *y)).collect()
+}
+
+/// `value` where `mask` is true, else `a`.
+pub(crate) fn masked_fill<T: Dtype>(a: &[T], mask: &[bool], value: T) -> Vec<T> {
+    assert_eq!(a.len(), mask.len());
+    a.iter()
+        .zip(mask)
+        .map(|(x, m)| if *m { value } else { *x })
+        .collect()
+}
+
+/// `a` where `cond` is true, else `b`.
+pub(crate) fn where_cond<T: Dtype>(cond: &[bool], a: &[T], b: &[T]) -> Vec<T> {
+    assert_eq!(cond.len(), a.len());
+    assert_eq!(cond.len(), b.len());
+    cond.iter()
+        .zip(a.iter().zip(b))
+        .map(|(c, (x, y))| if *c { *x } else { *y })
+        .collect()
+}
+
+pub(crate) fn el_neg<T: SignedDtype>(a: &[T]) -> Vec<T> {
     el_unary(|x| T::neg(*x), a)
 }
 
-pub(crate) fn el_relu<T: Dtype>(a: &[T]) -> Vec<T> {
+pub(crate) fn el_relu<T: NumDtype>(a: &[T]) -> Vec<T> {
     el_unary(|x| if *x >= T::zero() { *x } else { T::zero() }, a)
 }
 
-pub(crate) fn el_inv<T: Dtype>(a: &[T]) -> Vec<T> {
+pub(crate) fn el_inv<T: NumDtype>(a: &[T]) -> Vec<T> {
     el_unary(|x| T::one() / *x, a)
 }
 
-pub(crate) fn el_pos<T: Dtype>(a: &[T]) -> Vec<T> {
+pub(crate) fn el_pos<T: NumDtype>(a: &[T]) -> Vec<T> {
     // Used for relu grad
-    // Return 1 if x >= 0, else 0
-    el_unary(|x| if *x >= T::zero() { T::one() } else { T::zero() }, a)
+    // Return 1 if x > 0, else 0
+    el_unary(|x| if *x > T::zero() { T::one() } else { T::zero() }, a)
+}
+
+pub(crate) fn el_exp<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    el_unary(|x| x.exp(), a)
+}
+
+pub(crate) fn el_ln<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    el_unary(|x| x.ln(), a)
+}
+
+pub(crate) fn el_log1p<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    el_unary(|x| x.ln_1p(), a)
+}
+
+pub(crate) fn el_sqrt<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    el_unary(|x| x.sqrt(), a)
+}
+
+pub(crate) fn el_rsqrt<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    el_unary(|x| x.sqrt().recip(), a)
+}
+
+pub(crate) fn el_powf<T: FloatDtype>(a: &[T], p: T) -> Vec<T> {
+    el_unary(|x| x.powf(p), a)
+}
+
+pub(crate) fn el_powi<T: FloatDtype>(a: &[T], n: i32) -> Vec<T> {
+    el_unary(|x| x.powi(n), a)
+}
+
+pub(crate) fn el_sin<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    el_unary(|x| x.sin(), a)
+}
+
+pub(crate) fn el_cos<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    el_unary(|x| x.cos(), a)
+}
+
+pub(crate) fn el_tan<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    el_unary(|x| x.tan(), a)
+}
+
+pub(crate) fn el_tanh<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    el_unary(|x| x.tanh(), a)
+}
+
+/// Cast an `f64` constant to a float dtype.
+pub(crate) fn float_const<T: FloatDtype>(v: f64) -> T {
+    T::from_f64(v).expect("Failed to cast constant to dtype")
+}
+
+/// The error function. Uses the Taylor series for `|x| < 3` and a continued fraction for `erfc`
+/// beyond, both accurate to around 1e-13 in f64.
+pub(crate) fn erf<T: FloatDtype>(x: T) -> T {
+    let ax = x.abs();
+    let two_over_sqrt_pi: T = float_const(std::f64::consts::FRAC_2_SQRT_PI);
+    if ax < float_const(3.0) {
+        let (mut term, mut sum) = (x, x);
+        for n in 1..80 {
+            let n_t: T = float_const(n as f64);
+            term = -term * x * x / n_t;
+            let next = term / (n_t + n_t + T::one());
+            sum = sum + next;
+            if next.abs() < T::epsilon() * sum.abs() {
+                break;
+            }
+        }
+        two_over_sqrt_pi * sum
+    } else {
+        // erfc(x) = exp(-x^2) / sqrt(pi) / (x + (1/2) / (x + 1 / (x + (3/2) / (x + ...))))
+        let mut f = ax;
+        for k in (1..60).rev() {
+            f = ax + float_const::<T>(k as f64 / 2.0) / f;
+        }
+        let erfc = (-ax * ax).exp() * two_over_sqrt_pi / (f + f);
+        (T::one() - erfc).copysign(x)
+    }
+}
+
+pub(crate) fn sigmoid<T: FloatDtype>(x: T) -> T {
+    // Only exponentiate negative values so large inputs don't overflow
+    if x >= T::zero() {
+        (T::one() + (-x).exp()).recip()
+    } else {
+        x.exp() / (T::one() + x.exp())
+    }
+}
+
+pub(crate) fn softplus<T: FloatDtype>(x: T) -> T {
+    // ln(1 + e^x) = max(x, 0) + ln(1 + e^-|x|)
+    x.max(T::zero()) + (-x.abs()).exp().ln_1p()
+}
+
+/// Scale and alpha of selu.
+pub(crate) const SELU_LAMBDA: f64 = 1.050_700_987_355_480_5;
+pub(crate) const SELU_ALPHA: f64 = 1.673_263_242_354_377_3;
+
+/// Coefficient of the cubic term in the tanh approximation of gelu.
+pub(crate) const GELU_COEFF: f64 = 0.044_715;
+
+pub(crate) fn el_sigmoid<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    el_unary(|x| sigmoid(*x), a)
+}
+
+pub(crate) fn el_gelu<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    // x * Phi(x)
+    let half: T = float_const(0.5);
+    let frac_1_sqrt_2: T = float_const(std::f64::consts::FRAC_1_SQRT_2);
+    el_unary(|x| half * *x * (T::one() + erf(*x * frac_1_sqrt_2)), a)
+}
+
+pub(crate) fn el_gelu_tanh<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    // x / 2 * (1 + tanh(sqrt(2 / pi) * (x + c * x^3)))
+    let half: T = float_const(0.5);
+    let k: T = float_const((2.0 / std::f64::consts::PI).sqrt());
+    let c: T = float_const(GELU_COEFF);
+    el_unary(
+        |x| half * *x * (T::one() + (k * (*x + c * x.powi(3))).tanh()),
+        a,
+    )
+}
+
+pub(crate) fn el_silu<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    el_unary(|x| *x * sigmoid(*x), a)
+}
+
+pub(crate) fn el_leaky_relu<T: FloatDtype>(a: &[T], alpha: T) -> Vec<T> {
+    el_unary(|x| if *x > T::zero() { *x } else { alpha * *x }, a)
+}
+
+pub(crate) fn el_elu<T: FloatDtype>(a: &[T], alpha: T) -> Vec<T> {
+    el_unary(
+        |x| {
+            if *x > T::zero() {
+                *x
+            } else {
+                alpha * x.exp_m1()
+            }
+        },
+        a,
+    )
+}
+
+pub(crate) fn el_selu<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    let lambda: T = float_const(SELU_LAMBDA);
+    el_unary(|x| lambda * *x, &el_elu(a, float_const(SELU_ALPHA)))
+}
+
+pub(crate) fn el_softplus<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    el_unary(|x| softplus(*x), a)
 }
 
-pub(crate) fn scalar_mul<T: Dtype>(a: T, b: &[T]) -> Vec<T> {
+pub(crate) fn el_hardtanh<T: FloatDtype>(a: &[T], (min, max): (T, T)) -> Vec<T> {
+    el_unary(|x| x.max(min).min(max), a)
+}
+
+pub(crate) fn el_mish<T: FloatDtype>(a: &[T]) -> Vec<T> {
+    el_unary(|x| *x * softplus(*x).tanh(), a)
+}
+
+/// `ln(x)` clamped to at least -100, so that losses stay finite at probabilities of 0.
+fn clamped_ln<T: FloatDtype>(x: T) -> T {
+    x.ln().max(float_const(-100.0))
+}
+
+pub(crate) fn el_mse_loss<T: FloatDtype>(p: &[T], t: &[T]) -> Vec<T> {
+    el_bin(|(p, t)| (*p - *t) * (*p - *t), p, t)
+}
+
+pub(crate) fn el_l1_loss<T: FloatDtype>(p: &[T], t: &[T]) -> Vec<T> {
+    el_bin(|(p, t)| (*p - *t).abs(), p, t)
+}
+
+pub(crate) fn el_huber_loss<T: FloatDtype>(p: &[T], t: &[T], delta: T) -> Vec<T> {
+    // 0.5 * d^2 for |d| <= delta, else delta * (|d| - 0.5 * delta)
+    let half: T = float_const(0.5);
+    el_bin(
+        |(p, t)| {
+            let d = (*p - *t).abs();
+            if d <= delta {
+                half * d * d
+            } else {
+                delta * (d - half * delta)
+            }
+        },
+        p,
+        t,
+    )
+}
+
+pub(crate) fn el_smooth_l1_loss<T: FloatDtype>(p: &[T], t: &[T], beta: T) -> Vec<T> {
+    // huber(d, beta) / beta
+    el_unary(|x| *x / beta, &el_huber_loss(p, t, beta))
+}
+
+pub(crate) fn el_bce_loss<T: FloatDtype>(p: &[T], t: &[T]) -> Vec<T> {
+    el_bin(
+        |(p, t)| -(*t * clamped_ln(*p) + (T::one() - *t) * clamped_ln(T::one() - *p)),
+        p,
+        t,
+    )
+}
+
+pub(crate) fn el_bce_with_logits_loss<T: FloatDtype>(x: &[T], t: &[T]) -> Vec<T> {
+    // -(t * ln(s(x)) + (1 - t) * ln(1 - s(x))) = softplus(x) - t * x
+    el_bin(|(x, t)| softplus(*x) - *t * *x, x, t)
+}
+
+pub(crate) fn el_kl_div_loss<T: FloatDtype>(x: &[T], t: &[T]) -> Vec<T> {
+    // t * (ln(t) - x), where x holds log probabilities and 0 * ln(0) is taken as 0
+    el_bin(
+        |(x, t)| {
+            if *t > T::zero() {
+                *t * (t.ln() - *x)
+            } else {
+                T::zero()
+            }
+        },
+        x,
+        t,
+    )
+}
+
+pub(crate) fn el_hinge_loss<T: FloatDtype>(p: &[T], t: &[T], margin: T) -> Vec<T> {
+    el_bin(|(p, t)| (margin - *t * *p).max(T::zero()), p, t)
+}
+
+/// Cosine similarity of each pair of rows of `a` and `b`, which have rows of length `d`.
+pub(crate) fn cosine_similarity<T: FloatDtype>(a: &[T], b: &[T], d: usize) -> Vec<T> {
+    assert_eq!(a.len(), b.len());
+    a.chunks(d)
+        .zip(b.chunks(d))
+        .map(|(a, b)| {
+            let norms = (dot(a, a) * dot(b, b)).sqrt();
+            dot(a, b) / norms.max(float_const(1e-8))
+        })
+        .collect()
+}
+
+/// `-a[n, idx[n]]` for each row `n` of `a`, which has rows of length `c`.
+pub(crate) fn nll_loss<T: SignedDtype>(a: &[T], idx: &[usize], c: usize) -> Vec<T> {
+    assert_eq!(a.len(), idx.len() * c);
+    idx.iter().enumerate().map(|(n, i)| -a[n * c + i]).collect()
+}
+
+#[test]
+fn test_erf() {
+    // Reference values from scipy.special.erf
+    let xs: [f64; 6] = [0.0, 0.5, -1.0, 2.5, 3.5, -5.0];
+    let expected = [
+        0.0,
+        0.520_499_877_813_046_5,
+        -0.842_700_792_949_714_9,
+        0.999_593_047_982_555,
+        0.999_999_256_901_627_7,
+        -0.999_999_999_998_462_5,
+    ];
+    for (x, e) in xs.iter().zip(expected) {
+        assert!((erf(*x) - e).abs() < 1e-12, "erf({}) = {}", x, erf(*x));
+    }
+}
+
+pub(crate) fn scalar_mul<T: NumDtype>(a: T, b: &[T]) -> Vec<T> {
     el_unary(|x| a * *x, b)
 }
 
-pub(crate) fn scalar_add<T: Dtype>(a: T, b: &[T]) -> Vec<T> {
+pub(crate) fn scalar_add<T: NumDtype>(a: T, b: &[T]) -> Vec<T> {
     el_unary(|x| a + *x, b)
 }
+
+/// Strides for reading an array of `shape` as if it had `out_shape`. Broadcast dims get a stride
+/// of 0 so every output index along them maps back to the same source element.
+fn broadcast_strides(shape: &[usize], out_shape: &[usize]) -> Vec<usize> {
+    assert!(shape.len() <= out_shape.len());
+    let offset = out_shape.len() - shape.len();
+    let mut strides = vec![0; out_shape.len()];
+    let mut stride = 1;
+    for (i, d) in shape.iter().enumerate().rev() {
+        if *d != 1 {
+            assert_eq!(*d, out_shape[i + offset]);
+            strides[i + offset] = stride;
+        }
+        stride *= d;
+    }
+    strides
+}
+
+/// Map a flat index into an array of `out_shape` to a flat index using `strides`.
+fn strided_index(mut i: usize, out_shape: &[usize], strides: &[usize]) -> usize {
+    let mut idx = 0;
+    for (d, s) in out_shape.iter().zip(strides.iter()).rev() {
+        idx += (i % d) * s;
+        i /= d;
+    }
+    idx
+}
+
+/// Copy the strided view of `a` starting at `offset` into a new row-major array of `shape`.
+pub(crate) fn strided_copy<T: Dtype>(
+    a: &[T],
+    offset: usize,
+    shape: &[usize],
+    strides: &[usize],
+) -> Vec<T> {
+    let n: usize = shape.iter().product();
+    (0..n)
+        .map(|i| a[offset + strided_index(i, shape, strides)])
+        .collect()
+}
+
+/// Inverse of `strided_copy`: add each element of the row-major `src` into its position in `a`.
+/// Elements which share a position, e.g. along a stride 0 axis, accumulate.
+pub(crate) fn strided_add_assign<T: NumDtype>(
+    a: &mut [T],
+    src: &[T],
+    offset: usize,
+    shape: &[usize],
+    strides: &[usize],
+) {
+    assert_eq!(shape.iter().product::<usize>(), src.len());
+    for (i, v) in src.iter().enumerate() {
+        let d = &mut a[offset + strided_index(i, shape, strides)];
+        *d = *d + *v;
+    }
+}
+
+/// Write each element of the row-major `src` into its position in `a`.
+pub(crate) fn strided_assign<T: Dtype>(
+    a: &mut [T],
+    src: &[T],
+    offset: usize,
+    shape: &[usize],
+    strides: &[usize],
+) {
+    assert_eq!(shape.iter().product::<usize>(), src.len());
+    for (i, v) in src.iter().enumerate() {
+        a[offset + strided_index(i, shape, strides)] = *v;
+    }
+}
+
+#[test]
+fn test_strided_copy() {
+    // shape = (2, 3)
+    let a: Vec<i32> = (0..6).collect();
+    // Transposed view, shape = (3, 2)
+    assert_eq!(strided_copy(&a, 0, &[3, 2], &[1, 3]), [0, 3, 1, 4, 2, 5]);
+    // Column 1, shape = (2,)
+    assert_eq!(strided_copy(&a, 1, &[2], &[3]), [1, 4]);
+
+    let mut g = vec![0; 3];
+    // Row broadcast to shape = (2, 3)
+    strided_add_assign(&mut g, &[1, 2, 3, 4, 5, 6], 0, &[2, 3], &[0, 1]);
+    assert_eq!(g, [5, 7, 9]);
+    strided_assign(&mut g, &[0, 0], 1, &[2], &[1]);
+    assert_eq!(g, [5, 0, 0]);
+}
+
+/// Broadcast an array of `shape` to `out_shape`. Borrows when no broadcasting is needed.
+pub(crate) fn broadcast<'a, T: Dtype>(
+    a: &'a [T],
+    shape: &[usize],
+    out_shape: &[usize],
+) -> Cow<'a, [T]> {
+    if shape == out_shape {
+        return a.into();
+    }
+    let strides = broadcast_strides(shape, out_shape);
+    let n: usize = out_shape.iter().product();
+    (0..n)
+        .map(|i| a[strided_index(i, out_shape, &strides)])
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Inverse of `broadcast` for gradients: sums an array of `out_shape` over the broadcast dims
+/// so the result has `shape`.
+pub(crate) fn reduce_to_shape<T: NumDtype>(
+    a: Vec<T>,
+    out_shape: &[usize],
+    shape: &[usize],
+) -> Vec<T> {
+    if shape == out_shape {
+        return a;
+    }
+    let strides = broadcast_strides(shape, out_shape);
+    let mut data = vec![T::zero(); shape.iter().product()];
+    for (i, x) in a.into_iter().enumerate() {
+        let j = strided_index(i, out_shape, &strides);
+        data[j] = data[j] + x;
+    }
+    data
+}
+
+#[test]
+fn test_broadcast() {
+    // a = [0, 1, 2] shape = (3,) -> (2, 3)
+    let a = vec![0, 1, 2];
+    assert_eq!(*broadcast(&a, &[3], &[2, 3]), [0, 1, 2, 0, 1, 2]);
+    // a = [[0], [1]] shape = (2, 1) -> (2, 3)
+    let a = vec![0, 1];
+    assert_eq!(*broadcast(&a, &[2, 1], &[2, 3]), [0, 0, 0, 1, 1, 1]);
+    // a = [[0, 1, 2]] shape = (1, 3) -> (2, 2, 3)
+    let a = vec![0, 1, 2];
+    assert_eq!(
+        *broadcast(&a, &[1, 3], &[2, 2, 3]),
+        [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2]
+    );
+}
+
+#[test]
+fn test_reduce_to_shape() {
+    let a: Vec<i32> = (0..6).collect(); // shape = (2, 3)
+    assert_eq!(reduce_to_shape(a.clone(), &[2, 3], &[3]), [3, 5, 7]);
+    assert_eq!(reduce_to_shape(a.clone(), &[2, 3], &[2, 1]), [3, 12]);
+    assert_eq!(reduce_to_shape(a, &[2, 3], &[1, 1]), [15]);
+}
+
+/// Split `shape` around `axis` into (outer, axis, inner) sizes, so an element at index `k` along
+/// `axis` lives at `o * axis * inner + k * inner + i`.
+pub(crate) fn axis_sizes(shape: &[usize], axis: usize) -> (usize, usize, usize) {
+    assert!(axis < shape.len());
+    let outer = shape[..axis].iter().product();
+    let inner = shape[axis + 1..].iter().product();
+    (outer, shape[axis], inner)
+}
+
+/// Reduce an array of `shape` along `axis` by folding `f` over each slice.
+pub(crate) fn reduce_axis<T: NumDtype, F>(f: F, a: &[T], shape: &[usize], axis: usize) -> Vec<T>
+where
+    F: Fn(T, T) -> T,
+{
+    let (outer, n, inner) = axis_sizes(shape, axis);
+    assert_eq!(outer * n * inner, a.len());
+    let mut data = Vec::with_capacity(outer * inner);
+    for o in 0..outer {
+        for i in 0..inner {
+            let base = o * n * inner + i;
+            data.push((1..n).fold(a[base], |acc, k| f(acc, a[base + k * inner])));
+        }
+    }
+    data
+}
+
+/// For each slice along `axis`, find the index of the element selected by `pick`.
+/// `pick(x, cur)` should return true if `x` should replace the current selection,
+/// ties keep the first occurrence.
+pub(crate) fn arg_reduce_axis<T: NumDtype, F>(
+    pick: F,
+    a: &[T],
+    shape: &[usize],
+    axis: usize,
+) -> Vec<usize>
+where
+    F: Fn(&T, &T) -> bool,
+{
+    let (outer, n, inner) = axis_sizes(shape, axis);
+    assert_eq!(outer * n * inner, a.len());
+    let mut data = Vec::with_capacity(outer * inner);
+    for o in 0..outer {
+        for i in 0..inner {
+            let base = o * n * inner + i;
+            data.push((1..n).fold(0, |cur, k| {
+                if pick(&a[base + k * inner], &a[base + cur * inner]) {
+                    k
+                } else {
+                    cur
+                }
+            }));
+        }
+    }
+    data
+}
+
+pub(crate) fn sum_axis<T: NumDtype>(a: &[T], shape: &[usize], axis: usize) -> Vec<T> {
+    reduce_axis(|x, y| x + y, a, shape, axis)
+}
+
+pub(crate) fn prod_axis<T: NumDtype>(a: &[T], shape: &[usize], axis: usize) -> Vec<T> {
+    reduce_axis(|x, y| x * y, a, shape, axis)
+}
+
+pub(crate) fn mean_axis<T: NumDtype>(a: &[T], shape: &[usize], axis: usize) -> Vec<T> {
+    let n = T::from_usize(shape[axis]).expect("Failed to cast axis size to dtype");
+    el_unary(|x| *x / n, &sum_axis(a, shape, axis))
+}
+
+pub(crate) fn max_axis<T: NumDtype>(a: &[T], shape: &[usize], axis: usize) -> Vec<T> {
+    reduce_axis(|x, y| if x >= y { x } else { y }, a, shape, axis)
+}
+
+pub(crate) fn min_axis<T: NumDtype>(a: &[T], shape: &[usize], axis: usize) -> Vec<T> {
+    reduce_axis(|x, y| if x <= y { x } else { y }, a, shape, axis)
+}
+
+/// Broadcast the result of reducing an array of `shape` along `axis` back to `shape`.
+pub(crate) fn unreduce_axis<T: NumDtype>(r: &[T], shape: &[usize], axis: usize) -> Vec<T> {
+    let mut keepdim_shape = shape.to_vec();
+    keepdim_shape[axis] = 1;
+    broadcast(r, &keepdim_shape, shape).into_owned()
+}
+
+/// Max of each slice along `axis`, with infinite maxes replaced by 0 so subtracting it from the
+/// slice never gives NaN.
+fn finite_max_axis<T: FloatDtype>(a: &[T], shape: &[usize], axis: usize) -> Vec<T> {
+    el_unary(
+        |m: &T| if m.is_finite() { *m } else { T::zero() },
+        &max_axis(a, shape, axis),
+    )
+}
+
+/// `ln(sum(exp(a), axis))`, shifted by the max of each slice so that `exp` can't overflow.
+pub(crate) fn logsumexp_axis<T: FloatDtype>(a: &[T], shape: &[usize], axis: usize) -> Vec<T> {
+    let m = finite_max_axis(a, shape, axis);
+    let e = el_exp(&el_sub(a, &unreduce_axis(&m, shape, axis)));
+    el_add(&m, &el_ln(&sum_axis(&e, shape, axis)))
+}
+
+pub(crate) fn softmax_axis<T: FloatDtype>(a: &[T], shape: &[usize], axis: usize) -> Vec<T> {
+    let m = finite_max_axis(a, shape, axis);
+    let e = el_exp(&el_sub(a, &unreduce_axis(&m, shape, axis)));
+    let s = sum_axis(&e, shape, axis);
+    el_div(&e, &unreduce_axis(&s, shape, axis))
+}
+
+pub(crate) fn log_softmax_axis<T: FloatDtype>(a: &[T], shape: &[usize], axis: usize) -> Vec<T> {
+    // Subtract the max before the log of the sum, rather than the whole logsumexp at once, so
+    // large logits don't lose precision
+    let m = finite_max_axis(a, shape, axis);
+    let shifted = el_sub(a, &unreduce_axis(&m, shape, axis));
+    let s = el_ln(&sum_axis(&el_exp(&shifted), shape, axis));
+    el_sub(&shifted, &unreduce_axis(&s, shape, axis))
+}
+
+#[test]
+fn test_softmax_axis() {
+    let a = [1.0, 2.0, 3.0, 1000.0, 1000.0, f64::NEG_INFINITY];
+    let s = softmax_axis(&a, &[2, 3], 1);
+    let e = [1f64.exp(), 2f64.exp(), 3f64.exp()];
+    let total: f64 = e.iter().sum();
+    let expected = [e[0] / total, e[1] / total, e[2] / total, 0.5, 0.5, 0.0];
+    for (x, y) in s.iter().zip(expected) {
+        assert!((x - y).abs() < 1e-12);
+    }
+    assert_eq!(logsumexp_axis(&a, &[2, 3], 1)[1], 1000.0 + 2f64.ln());
+    assert_eq!(
+        logsumexp_axis(&[f64::NEG_INFINITY; 2], &[2], 0),
+        [f64::NEG_INFINITY]
+    );
+}
+
+/// Build a 0/1 array of `shape` with a 1 at index `args[j]` along `axis` for each slice `j`.
+pub(crate) fn axis_mask<T: NumDtype>(args: &[usize], shape: &[usize], axis: usize) -> Vec<T> {
+    let (outer, n, inner) = axis_sizes(shape, axis);
+    assert_eq!(outer * inner, args.len());
+    let mut data = vec![T::zero(); outer * n * inner];
+    for o in 0..outer {
+        for i in 0..inner {
+            data[o * n * inner + args[o * inner + i] * inner + i] = T::one();
+        }
+    }
+    data
+}
+
+/// Concatenate arrays along an axis. Each part is viewed as `outer` equal chunks (everything
+/// before the axis) and the output interleaves the chunks of each part in order.
+pub(crate) fn concat<T: Dtype>(parts: &[&[T]], outer: usize) -> Vec<T> {
+    let mut data = Vec::with_capacity(parts.iter().map(|p| p.len()).sum());
+    for o in 0..outer {
+        for p in parts {
+            let n = p.len() / outer;
+            data.extend_from_slice(&p[o * n..(o + 1) * n]);
+        }
+    }
+    data
+}
+
+/// Inverse of `concat`: split `a` into parts with total lengths `lens`.
+pub(crate) fn split_parts<T: Dtype>(a: &[T], outer: usize, lens: &[usize]) -> Vec<Vec<T>> {
+    assert_eq!(lens.iter().sum::<usize>(), a.len());
+    let mut parts: Vec<_> = lens.iter().map(|n| Vec::with_capacity(*n)).collect();
+    let mut rest = a;
+    for _ in 0..outer {
+        for (p, n) in parts.iter_mut().zip(lens) {
+            let (chunk, r) = rest.split_at(n / outer);
+            p.extend_from_slice(chunk);
+            rest = r;
+        }
+    }
+    parts
+}
+
+#[test]
+fn test_reduce_axis() {
+    let a: Vec<i32> = (0..24).collect(); // shape = (2, 3, 4)
+    let shape = [2, 3, 4];
+    assert_eq!(
+        sum_axis(&a, &shape, 0),
+        [12, 14, 16, 18, 20, 22, 24, 26, 28, 30, 32, 34]
+    );
+    assert_eq!(sum_axis(&a, &shape, 1), [12, 15, 18, 21, 48, 51, 54, 57]);
+    assert_eq!(sum_axis(&a, &shape, 2), [6, 22, 38, 54, 70, 86]);
+    assert_eq!(max_axis(&a, &shape, 1), [8, 9, 10, 11, 20, 21, 22, 23]);
+    assert_eq!(arg_reduce_axis(|x, m| x < m, &a, &shape, 2), [0; 6]);
+}
+
+/// Gather along `axis`, so that `out[.., j, ..] = a[.., idx[.., j, ..], ..]`. `idx` has the shape
+/// of `a` except along `axis`, where it has size `k`.
+pub(crate) fn gather<T: Dtype>(
+    a: &[T],
+    shape: &[usize],
+    idx: &[usize],
+    k: usize,
+    axis: usize,
+) -> Vec<T> {
+    let (outer, n, inner) = axis_sizes(shape, axis);
+    assert_eq!(outer * n * inner, a.len());
+    assert_eq!(outer * k * inner, idx.len());
+    idx.iter()
+        .enumerate()
+        .map(|(pos, &j)| a[(pos / (k * inner)) * n * inner + j * inner + pos % inner])
+        .collect()
+}
+
+/// Inverse of `gather`: add each element of `src` into a zero array of `shape` at the position
+/// along `axis` given by `idx`.
+pub(crate) fn scatter_add<T: NumDtype>(
+    src: &[T],
+    shape: &[usize],
+    idx: &[usize],
+    k: usize,
+    axis: usize,
+) -> Vec<T> {
+    let (outer, n, inner) = axis_sizes(shape, axis);
+    assert_eq!(outer * k * inner, idx.len());
+    assert_eq!(src.len(), idx.len());
+    let mut data = vec![T::zero(); outer * n * inner];
+    for (pos, (&j, &v)) in idx.iter().zip(src).enumerate() {
+        let d = &mut data[(pos / (k * inner)) * n * inner + j * inner + pos % inner];
+        *d = *d + v;
+    }
+    data
+}
+
+/// Select the slices `idx` along `axis`, in order.
+pub(crate) fn index_select<T: Dtype>(
+    a: &[T],
+    shape: &[usize],
+    idx: &[usize],
+    axis: usize,
+) -> Vec<T> {
+    let (outer, n, inner) = axis_sizes(shape, axis);
+    assert_eq!(outer * n * inner, a.len());
+    let mut data = Vec::with_capacity(outer * idx.len() * inner);
+    for c in a.chunks(n * inner) {
+        for &j in idx {
+            data.extend_from_slice(&c[j * inner..(j + 1) * inner]);
+        }
+    }
+    data
+}
+
+/// Inverse of `index_select`: add slice `i` of `a` into slice `idx[i]` of a zero array of `shape`.
+pub(crate) fn index_add<T: NumDtype>(
+    a: &[T],
+    shape: &[usize],
+    idx: &[usize],
+    axis: usize,
+) -> Vec<T> {
+    let (outer, n, inner) = axis_sizes(shape, axis);
+    assert_eq!(outer * idx.len() * inner, a.len());
+    let mut data = vec![T::zero(); outer * n * inner];
+    for (dst, src) in data.chunks_mut(n * inner).zip(a.chunks(idx.len() * inner)) {
+        for (&j, s) in idx.iter().zip(src.chunks(inner)) {
+            for (d, v) in dst[j * inner..(j + 1) * inner].iter_mut().zip(s) {
+                *d = *d + *v;
+            }
+        }
+    }
+    data
+}
+
+/// Sizes and hyperparameters of a 2d convolution of `(N, C, H, W)` inputs with
+/// `(O, C / groups, KH, KW)` weights. Pairs are (height, width). A 1d convolution is one with a
+/// height of 1.
+#[derive(Debug, Clone)]
+pub(crate) struct ConvGeometry {
+    pub(crate) batch: usize,
+    pub(crate) in_channels: usize,
+    pub(crate) out_channels: usize,
+    pub(crate) groups: usize,
+    pub(crate) in_size: (usize, usize),
+    pub(crate) kernel: (usize, usize),
+    pub(crate) out_size: (usize, usize),
+    pub(crate) stride: (usize, usize),
+    pub(crate) padding: (usize, usize),
+    pub(crate) dilation: (usize, usize),
+}
+
+impl ConvGeometry {
+    /// Rows of the im2col matrix used by each group, i.e. `C / groups * KH * KW`.
+    pub(crate) fn group_rows(&self) -> usize {
+        self.in_channels / self.groups * self.kernel.0 * self.kernel.1
+    }
+
+    pub(crate) fn out_pixels(&self) -> usize {
+        self.out_size.0 * self.out_size.1
+    }
+
+    /// Position in the unpadded input read by output `o` and kernel offset `k` along one axis.
+    fn input_pos(o: usize, k: usize, axis: (usize, usize, usize, usize)) -> Option<usize> {
+        let (stride, dilation, padding, n) = axis;
+        (o * stride + k * dilation)
+            .checked_sub(padding)
+            .filter(|i| *i < n)
+    }
+
+    /// For each `(row, pixel)` of the im2col matrix of one image, the index into the image it
+    /// reads from, or `None` where it reads padding.
+    fn im2col_indices(&self) -> impl Iterator<Item = (usize, Option<usize>)> + '_ {
+        let (h, w) = self.in_size;
+        let (kh, kw) = self.kernel;
+        let (oh, ow) = self.out_size;
+        let row_axis = (self.stride.0, self.dilation.0, self.padding.0, h);
+        let col_axis = (self.stride.1, self.dilation.1, self.padding.1, w);
+        (0..self.in_channels * kh * kw).flat_map(move |row| {
+            let (c, ki, kj) = (row / (kh * kw), row / kw % kh, row % kw);
+            (0..oh * ow).map(move |p| {
+                let i = Self::input_pos(p / ow, ki, row_axis);
+                let j = Self::input_pos(p % ow, kj, col_axis);
+                (
+                    row * oh * ow + p,
+                    i.zip(j).map(|(i, j)| (c * h + i) * w + j),
+                )
+            })
+        })
+    }
+}
+
+/// Unfold one `(C, H, W)` image into a `(C * KH * KW, OH * OW)` matrix, whose column `p` holds
+/// the input patch that output pixel `p` sees.
+pub(crate) fn im2col<T: NumDtype>(img: &[T], g: &ConvGeometry) -> Vec<T> {
+    let mut cols = vec![T::zero(); g.in_channels * g.kernel.0 * g.kernel.1 * g.out_pixels()];
+    for (k, i) in g.im2col_indices() {
+        if let Some(i) = i {
+            cols[k] = img[i];
+        }
+    }
+    cols
+}
+
+/// Inverse of `im2col` for gradients, summing the entries of overlapping patches.
+pub(crate) fn col2im<T: NumDtype>(cols: &[T], g: &ConvGeometry) -> Vec<T> {
+    let mut img = vec![T::zero(); g.in_channels * g.in_size.0 * g.in_size.1];
+    for (k, i) in g.im2col_indices() {
+        if let Some(i) = i {
+            img[i] = img[i] + cols[k];
+        }
+    }
+    img
+}
+
+/// Grouped 2d convolution, as a matmul of each group of weights with the matching rows of the
+/// im2col matrix of each image.
+pub(crate) fn conv2d<T: NumDtype>(x: &[T], w: &[T], g: &ConvGeometry) -> Vec<T> {
+    let (k, p) = (g.group_rows(), g.out_pixels());
+    let og = g.out_channels / g.groups;
+    let img_len = g.in_channels * g.in_size.0 * g.in_size.1;
+    assert_eq!(x.len(), g.batch * img_len);
+    assert_eq!(w.len(), g.out_channels * k);
+    let mut data = Vec::with_capacity(g.batch * g.out_channels * p);
+    for img in x.chunks(img_len) {
+        let cols = im2col(img, g);
+        for (w_g, cols_g) in w.chunks(og * k).zip(cols.chunks(k * p)) {
+            data.extend(matmul(w_g, cols_g, og, k, p));
+        }
+    }
+    data
+}
+
+/// For each channel and output pixel of a pooling op, the indices of the input elements in its
+/// window. Windows never include padding. The geometry has one channel per `(N, C)` pair and a
+/// batch of 1.
+pub(crate) fn pool_windows(g: &ConvGeometry) -> Vec<Vec<usize>> {
+    let (h, w) = g.in_size;
+    let (kh, kw) = g.kernel;
+    let row_axis = (g.stride.0, g.dilation.0, g.padding.0, h);
+    let col_axis = (g.stride.1, g.dilation.1, g.padding.1, w);
+    let p = g.out_pixels();
+    (0..g.in_channels * p)
+        .map(|cp| {
+            let (c, i, j) = (cp / p, cp % p / g.out_size.1, cp % g.out_size.1);
+            let rows = (0..kh).filter_map(|ki| ConvGeometry::input_pos(i, ki, row_axis));
+            rows.flat_map(|y| {
+                (0..kw)
+                    .filter_map(move |kj| ConvGeometry::input_pos(j, kj, col_axis))
+                    .map(move |x| (c * h + y) * w + x)
+            })
+            .collect()
+        })
+        .collect()
+}
+
+/// Pooling windows which split each `in_size` channel into `out_size` near-equal, possibly
+/// overlapping, regions. Output `i` along an axis of size `n` covers
+/// `floor(i * n / out)..ceil((i + 1) * n / out)`.
+pub(crate) fn adaptive_pool_windows(
+    channels: usize,
+    in_size: (usize, usize),
+    out_size: (usize, usize),
+) -> Vec<Vec<usize>> {
+    let range = |i: usize, n: usize, out: usize| (i * n / out)..((i + 1) * n).div_ceil(out);
+    let (h, w) = in_size;
+    let (oh, ow) = out_size;
+    let mut windows = Vec::with_capacity(channels * oh * ow);
+    for c in 0..channels {
+        for i in 0..oh {
+            for j in 0..ow {
+                windows.push(
+                    range(i, h, oh)
+                        .flat_map(|y| range(j, w, ow).map(move |x| (c * h + y) * w + x))
+                        .collect(),
+                );
+            }
+        }
+    }
+    windows
+}
+
+/// Index of the (first) max of `a` in each window.
+pub(crate) fn max_pool_args<T: NumDtype>(a: &[T], windows: &[Vec<usize>]) -> Vec<usize> {
+    windows
+        .iter()
+        .map(|win| {
+            win.iter()
+                .copied()
+                .reduce(|m, i| if a[i] > a[m] { i } else { m })
+                .expect("Pooling window is empty")
+        })
+        .collect()
+}
+
+pub(crate) fn max_pool<T: NumDtype>(a: &[T], windows: &[Vec<usize>]) -> Vec<T> {
+    max_pool_args(a, windows).iter().map(|i| a[*i]).collect()
+}
+
+/// Sum of each window divided by `divisor`, or by the size of the window if `divisor` is `None`.
+pub(crate) fn avg_pool<T: NumDtype>(
+    a: &[T],
+    windows: &[Vec<usize>],
+    divisor: Option<usize>,
+) -> Vec<T> {
+    windows
+        .iter()
+        .map(|win| {
+            let n = T::from_usize(divisor.unwrap_or(win.len()))
+                .expect("Failed to cast window size to dtype");
+            win.iter().fold(T::zero(), |s, i| s + a[*i]) / n
+        })
+        .collect()
+}
+
+#[test]
+fn test_pool_windows() {
+    let windows = adaptive_pool_windows(1, (3, 5), (2, 2));
+    assert_eq!(windows[0], [0, 1, 2, 5, 6, 7]);
+    // Rows 1..3 overlap the first window along the odd sized axis
+    assert_eq!(windows[3], [7, 8, 9, 12, 13, 14]);
+
+    let a = [1, 5, 2, 7, 3, 3, 0, 9, 4];
+    let windows = [vec![0, 1, 3, 4], vec![4, 5, 8], vec![2]];
+    assert_eq!(max_pool_args(&a, &windows), [3, 8, 2]);
+    assert_eq!(avg_pool(&a, &windows, Some(4)), [4, 2, 0]);
+    assert_eq!(avg_pool(&a, &windows, None), [4, 3, 2]);
+}
+
+/// Which group of elements each element of a normalisation op is normalised with, and which
+/// channel of the affine params scales it. Layer and RMS norm have a group per row and a
+/// channel per column, group norm a group per set of channels in each sample, and batch norm a
+/// group per channel across the batch. All groups have the same size.
+///
+/// Both are periodic in the row-major index `i`: the group is `i / group_stride % n_groups`
+/// and the channel is `i / channel_stride % n_channels`.
+#[derive(Debug, Clone)]
+pub(crate) struct NormLayout {
+    pub(crate) len: usize,
+    pub(crate) n_groups: usize,
+    pub(crate) group_stride: usize,
+    pub(crate) n_channels: usize,
+    pub(crate) channel_stride: usize,
+    /// Whether the mean is subtracted. RMS norm only divides by the root mean square.
+    pub(crate) centered: bool,
+}
+
+impl NormLayout {
+    /// Normalise over the last axis, of size `d`.
+    pub(crate) fn last_axis(len: usize, d: usize, centered: bool) -> Self {
+        Self {
+            len,
+            n_groups: len / d,
+            group_stride: d,
+            n_channels: d,
+            channel_stride: 1,
+            centered,
+        }
+    }
+
+    /// Normalise `(N, C, ...)` over `groups` groups of channels of each sample.
+    pub(crate) fn channel_groups(shape: &[usize], groups: usize) -> Self {
+        let (n, c) = (shape[0], shape[1]);
+        let spatial: usize = shape[2..].iter().product();
+        Self {
+            len: n * c * spatial,
+            n_groups: n * groups,
+            group_stride: c / groups * spatial,
+            n_channels: c,
+            channel_stride: spatial,
+            centered: true,
+        }
+    }
+
+    /// Normalise `(N, C, ...)` over each channel, across the batch and any other axes.
+    pub(crate) fn per_channel(shape: &[usize]) -> Self {
+        let (n, c) = (shape[0], shape[1]);
+        let spatial: usize = shape[2..].iter().product();
+        Self {
+            len: n * c * spatial,
+            n_groups: c,
+            group_stride: spatial,
+            n_channels: c,
+            channel_stride: spatial,
+            centered: true,
+        }
+    }
+
+    pub(crate) fn group(&self, i: usize) -> usize {
+        i / self.group_stride % self.n_groups
+    }
+
+    pub(crate) fn channel(&self, i: usize) -> usize {
+        i / self.channel_stride % self.n_channels
+    }
+
+    pub(crate) fn group_size(&self) -> usize {
+        self.len / self.n_groups
+    }
+
+    /// Mean of each group of `a`.
+    pub(crate) fn group_mean<T: FloatDtype>(&self, a: &[T]) -> Vec<T> {
+        let mut sums = vec![T::zero(); self.n_groups];
+        for (i, x) in a.iter().enumerate() {
+            let g = self.group(i);
+            sums[g] = sums[g] + *x;
+        }
+        let n = T::from_usize(self.group_size()).expect("Failed to cast group size to dtype");
+        sums.into_iter().map(|s| s / n).collect()
+    }
+}
+
+/// Mean and (biased) variance of each group. The variance is taken of the deviations from the
+/// mean, rather than as `E[x^2] - E[x]^2`, which loses precision for large means. For uncentered
+/// layouts the mean is 0, so the variance is the mean square.
+pub(crate) fn norm_stats<T: FloatDtype>(a: &[T], l: &NormLayout) -> (Vec<T>, Vec<T>) {
+    let mean = if l.centered {
+        l.group_mean(a)
+    } else {
+        vec![T::zero(); l.n_groups]
+    };
+    let sq: Vec<T> = a
+        .iter()
+        .enumerate()
+        .map(|(i, x)| (*x - mean[l.group(i)]) * (*x - mean[l.group(i)]))
+        .collect();
+    (mean, l.group_mean(&sq))
+}
+
+/// `(a - mean) / sqrt(var + eps)`, using the stats of the group of each element.
+pub(crate) fn normalize<T: FloatDtype>(
+    a: &[T],
+    l: &NormLayout,
+    mean: &[T],
+    var: &[T],
+    eps: T,
+) -> Vec<T> {
+    let rstd: Vec<T> = var.iter().map(|v| (*v + eps).sqrt().recip()).collect();
+    a.iter()
+        .enumerate()
+        .map(|(i, x)| (*x - mean[l.group(i)]) * rstd[l.group(i)])
+        .collect()
+}
+
+/// Scale and shift each element by the params of its channel.
+pub(crate) fn norm_affine<T: FloatDtype>(
+    x_hat: &[T],
+    l: &NormLayout,
+    weight: Option<&[T]>,
+    bias: Option<&[T]>,
+) -> Vec<T> {
+    x_hat
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let c = l.channel(i);
+            let x = weight.map_or(*x, |w| *x * w[c]);
+            bias.map_or(x, |b| x + b[c])
+        })
+        .collect()
+}
+
+#[test]
+fn test_norm_layout() {
+    let groups = |l: &NormLayout| (0..l.len).map(|i| l.group(i)).collect::<Vec<_>>();
+    let l = NormLayout::per_channel(&[2, 3, 2]);
+    assert_eq!(groups(&l), [0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2]);
+    let l = NormLayout::channel_groups(&[2, 4, 1], 2);
+    assert_eq!(groups(&l), [0, 0, 1, 1, 2, 2, 3, 3]);
+    let channels: Vec<_> = (0..l.len).map(|i| l.channel(i)).collect();
+    assert_eq!(channels, [0, 1, 2, 3, 0, 1, 2, 3]);
+
+    let a = [1e9 + 1.0, 1e9 + 3.0, -1.0, 1.0];
+    let (mean, var) = norm_stats(&a, &NormLayout::last_axis(4, 2, true));
+    assert_eq!(mean, [1e9 + 2.0, 0.0]);
+    assert_eq!(var, [1.0, 1.0]);
+    let (mean, var) = norm_stats(&[3.0, 4.0], &NormLayout::last_axis(2, 2, false));
+    assert_eq!((mean, var), (vec![0.0], vec![12.5]));
+}
+
+#[test]
+fn test_im2col() {
+    // 1 channel 3x3 image, 2x2 kernel, padding 1 and stride 2 gives a 2x2 output
+    let g = ConvGeometry {
+        batch: 1,
+        in_channels: 1,
+        out_channels: 1,
+        groups: 1,
+        in_size: (3, 3),
+        kernel: (2, 2),
+        out_size: (2, 2),
+        stride: (2, 2),
+        padding: (1, 1),
+        dilation: (1, 1),
+    };
+    let img: Vec<i32> = (1..10).collect();
+    let cols = im2col(&img, &g);
+    assert_eq!(cols, [0, 0, 0, 5, 0, 0, 4, 6, 0, 2, 0, 8, 1, 3, 7, 9]);
+    // Each input pixel is read at most once with this stride
+    let ones = col2im(&[1; 16], &g);
+    assert_eq!(ones, [1, 1, 1, 1, 1, 1, 1, 1, 1]);
+    assert_eq!(conv2d(&img, &[1, 1, 1, 1], &g), [1, 5, 11, 28]);
+}
+
+/// Pad an array of `shape` along `axis`. Output position `p` along the axis copies source
+/// position `idx[p]`, or is `fill` where that is `None`.
+pub(crate) fn pad<T: NumDtype>(
+    a: &[T],
+    shape: &[usize],
+    axis: usize,
+    idx: &[Option<usize>],
+    fill: T,
+) -> Vec<T> {
+    let (outer, n, inner) = axis_sizes(shape, axis);
+    assert_eq!(outer * n * inner, a.len());
+    let mut data = Vec::with_capacity(outer * idx.len() * inner);
+    for c in a.chunks(n * inner) {
+        for j in idx {
+            match j {
+                Some(j) => data.extend_from_slice(&c[j * inner..(j + 1) * inner]),
+                None => data.extend(std::iter::repeat_n(fill, inner)),
+            }
+        }
+    }
+    data
+}
+
+/// Inverse of `pad` for gradients: add each padded position back into the source position it
+/// was copied from, in a zero array of `shape`.
+pub(crate) fn unpad<T: NumDtype>(
+    a: &[T],
+    shape: &[usize],
+    axis: usize,
+    idx: &[Option<usize>],
+) -> Vec<T> {
+    let (outer, n, inner) = axis_sizes(shape, axis);
+    assert_eq!(outer * idx.len() * inner, a.len());
+    let mut data = vec![T::zero(); outer * n * inner];
+    for (dst, src) in data.chunks_mut(n * inner).zip(a.chunks(idx.len() * inner)) {
+        for (j, s) in idx.iter().zip(src.chunks(inner)) {
+            if let Some(j) = j {
+                for (d, v) in dst[j * inner..(j + 1) * inner].iter_mut().zip(s) {
+                    *d = *d + *v;
+                }
+            }
+        }
+    }
+    data
+}
+
+#[test]
+fn test_pad() {
+    let a: Vec<i32> = (1..7).collect(); // shape = (2, 3)
+    let idx = [None, Some(0), Some(1), Some(2), Some(2)];
+    let p = pad(&a, &[2, 3], 1, &idx, 0);
+    assert_eq!(p, [0, 1, 2, 3, 3, 0, 4, 5, 6, 6]);
+    assert_eq!(unpad(&p, &[2, 3], 1, &idx), [1, 2, 6, 4, 5, 12]);
+    assert_eq!(
+        pad(&a, &[2, 3], 0, &[Some(1), None], -1),
+        [4, 5, 6, -1, -1, -1]
+    );
+}
+
+#[test]
+fn test_gather() {
+    let a: Vec<i32> = (0..6).collect(); // shape = (2, 3)
+    let idx = [2, 0, 1, 1]; // shape = (2, 2)
+    let g = gather(&a, &[2, 3], &idx, 2, 1);
+    assert_eq!(g, [2, 0, 4, 4]);
+    assert_eq!(scatter_add(&g, &[2, 3], &idx, 2, 1), [0, 0, 2, 0, 8, 0]);
+    assert_eq!(gather(&a, &[2, 3], &[1, 0, 1], 1, 0), [3, 1, 5]);
+}
+
+#[test]
+fn test_index_select() {
+    let a: Vec<i32> = (0..6).collect(); // shape = (3, 2)
+    let s = index_select(&a, &[3, 2], &[2, 0, 2], 0);
+    assert_eq!(s, [4, 5, 0, 1, 4, 5]);
+    assert_eq!(index_add(&s, &[3, 2], &[2, 0, 2], 0), [0, 1, 0, 0, 8, 10]);
+    assert_eq!(index_select(&a, &[3, 2], &[1], 1), [1, 3, 5]);
+}
+
+#[test]
+fn test_concat() {
+    let a: Vec<i32> = (0..4).collect(); // shape = (2, 2)
+    let b: Vec<i32> = (4..10).collect(); // shape = (2, 3)
+    let c = concat(&[&a, &b], 2); // axis 1, shape = (2, 5)
+    assert_eq!(c, [0, 1, 4, 5, 6, 2, 3, 7, 8, 9]);
+    assert_eq!(split_parts(&c, 2, &[4, 6]), [a, b]);
+}
@@ -1,10 +1,10 @@
-use crate::dtype::Dtype;
+use crate::dtype::NumDtype;
 use crate::ops::vec::{el_sub, scalar_mul};
 use num::FromPrimitive;
 use std::any::type_name;
 
 pub trait Optimizer {
-    fn compute<T: Dtype>(&mut self, tid: usize, t_value: &[T], t_grad: &[T]) -> Vec<T>;
+    fn compute<T: NumDtype>(&mut self, tid: usize, t_value: &[T], t_grad: &[T]) -> Vec<T>;
 }
 
 pub struct GradientDescent {
@@ -12,9 +12,9 @@ pub struct GradientDescent {
 }
 
 impl Optimizer for GradientDescent {
-    fn compute<T: Dtype>(&mut self, _tid: usize, t_value: &[T], t_grad: &[T]) -> Vec<T> {
+    fn compute<T: NumDtype>(&mut self, _tid: usize, t_value: &[T], t_grad: &[T]) -> Vec<T> {
         let lr: T = FromPrimitive::from_f32(self.lr)
-            .expect(&format!("Failed to cast lr to {}", type_name::<T>()));
+            .unwrap_or_else(|| panic!("Failed to cast lr to {}", type_name::<T>()));
         el_sub(t_value, &scalar_mul(lr, t_grad))
     }
 }
@@ -1,7 +1,8 @@
-use rand::distributions::Distribution;
+use rand::distributions::{Bernoulli, Distribution};
 use rand::Rng;
 use statrs::distribution::Normal;
 
+use crate::dtype::FloatDtype;
 use crate::{shape::Shape, Tensor};
 
 pub fn randn<S: Shape, R: Rng>(mu: f64, sigma: f64, rng: R) -> Tensor<f64, S> {
@@ -9,3 +10,28 @@ pub fn randn<S: Shape, R: Rng>(mu: f64, sigma: f64, rng: R) -> Tensor<f64, S> {
     let v = normal.sample_iter(rng).take(S::NUM_ELS).collect();
     unsafe { Tensor::<f64, S>::from_vec_unchecked(v) }
 }
+
+/// Each element is 1 with probability `p` and 0 otherwise. Panics unless `0 <= p <= 1`.
+pub fn bernoulli<T: FloatDtype, S: Shape, R: Rng>(p: f64, rng: R) -> Tensor<T, S> {
+    let v = bernoulli_vec(S::NUM_ELS, p, rng)
+        .into_iter()
+        .map(|b| if b { T::one() } else { T::zero() })
+        .collect();
+    unsafe { Tensor::<T, S>::from_vec_unchecked(v) }
+}
+
+fn bernoulli_vec<R: Rng>(len: usize, p: f64, rng: R) -> Vec<bool> {
+    let bernoulli =
+        Bernoulli::new(p).unwrap_or_else(|_| panic!("Probability {} is not between 0 and 1", p));
+    bernoulli.sample_iter(rng).take(len).collect()
+}
+
+/// A mask which zeroes each element with probability `p` and scales the rest by `1 / (1 - p)`,
+/// so that the mask has an expected value of 1.
+pub(crate) fn dropout_mask<T: FloatDtype, R: Rng>(len: usize, p: f64, rng: R) -> Vec<T> {
+    let scale = T::from_f64(1.0 / (1.0 - p)).expect("Failed to cast dropout scale to dtype");
+    bernoulli_vec(len, 1.0 - p, rng)
+        .into_iter()
+        .map(|keep| if keep { scale } else { T::zero() })
+        .collect()
+}
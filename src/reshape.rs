@@ -1,23 +1,24 @@
 use std::{marker::PhantomData, rc::Rc};
 
 use crate::{
-    dtype::Dtype,
+    dtype::NumDtype,
     ops::Op,
-    shape::{HasNEls, Shape, D1, D2, D3, I},
-    tensor::{Tensor, TensorBox},
+    shape::{BroadcastTo, Dims, HasNEls, InsertAxis, Shape, Squeeze, D1, D2, D3, D4, I},
+    tensor::{Tensor, TensorBox, TensorTrait},
+    tensor_data::{Layout, TensorData},
 };
 
-pub trait Flattens<T: Dtype, const A: usize> {
+pub trait Flattens<T: NumDtype, const A: usize> {
     fn flatten(self) -> Tensor<T, (I<A>,)>;
 }
 
 #[derive(Debug)]
-pub struct FlattenStruct<T: Dtype, const A: usize, TensorType: Flattens<T, A>> {
+pub struct FlattenStruct<T: NumDtype, const A: usize, TensorType: Flattens<T, A>> {
     data: TensorType,
     _dtype: PhantomData<T>,
 }
 
-impl<T: Dtype, const A: usize, TensorType: Flattens<T, A>> FlattenStruct<T, A, TensorType> {
+impl<T: NumDtype, const A: usize, TensorType: Flattens<T, A>> FlattenStruct<T, A, TensorType> {
     pub fn new(data: TensorType) -> Self {
         Self {
             data,
@@ -26,7 +27,7 @@ impl<T: Dtype, const A: usize, TensorType: Flattens<T, A>> FlattenStruct<T, A, T
     }
 }
 
-impl<const A: usize, T: Dtype, S: Shape> Op for FlattenStruct<T, A, Tensor<T, S>>
+impl<const A: usize, T: NumDtype, S: Shape> Op for FlattenStruct<T, A, Tensor<T, S>>
 where
     Tensor<T, S>: Flattens<T, A>,
 {
@@ -44,32 +45,32 @@ where
         }
     }
 
-    fn operands(&self) -> Vec<TensorBox> {
+    fn operands(&self) -> Vec<TensorBox<'_>> {
         vec![TensorBox::new(self.data.id, &self.data)]
     }
 }
 
-impl<const A: usize, T: Dtype, S: Shape> Flattens<T, A> for Tensor<T, S>
+impl<const A: usize, T: NumDtype, S: Shape> Flattens<T, A> for Tensor<T, S>
 where
     S: HasNEls<A>,
 {
     fn flatten(self) -> Tensor<T, (I<A>,)> {
-        FlattenStruct::new(self).forward()
+        FlattenStruct::new(self.contiguous()).forward()
     }
 }
 
-pub trait Reshapes<T: Dtype, S: Shape> {
+pub trait Reshapes<T: NumDtype, S: Shape> {
     fn reshape(self) -> Tensor<T, S>;
 }
 
 #[derive(Debug)]
-pub struct ReshapeStruct<T: Dtype, S: Shape, TensorType: Reshapes<T, S>> {
+pub struct ReshapeStruct<T: NumDtype, S: Shape, TensorType: Reshapes<T, S>> {
     data: TensorType,
     _dtype: PhantomData<T>,
     _shape: PhantomData<S>,
 }
 
-impl<T: Dtype, S: Shape, TensorType: Reshapes<T, S>> ReshapeStruct<T, S, TensorType> {
+impl<T: NumDtype, S: Shape, TensorType: Reshapes<T, S>> ReshapeStruct<T, S, TensorType> {
     pub fn new(data: TensorType) -> Self {
         Self {
             data,
@@ -79,7 +80,7 @@ impl<T: Dtype, S: Shape, TensorType: Reshapes<T, S>> ReshapeStruct<T, S, TensorT
     }
 }
 
-impl<T: Dtype, S: Shape, Si: Shape> Op for ReshapeStruct<T, S, Tensor<T, Si>>
+impl<T: NumDtype, S: Shape, Si: Shape> Op for ReshapeStruct<T, S, Tensor<T, Si>>
 where
     Tensor<T, Si>: Reshapes<T, S>,
 {
@@ -97,35 +98,263 @@ where
         }
     }
 
-    fn operands(&self) -> Vec<TensorBox> {
+    fn operands(&self) -> Vec<TensorBox<'_>> {
         vec![TensorBox::new(self.data.id, &self.data)]
     }
 }
 
-impl<const A: usize, T: Dtype, S: Shape> Reshapes<T, D1<A>> for Tensor<T, S>
+impl<const A: usize, T: NumDtype, S: Shape> Reshapes<T, D1<A>> for Tensor<T, S>
 where
     S: HasNEls<A>,
 {
     fn reshape(self) -> Tensor<T, (I<A>,)> {
-        ReshapeStruct::new(self).forward()
+        ReshapeStruct::new(self.contiguous()).forward()
     }
 }
 
-impl<const A: usize, const B: usize, T: Dtype, S: Shape> Reshapes<T, D2<A, B>> for Tensor<T, S>
+impl<const A: usize, const B: usize, T: NumDtype, S: Shape> Reshapes<T, D2<A, B>> for Tensor<T, S>
 where
     S: HasNEls<{ A * B }>,
 {
     fn reshape(self) -> Tensor<T, (I<A>, I<B>)> {
-        ReshapeStruct::new(self).forward()
+        ReshapeStruct::new(self.contiguous()).forward()
     }
 }
 
-impl<const A: usize, const B: usize, const C: usize, T: Dtype, S: Shape> Reshapes<T, D3<A, B, C>>
+impl<const A: usize, const B: usize, const C: usize, T: NumDtype, S: Shape> Reshapes<T, D3<A, B, C>>
     for Tensor<T, S>
 where
     S: HasNEls<{ A * B * C }>,
 {
     fn reshape(self) -> Tensor<T, (I<A>, I<B>, I<C>)> {
-        ReshapeStruct::new(self).forward()
+        ReshapeStruct::new(self.contiguous()).forward()
+    }
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, T: NumDtype, S: Shape>
+    Reshapes<T, D4<A, B, C, D>> for Tensor<T, S>
+where
+    S: HasNEls<{ A * B * C * D }>,
+{
+    fn reshape(self) -> Tensor<T, (I<A>, I<B>, I<C>, I<D>)> {
+        ReshapeStruct::new(self.contiguous()).forward()
+    }
+}
+
+/// Views the data of a tensor with a different shape of the same size, e.g. when adding or
+/// removing size 1 axes. Like `ReshapeStruct`, storage is shared with the operand. `layout` is
+/// the layout of the view when the operand is itself a strided view.
+#[derive(Debug)]
+pub struct ViewStruct<T: NumDtype, Si: Dims, So: Dims> {
+    pub(crate) data: Tensor<T, Si>,
+    pub(crate) layout: Option<Layout>,
+    pub(crate) shape: So,
+}
+
+impl<T: NumDtype, Si: Dims, So: Dims> Op for ViewStruct<T, Si, So> {
+    type Produces = Tensor<T, So>;
+
+    fn propogate_grad(&self, _t: &Self::Produces) {
+        // View does not change data, therefore no grad propogation occurs
+    }
+
+    fn recompute(&self, _t: &Self::Produces) {}
+
+    fn forward(self) -> Self::Produces {
+        let data = match &self.layout {
+            Some(layout) => self.data.data.view(layout.clone()),
+            None => self.data.data.clone(),
+        };
+        let shape = self.shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.data.id, &self.data)]
+    }
+}
+
+/// Copies a strided view into its own row-major storage.
+#[derive(Debug)]
+pub struct ContiguousStruct<T: NumDtype, S: Dims>(Tensor<T, S>);
+
+impl<T: NumDtype, S: Dims> Op for ContiguousStruct<T, S> {
+    type Produces = Tensor<T, S>;
+
+    fn propogate_grad(&self, t: &Self::Produces) {
+        // t = a
+        // d_da = d_dt, scattered back into the storage of a
+        if let Some(d_dt) = t.data.grad_ref().as_ref() {
+            self.0.update_grad(d_dt.to_vec());
+        } else {
+            panic!("Attempted to propogate grad, but no grad value exists.")
+        }
+    }
+
+    fn recompute(&self, t: &Self::Produces) {
+        t.data.replace(self.0.borrow_value().to_vec())
+    }
+
+    fn forward(self) -> Self::Produces {
+        let data = TensorData::new(self.0.borrow_value().to_vec(), self.0.requires_grad());
+        let shape = self.0.shape.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.0.id, &self.0)]
+    }
+}
+
+impl<T: NumDtype, S: Dims> Tensor<T, S> {
+    /// Copy a strided view (e.g. from `transpose`, `narrow` or `expand`) into its own row-major
+    /// storage. Returns `self` if it already owns its storage.
+    pub fn contiguous(self) -> Self {
+        if self.data.is_contiguous() {
+            self
+        } else {
+            ContiguousStruct(self).forward()
+        }
+    }
+}
+
+/// Repeats the size 1 axes of a tensor to match a larger shape, as a view with a stride of 0
+/// along the repeated axes.
+#[derive(Debug)]
+pub struct ExpandStruct<T: NumDtype, Si: Dims, So: Dims>(pub(crate) Tensor<T, Si>, pub(crate) So);
+
+impl<T: NumDtype, Si: Dims, So: Dims> Op for ExpandStruct<T, Si, So> {
+    type Produces = Tensor<T, So>;
+
+    fn propogate_grad(&self, _t: &Self::Produces) {
+        // Storage is shared with the operand, and grads written along the stride 0 axes
+        // accumulate, so no grad propogation occurs
+    }
+
+    fn recompute(&self, _t: &Self::Produces) {}
+
+    fn forward(self) -> Self::Produces {
+        let layout = self
+            .0
+            .data
+            .layout(&self.0.shape.dims())
+            .broadcast_to(&self.1.dims());
+        let data = self.0.data.view(layout);
+        let shape = self.1.clone();
+        unsafe { Self::Produces::from_rc_td_op_and_shape_unchecked(data, Rc::new(self), shape) }
+    }
+
+    fn operands(&self) -> Vec<TensorBox<'_>> {
+        vec![TensorBox::new(self.0.id, &self.0)]
+    }
+}
+
+impl<T: NumDtype, S: Shape> Tensor<T, S> {
+    /// Insert a size 1 axis at `AXIS`, e.g. `(I<2>, I<3>)` to `(I<2>, I<1>, I<3>)` for `AXIS = 1`.
+    pub fn unsqueeze<const AXIS: usize>(self) -> Tensor<T, <S as InsertAxis<AXIS, 1>>::Output>
+    where
+        S: InsertAxis<AXIS, 1>,
+    {
+        let layout =
+            (!self.data.is_contiguous()).then(|| self.data.layout(S::shape()).insert_axis(AXIS));
+        ViewStruct {
+            data: self,
+            layout,
+            shape: Default::default(),
+        }
+        .forward()
+    }
+
+    /// Remove the size 1 axis at `AXIS`. Squeezing an axis with any other size doesn't compile.
+    pub fn squeeze<const AXIS: usize>(self) -> Tensor<T, <S as Squeeze<AXIS>>::Output>
+    where
+        S: Squeeze<AXIS>,
+    {
+        let layout =
+            (!self.data.is_contiguous()).then(|| self.data.layout(S::shape()).remove_axis(AXIS));
+        ViewStruct {
+            data: self,
+            layout,
+            shape: Default::default(),
+        }
+        .forward()
+    }
+
+    /// Broadcast the size 1 axes of this tensor to `S2`, e.g. `(I<1>, I<3>)` to `(I<4>, I<3>)`.
+    pub fn expand<S2: Shape>(self) -> Tensor<T, S2>
+    where
+        S: BroadcastTo<S2, Output = S2>,
+    {
+        ExpandStruct(self, S2::default()).forward()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::I;
+    use crate::tensor::Tensor;
+
+    #[test]
+    fn test_squeeze_unsqueeze_grad() {
+        let x = Tensor::new_with_grad([[1.0, 2.0, 3.0]]);
+        let s: Tensor<f64, (I<3>,)> = x.clone().squeeze::<0>();
+        let u: Tensor<f64, (I<3>, I<1>)> = s.unsqueeze::<1>();
+        assert_eq!(*u.borrow_value(), [1.0, 2.0, 3.0]);
+
+        (u * Tensor::new([[1.0], [2.0], [3.0]]))
+            .reduce_sum()
+            .backward();
+        assert_eq!(x.borrow_grad().as_deref(), Some(&[1.0, 2.0, 3.0][..]));
+    }
+
+    #[test]
+    fn test_expand_grad() {
+        let x = Tensor::new_with_grad([[1.0], [2.0]]);
+        let e = x.clone().expand::<(I<2>, I<3>)>();
+        assert_eq!(*e.borrow_value(), [1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+
+        (e * Tensor::new([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]))
+            .reduce_sum()
+            .backward();
+        assert_eq!(x.borrow_grad().as_deref(), Some(&[6.0, 15.0][..]));
+    }
+
+    #[test]
+    fn test_strided_views() {
+        let x = Tensor::new_with_grad([[0.0, 1.0, 2.0], [3.0, 4.0, 5.0]]);
+        // Column 1 of the transpose, i.e. row 1 of x, without copying
+        let v: Tensor<f64, (I<3>, I<1>)> = x.shallow_clone().transpose().narrow::<1, 1, 1>();
+        assert_eq!(*v.borrow_value(), [3.0, 4.0, 5.0]);
+        assert!(format!("{:?}", v).starts_with("Tensor([[3.00], [4.00], [5.00]]"));
+
+        // Views read through to the shared storage
+        x.replace_data_with(vec![0.0, 1.0, 2.0, 6.0, 7.0, 8.0]);
+        assert_eq!(*v.borrow_value(), [6.0, 7.0, 8.0]);
+
+        let c: Tensor<f64, (I<3>,)> = v.contiguous().squeeze::<1>();
+        (c * Tensor::new([1.0, 2.0, 3.0])).reduce_sum().backward();
+        assert_eq!(
+            x.borrow_grad().as_deref(),
+            Some(&[0.0, 0.0, 0.0, 1.0, 2.0, 3.0][..])
+        );
+    }
+
+    #[test]
+    fn test_view_write() {
+        let x = Tensor::new_with_grad([[0.0, 1.0], [2.0, 3.0]]);
+        x.clone().reduce_sum().backward();
+        let v: Tensor<f64, (I<2>, I<1>)> = x.shallow_clone().transpose().narrow::<1, 0, 1>();
+        v.replace_data_with(vec![4.0, 5.0]);
+        assert_eq!(*x.borrow_value(), [4.0, 5.0, 2.0, 3.0]);
+        // The grad was computed for the old value
+        assert!(x.borrow_grad().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot write to a broadcast view")]
+    fn test_broadcast_view_write() {
+        let x = Tensor::new([1.0]);
+        let e = x.expand::<(I<3>,)>();
+        e.replace_data_with(vec![1.0, 2.0, 3.0]);
     }
 }
@@ -1,17 +1,100 @@
-#[derive(Debug)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct I<const S: usize>;
 
-pub trait Dim: std::fmt::Debug + 'static {
-    fn size() -> usize;
+/// A dim whose size is only known at runtime, e.g. batch size or sequence length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dyn(pub usize);
+
+pub trait Dim: std::fmt::Debug + Clone + Copy + PartialEq + 'static {
+    fn size(&self) -> usize;
 }
 
 impl<const S: usize> Dim for I<S> {
-    fn size() -> usize {
+    fn size(&self) -> usize {
         S
     }
 }
 
-pub trait Shape: std::fmt::Debug + 'static {
+impl Dim for Dyn {
+    fn size(&self) -> usize {
+        self.0
+    }
+}
+
+/// Shapes made up of static or dynamic dims. The runtime extents are carried by the shape value,
+/// which is stored on the tensor. Fully static shapes additionally implement `Shape`.
+pub trait Dims: std::fmt::Debug + Clone + PartialEq + 'static {
+    fn dims(&self) -> Vec<usize>;
+
+    fn num_els(&self) -> usize {
+        self.dims().iter().product()
+    }
+}
+
+impl Dims for () {
+    fn dims(&self) -> Vec<usize> {
+        vec![]
+    }
+
+    fn num_els(&self) -> usize {
+        0
+    }
+}
+
+impl<A: Dim> Dims for (A,) {
+    fn dims(&self) -> Vec<usize> {
+        vec![self.0.size()]
+    }
+}
+
+impl<A: Dim, B: Dim> Dims for (A, B) {
+    fn dims(&self) -> Vec<usize> {
+        vec![self.0.size(), self.1.size()]
+    }
+}
+
+impl<A: Dim, B: Dim, C: Dim> Dims for (A, B, C) {
+    fn dims(&self) -> Vec<usize> {
+        vec![self.0.size(), self.1.size(), self.2.size()]
+    }
+}
+
+impl<A: Dim, B: Dim, C: Dim, D: Dim> Dims for (A, B, C, D) {
+    fn dims(&self) -> Vec<usize> {
+        vec![self.0.size(), self.1.size(), self.2.size(), self.3.size()]
+    }
+}
+
+/// A shape with a runtime rank, used by `DynTensor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynShape(pub Vec<usize>);
+
+impl Dims for DynShape {
+    fn dims(&self) -> Vec<usize> {
+        self.0.clone()
+    }
+}
+
+/// Returned when a tensor's runtime shape doesn't match the shape it is converted to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShapeMismatch {
+    pub expected: Vec<usize>,
+    pub found: Vec<usize>,
+}
+
+impl std::fmt::Display for ShapeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Expected shape {:?} but found {:?}",
+            self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for ShapeMismatch {}
+
+pub trait Shape: Dims + Default {
     const NUM_DIMS: usize;
     const NUM_ELS: usize;
     fn strides() -> &'static [usize];
@@ -69,15 +152,36 @@ impl<const A: usize, const B: usize, const C: usize> Shape for (I<A>, I<B>, I<C>
     }
 }
 
+impl<const A: usize, const B: usize, const C: usize, const D: usize> Shape
+    for (I<A>, I<B>, I<C>, I<D>)
+{
+    const NUM_DIMS: usize = 4;
+    const NUM_ELS: usize = { A * B * C * D };
+
+    fn strides() -> &'static [usize] {
+        &[B * C * D, C * D, D, 1]
+    }
+
+    fn shape() -> &'static [usize] {
+        &[A, B, C, D]
+    }
+}
+
 pub type D1<const N: usize> = (I<N>,);
 pub type D2<const N: usize, const M: usize> = (I<N>, I<M>);
 pub type D3<const N: usize, const M: usize, const O: usize> = (I<N>, I<M>, I<O>);
+pub type D4<const N: usize, const M: usize, const O: usize, const P: usize> =
+    (I<N>, I<M>, I<O>, I<P>);
 
 pub trait HasNEls<const N: usize> {}
 
 impl<const N: usize> HasNEls<N> for D1<N> {}
 impl<const N: usize, const M: usize> HasNEls<{ N * M }> for D2<N, M> {}
 impl<const N: usize, const M: usize, const O: usize> HasNEls<{ N * M * O }> for D3<N, M, O> {}
+impl<const N: usize, const M: usize, const O: usize, const P: usize> HasNEls<{ N * M * O * P }>
+    for D4<N, M, O, P>
+{
+}
 
 #[macro_export]
 macro_rules! s {
@@ -85,3 +189,1396 @@ macro_rules! s {
     ($d:expr) => {(mlframework::shape::I<$d>,)};
     ( $($d:expr),+ ) => {($(mlframework::shape::I<$d>),+)}
 }
+
+/// Size of a single broadcast dimension. Dims must either match or one of them must be 1,
+/// otherwise const evaluation fails and the offending op does not compile.
+pub const fn broadcast_dim(a: usize, b: usize) -> usize {
+    if a == b || b == 1 {
+        a
+    } else if a == 1 {
+        b
+    } else {
+        panic!("Dimensions are not compatible for broadcasting")
+    }
+}
+
+/// Broadcasting of a single dim against `Rhs`. Static dims are checked at compile time, any
+/// `Dyn` dim makes the output `Dyn` and is checked at runtime.
+pub trait BroadcastDim<Rhs: Dim>: Dim {
+    type Output: Dim;
+    fn broadcast_dim(&self, rhs: &Rhs) -> Self::Output;
+}
+
+impl<const A: usize, const B: usize> BroadcastDim<I<B>> for I<A>
+where
+    [(); broadcast_dim(A, B)]:,
+{
+    type Output = I<{ broadcast_dim(A, B) }>;
+
+    fn broadcast_dim(&self, _rhs: &I<B>) -> Self::Output {
+        I
+    }
+}
+
+impl<A: Dim> BroadcastDim<Dyn> for A {
+    type Output = Dyn;
+
+    fn broadcast_dim(&self, rhs: &Dyn) -> Dyn {
+        Dyn(runtime_broadcast_dim(self.size(), rhs.size()))
+    }
+}
+
+impl<const B: usize> BroadcastDim<I<B>> for Dyn {
+    type Output = Dyn;
+
+    fn broadcast_dim(&self, rhs: &I<B>) -> Dyn {
+        Dyn(runtime_broadcast_dim(self.size(), rhs.size()))
+    }
+}
+
+fn runtime_broadcast_dim(a: usize, b: usize) -> usize {
+    assert!(
+        a == b || a == 1 || b == 1,
+        "Dimensions {} and {} are not compatible for broadcasting",
+        a,
+        b
+    );
+    a.max(b)
+}
+
+/// Numpy-style broadcasting between two shapes. Dims are aligned from the right and missing
+/// leading dims are treated as 1. `Output` is the shape both operands are broadcast to.
+///
+/// ```compile_fail
+/// #![feature(generic_const_exprs)]
+/// use mlframework::{shape::I, Tensor};
+/// let a = Tensor::new([[1.0; 3]; 2]);
+/// let b = Tensor::new([1.0; 2]);
+/// let _: Tensor<f64, (I<2>, I<3>)> = a + b;
+/// ```
+pub trait BroadcastTo<Rhs: Dims>: Dims {
+    type Output: Dims;
+    fn broadcast_shape(&self, rhs: &Rhs) -> Self::Output;
+}
+
+impl BroadcastTo<DynShape> for DynShape {
+    type Output = DynShape;
+
+    fn broadcast_shape(&self, rhs: &DynShape) -> DynShape {
+        let n = self.0.len().max(rhs.0.len());
+        let pad = |d: &[usize]| [vec![1; n - d.len()], d.to_vec()].concat();
+        let (a, b) = (pad(&self.0), pad(&rhs.0));
+        DynShape(
+            a.iter()
+                .zip(b.iter())
+                .map(|(a, b)| runtime_broadcast_dim(*a, *b))
+                .collect(),
+        )
+    }
+}
+
+impl<A: BroadcastDim<B>, B: Dim> BroadcastTo<(B,)> for (A,) {
+    type Output = (A::Output,);
+
+    fn broadcast_shape(&self, rhs: &(B,)) -> Self::Output {
+        (self.0.broadcast_dim(&rhs.0),)
+    }
+}
+
+impl<A: BroadcastDim<B2>, B1: Dim, B2: Dim> BroadcastTo<(B1, B2)> for (A,) {
+    type Output = (B1, A::Output);
+
+    fn broadcast_shape(&self, rhs: &(B1, B2)) -> Self::Output {
+        (rhs.0, self.0.broadcast_dim(&rhs.1))
+    }
+}
+
+impl<A1: Dim, A2: BroadcastDim<B>, B: Dim> BroadcastTo<(B,)> for (A1, A2) {
+    type Output = (A1, A2::Output);
+
+    fn broadcast_shape(&self, rhs: &(B,)) -> Self::Output {
+        (self.0, self.1.broadcast_dim(&rhs.0))
+    }
+}
+
+impl<A1: BroadcastDim<B1>, A2: BroadcastDim<B2>, B1: Dim, B2: Dim> BroadcastTo<(B1, B2)>
+    for (A1, A2)
+{
+    type Output = (A1::Output, A2::Output);
+
+    fn broadcast_shape(&self, rhs: &(B1, B2)) -> Self::Output {
+        (self.0.broadcast_dim(&rhs.0), self.1.broadcast_dim(&rhs.1))
+    }
+}
+
+impl<A: BroadcastDim<B3>, B1: Dim, B2: Dim, B3: Dim> BroadcastTo<(B1, B2, B3)> for (A,) {
+    type Output = (B1, B2, A::Output);
+
+    fn broadcast_shape(&self, rhs: &(B1, B2, B3)) -> Self::Output {
+        (rhs.0, rhs.1, self.0.broadcast_dim(&rhs.2))
+    }
+}
+
+impl<A1: Dim, A2: Dim, A3: BroadcastDim<B>, B: Dim> BroadcastTo<(B,)> for (A1, A2, A3) {
+    type Output = (A1, A2, A3::Output);
+
+    fn broadcast_shape(&self, rhs: &(B,)) -> Self::Output {
+        (self.0, self.1, self.2.broadcast_dim(&rhs.0))
+    }
+}
+
+impl<A1: BroadcastDim<B2>, A2: BroadcastDim<B3>, B1: Dim, B2: Dim, B3: Dim>
+    BroadcastTo<(B1, B2, B3)> for (A1, A2)
+{
+    type Output = (B1, A1::Output, A2::Output);
+
+    fn broadcast_shape(&self, rhs: &(B1, B2, B3)) -> Self::Output {
+        (
+            rhs.0,
+            self.0.broadcast_dim(&rhs.1),
+            self.1.broadcast_dim(&rhs.2),
+        )
+    }
+}
+
+impl<A1: Dim, A2: BroadcastDim<B1>, A3: BroadcastDim<B2>, B1: Dim, B2: Dim> BroadcastTo<(B1, B2)>
+    for (A1, A2, A3)
+{
+    type Output = (A1, A2::Output, A3::Output);
+
+    fn broadcast_shape(&self, rhs: &(B1, B2)) -> Self::Output {
+        (
+            self.0,
+            self.1.broadcast_dim(&rhs.0),
+            self.2.broadcast_dim(&rhs.1),
+        )
+    }
+}
+
+impl<
+        A1: BroadcastDim<B1>,
+        A2: BroadcastDim<B2>,
+        A3: BroadcastDim<B3>,
+        B1: Dim,
+        B2: Dim,
+        B3: Dim,
+    > BroadcastTo<(B1, B2, B3)> for (A1, A2, A3)
+{
+    type Output = (A1::Output, A2::Output, A3::Output);
+
+    fn broadcast_shape(&self, rhs: &(B1, B2, B3)) -> Self::Output {
+        (
+            self.0.broadcast_dim(&rhs.0),
+            self.1.broadcast_dim(&rhs.1),
+            self.2.broadcast_dim(&rhs.2),
+        )
+    }
+}
+
+impl<A: BroadcastDim<B4>, B1: Dim, B2: Dim, B3: Dim, B4: Dim> BroadcastTo<(B1, B2, B3, B4)>
+    for (A,)
+{
+    type Output = (B1, B2, B3, A::Output);
+
+    fn broadcast_shape(&self, rhs: &(B1, B2, B3, B4)) -> Self::Output {
+        (rhs.0, rhs.1, rhs.2, self.0.broadcast_dim(&rhs.3))
+    }
+}
+
+impl<A1: Dim, A2: Dim, A3: Dim, A4: BroadcastDim<B>, B: Dim> BroadcastTo<(B,)>
+    for (A1, A2, A3, A4)
+{
+    type Output = (A1, A2, A3, A4::Output);
+
+    fn broadcast_shape(&self, rhs: &(B,)) -> Self::Output {
+        (self.0, self.1, self.2, self.3.broadcast_dim(&rhs.0))
+    }
+}
+
+impl<A1: BroadcastDim<B3>, A2: BroadcastDim<B4>, B1: Dim, B2: Dim, B3: Dim, B4: Dim>
+    BroadcastTo<(B1, B2, B3, B4)> for (A1, A2)
+{
+    type Output = (B1, B2, A1::Output, A2::Output);
+
+    fn broadcast_shape(&self, rhs: &(B1, B2, B3, B4)) -> Self::Output {
+        (
+            rhs.0,
+            rhs.1,
+            self.0.broadcast_dim(&rhs.2),
+            self.1.broadcast_dim(&rhs.3),
+        )
+    }
+}
+
+impl<A1: Dim, A2: Dim, A3: BroadcastDim<B1>, A4: BroadcastDim<B2>, B1: Dim, B2: Dim>
+    BroadcastTo<(B1, B2)> for (A1, A2, A3, A4)
+{
+    type Output = (A1, A2, A3::Output, A4::Output);
+
+    fn broadcast_shape(&self, rhs: &(B1, B2)) -> Self::Output {
+        (
+            self.0,
+            self.1,
+            self.2.broadcast_dim(&rhs.0),
+            self.3.broadcast_dim(&rhs.1),
+        )
+    }
+}
+
+impl<
+        A1: BroadcastDim<B2>,
+        A2: BroadcastDim<B3>,
+        A3: BroadcastDim<B4>,
+        B1: Dim,
+        B2: Dim,
+        B3: Dim,
+        B4: Dim,
+    > BroadcastTo<(B1, B2, B3, B4)> for (A1, A2, A3)
+{
+    type Output = (B1, A1::Output, A2::Output, A3::Output);
+
+    fn broadcast_shape(&self, rhs: &(B1, B2, B3, B4)) -> Self::Output {
+        (
+            rhs.0,
+            self.0.broadcast_dim(&rhs.1),
+            self.1.broadcast_dim(&rhs.2),
+            self.2.broadcast_dim(&rhs.3),
+        )
+    }
+}
+
+impl<
+        A1: Dim,
+        A2: BroadcastDim<B1>,
+        A3: BroadcastDim<B2>,
+        A4: BroadcastDim<B3>,
+        B1: Dim,
+        B2: Dim,
+        B3: Dim,
+    > BroadcastTo<(B1, B2, B3)> for (A1, A2, A3, A4)
+{
+    type Output = (A1, A2::Output, A3::Output, A4::Output);
+
+    fn broadcast_shape(&self, rhs: &(B1, B2, B3)) -> Self::Output {
+        (
+            self.0,
+            self.1.broadcast_dim(&rhs.0),
+            self.2.broadcast_dim(&rhs.1),
+            self.3.broadcast_dim(&rhs.2),
+        )
+    }
+}
+
+impl<
+        A1: BroadcastDim<B1>,
+        A2: BroadcastDim<B2>,
+        A3: BroadcastDim<B3>,
+        A4: BroadcastDim<B4>,
+        B1: Dim,
+        B2: Dim,
+        B3: Dim,
+        B4: Dim,
+    > BroadcastTo<(B1, B2, B3, B4)> for (A1, A2, A3, A4)
+{
+    type Output = (A1::Output, A2::Output, A3::Output, A4::Output);
+
+    fn broadcast_shape(&self, rhs: &(B1, B2, B3, B4)) -> Self::Output {
+        (
+            self.0.broadcast_dim(&rhs.0),
+            self.1.broadcast_dim(&rhs.1),
+            self.2.broadcast_dim(&rhs.2),
+            self.3.broadcast_dim(&rhs.3),
+        )
+    }
+}
+
+/// Shapes that can be reduced along `AXIS`. `Reduced` drops the axis and `KeepDim` keeps it with
+/// size 1. Reducing the only axis of a 1d shape gives `(I<1>,)`, matching `reduce_sum`.
+pub trait ReduceAxis<const AXIS: usize>: Shape {
+    type Reduced: Shape;
+    type KeepDim: Shape;
+}
+
+impl<const A: usize> ReduceAxis<0> for (I<A>,) {
+    type Reduced = (I<1>,);
+    type KeepDim = (I<1>,);
+}
+
+impl<const A: usize, const B: usize> ReduceAxis<0> for (I<A>, I<B>) {
+    type Reduced = (I<B>,);
+    type KeepDim = (I<1>, I<B>);
+}
+
+impl<const A: usize, const B: usize> ReduceAxis<1> for (I<A>, I<B>) {
+    type Reduced = (I<A>,);
+    type KeepDim = (I<A>, I<1>);
+}
+
+impl<const A: usize, const B: usize, const C: usize> ReduceAxis<0> for (I<A>, I<B>, I<C>) {
+    type Reduced = (I<B>, I<C>);
+    type KeepDim = (I<1>, I<B>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize> ReduceAxis<1> for (I<A>, I<B>, I<C>) {
+    type Reduced = (I<A>, I<C>);
+    type KeepDim = (I<A>, I<1>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize> ReduceAxis<2> for (I<A>, I<B>, I<C>) {
+    type Reduced = (I<A>, I<B>);
+    type KeepDim = (I<A>, I<B>, I<1>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize> ReduceAxis<0>
+    for (I<A>, I<B>, I<C>, I<D>)
+{
+    type Reduced = (I<B>, I<C>, I<D>);
+    type KeepDim = (I<1>, I<B>, I<C>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize> ReduceAxis<1>
+    for (I<A>, I<B>, I<C>, I<D>)
+{
+    type Reduced = (I<A>, I<C>, I<D>);
+    type KeepDim = (I<A>, I<1>, I<C>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize> ReduceAxis<2>
+    for (I<A>, I<B>, I<C>, I<D>)
+{
+    type Reduced = (I<A>, I<B>, I<D>);
+    type KeepDim = (I<A>, I<B>, I<1>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize> ReduceAxis<3>
+    for (I<A>, I<B>, I<C>, I<D>)
+{
+    type Reduced = (I<A>, I<B>, I<C>);
+    type KeepDim = (I<A>, I<B>, I<C>, I<1>);
+}
+
+/// Shapes whose last axis has size `D`, e.g. the features normalised by `layer_norm`.
+pub trait LastDim<const D: usize>: Shape {}
+
+impl<const D: usize> LastDim<D> for (I<D>,) {}
+impl<const A: usize, const D: usize> LastDim<D> for (I<A>, I<D>) {}
+impl<const A: usize, const B: usize, const D: usize> LastDim<D> for (I<A>, I<B>, I<D>) {}
+impl<const A: usize, const B: usize, const C: usize, const D: usize> LastDim<D>
+    for (I<A>, I<B>, I<C>, I<D>)
+{
+}
+
+/// Batched shapes `(N, C, ...)` with `C` channels along axis 1.
+pub trait ChannelDim<const C: usize>: Shape {}
+
+impl<const N: usize, const C: usize> ChannelDim<C> for (I<N>, I<C>) {}
+impl<const N: usize, const C: usize, const L: usize> ChannelDim<C> for (I<N>, I<C>, I<L>) {}
+impl<const N: usize, const C: usize, const H: usize, const W: usize> ChannelDim<C>
+    for (I<N>, I<C>, I<H>, I<W>)
+{
+}
+
+/// Type-level axis order for a rank 2 `permute`.
+#[derive(Debug)]
+pub struct Axes2<const A: usize, const B: usize>;
+
+/// Type-level axis order for a rank 3 `permute`.
+#[derive(Debug)]
+pub struct Axes3<const A: usize, const B: usize, const C: usize>;
+
+/// Type-level axis order for a rank 4 `permute`, e.g. `Axes4<0, 2, 3, 1>` for NCHW to NHWC.
+#[derive(Debug)]
+pub struct Axes4<const A: usize, const B: usize, const C: usize, const D: usize>;
+
+pub trait Axes: std::fmt::Debug + 'static {
+    fn axes() -> &'static [usize];
+}
+
+impl<const A: usize, const B: usize> Axes for Axes2<A, B> {
+    fn axes() -> &'static [usize] {
+        &[A, B]
+    }
+}
+
+impl<const A: usize, const B: usize, const C: usize> Axes for Axes3<A, B, C> {
+    fn axes() -> &'static [usize] {
+        &[A, B, C]
+    }
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize> Axes for Axes4<A, B, C, D> {
+    fn axes() -> &'static [usize] {
+        &[A, B, C, D]
+    }
+}
+
+/// Size of dim `i` after permuting `dims` by `axes`. Fails const evaluation if `axes` is not a
+/// permutation of `0..N`.
+pub const fn permuted_dim<const N: usize>(dims: [usize; N], axes: [usize; N], i: usize) -> usize {
+    let mut seen = [false; N];
+    let mut j = 0;
+    while j < N {
+        if axes[j] >= N || seen[axes[j]] {
+            panic!("Axes are not a valid permutation")
+        }
+        seen[axes[j]] = true;
+        j += 1;
+    }
+    dims[axes[i]]
+}
+
+// Generic constants can't construct arrays, so these wrap `permuted_dim` for each rank.
+
+pub const fn permuted_dim2(a: usize, b: usize, p0: usize, p1: usize, i: usize) -> usize {
+    permuted_dim([a, b], [p0, p1], i)
+}
+
+pub const fn permuted_dim3(
+    a: usize,
+    b: usize,
+    c: usize,
+    p0: usize,
+    p1: usize,
+    p2: usize,
+    i: usize,
+) -> usize {
+    permuted_dim([a, b, c], [p0, p1, p2], i)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub const fn permuted_dim4(
+    a: usize,
+    b: usize,
+    c: usize,
+    d: usize,
+    p0: usize,
+    p1: usize,
+    p2: usize,
+    p3: usize,
+    i: usize,
+) -> usize {
+    permuted_dim([a, b, c, d], [p0, p1, p2, p3], i)
+}
+
+/// Shapes that can be permuted by the axis order `P`, with `Output` the permuted shape.
+pub trait Permute<P: Axes>: Shape {
+    type Output: Shape;
+}
+
+impl<const A: usize, const B: usize, const P0: usize, const P1: usize> Permute<Axes2<P0, P1>>
+    for (I<A>, I<B>)
+where
+    [(); permuted_dim2(A, B, P0, P1, 0)]:,
+    [(); permuted_dim2(A, B, P0, P1, 1)]:,
+{
+    type Output = (
+        I<{ permuted_dim2(A, B, P0, P1, 0) }>,
+        I<{ permuted_dim2(A, B, P0, P1, 1) }>,
+    );
+}
+
+impl<
+        const A: usize,
+        const B: usize,
+        const C: usize,
+        const P0: usize,
+        const P1: usize,
+        const P2: usize,
+    > Permute<Axes3<P0, P1, P2>> for (I<A>, I<B>, I<C>)
+where
+    [(); permuted_dim3(A, B, C, P0, P1, P2, 0)]:,
+    [(); permuted_dim3(A, B, C, P0, P1, P2, 1)]:,
+    [(); permuted_dim3(A, B, C, P0, P1, P2, 2)]:,
+{
+    type Output = (
+        I<{ permuted_dim3(A, B, C, P0, P1, P2, 0) }>,
+        I<{ permuted_dim3(A, B, C, P0, P1, P2, 1) }>,
+        I<{ permuted_dim3(A, B, C, P0, P1, P2, 2) }>,
+    );
+}
+
+impl<
+        const A: usize,
+        const B: usize,
+        const C: usize,
+        const D: usize,
+        const P0: usize,
+        const P1: usize,
+        const P2: usize,
+        const P3: usize,
+    > Permute<Axes4<P0, P1, P2, P3>> for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); permuted_dim4(A, B, C, D, P0, P1, P2, P3, 0)]:,
+    [(); permuted_dim4(A, B, C, D, P0, P1, P2, P3, 1)]:,
+    [(); permuted_dim4(A, B, C, D, P0, P1, P2, P3, 2)]:,
+    [(); permuted_dim4(A, B, C, D, P0, P1, P2, P3, 3)]:,
+{
+    type Output = (
+        I<{ permuted_dim4(A, B, C, D, P0, P1, P2, P3, 0) }>,
+        I<{ permuted_dim4(A, B, C, D, P0, P1, P2, P3, 1) }>,
+        I<{ permuted_dim4(A, B, C, D, P0, P1, P2, P3, 2) }>,
+        I<{ permuted_dim4(A, B, C, D, P0, P1, P2, P3, 3) }>,
+    );
+}
+
+/// Checks `START..START + LEN` lies within a dim of size `dim`, failing const evaluation if not.
+pub const fn narrow_len(dim: usize, start: usize, len: usize) -> usize {
+    if start + len > dim {
+        panic!("Narrowed range is out of bounds")
+    }
+    len
+}
+
+/// Shapes that can be narrowed to `START..START + LEN` along `AXIS`, with `Output` the narrowed
+/// shape. Out of bounds ranges fail to compile.
+pub trait Narrow<const AXIS: usize, const START: usize, const LEN: usize>: Shape {
+    type Output: Shape;
+}
+
+impl<const A: usize, const START: usize, const LEN: usize> Narrow<0, START, LEN> for (I<A>,)
+where
+    [(); narrow_len(A, START, LEN)]:,
+{
+    type Output = (I<{ narrow_len(A, START, LEN) }>,);
+}
+
+impl<const A: usize, const B: usize, const START: usize, const LEN: usize> Narrow<0, START, LEN>
+    for (I<A>, I<B>)
+where
+    [(); narrow_len(A, START, LEN)]:,
+{
+    type Output = (I<{ narrow_len(A, START, LEN) }>, I<B>);
+}
+
+impl<const A: usize, const B: usize, const START: usize, const LEN: usize> Narrow<1, START, LEN>
+    for (I<A>, I<B>)
+where
+    [(); narrow_len(B, START, LEN)]:,
+{
+    type Output = (I<A>, I<{ narrow_len(B, START, LEN) }>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const START: usize, const LEN: usize>
+    Narrow<0, START, LEN> for (I<A>, I<B>, I<C>)
+where
+    [(); narrow_len(A, START, LEN)]:,
+{
+    type Output = (I<{ narrow_len(A, START, LEN) }>, I<B>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const START: usize, const LEN: usize>
+    Narrow<1, START, LEN> for (I<A>, I<B>, I<C>)
+where
+    [(); narrow_len(B, START, LEN)]:,
+{
+    type Output = (I<A>, I<{ narrow_len(B, START, LEN) }>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const START: usize, const LEN: usize>
+    Narrow<2, START, LEN> for (I<A>, I<B>, I<C>)
+where
+    [(); narrow_len(C, START, LEN)]:,
+{
+    type Output = (I<A>, I<B>, I<{ narrow_len(C, START, LEN) }>);
+}
+
+impl<
+        const A: usize,
+        const B: usize,
+        const C: usize,
+        const D: usize,
+        const START: usize,
+        const LEN: usize,
+    > Narrow<0, START, LEN> for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); narrow_len(A, START, LEN)]:,
+{
+    type Output = (I<{ narrow_len(A, START, LEN) }>, I<B>, I<C>, I<D>);
+}
+
+impl<
+        const A: usize,
+        const B: usize,
+        const C: usize,
+        const D: usize,
+        const START: usize,
+        const LEN: usize,
+    > Narrow<1, START, LEN> for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); narrow_len(B, START, LEN)]:,
+{
+    type Output = (I<A>, I<{ narrow_len(B, START, LEN) }>, I<C>, I<D>);
+}
+
+impl<
+        const A: usize,
+        const B: usize,
+        const C: usize,
+        const D: usize,
+        const START: usize,
+        const LEN: usize,
+    > Narrow<2, START, LEN> for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); narrow_len(C, START, LEN)]:,
+{
+    type Output = (I<A>, I<B>, I<{ narrow_len(C, START, LEN) }>, I<D>);
+}
+
+impl<
+        const A: usize,
+        const B: usize,
+        const C: usize,
+        const D: usize,
+        const START: usize,
+        const LEN: usize,
+    > Narrow<3, START, LEN> for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); narrow_len(D, START, LEN)]:,
+{
+    type Output = (I<A>, I<B>, I<C>, I<{ narrow_len(D, START, LEN) }>);
+}
+
+/// Shapes that can be concatenated with `Rhs` along `AXIS`. All other dims must match and the
+/// `AXIS` dim of `Output` is the sum of both.
+pub trait Concat<Rhs: Shape, const AXIS: usize>: Shape {
+    type Output: Shape;
+}
+
+impl<const A: usize, const R: usize> Concat<(I<R>,), 0> for (I<A>,)
+where
+    [(); A + R]:,
+{
+    type Output = (I<{ A + R }>,);
+}
+
+impl<const A: usize, const B: usize, const R: usize> Concat<(I<R>, I<B>), 0> for (I<A>, I<B>)
+where
+    [(); A + R]:,
+{
+    type Output = (I<{ A + R }>, I<B>);
+}
+
+impl<const A: usize, const B: usize, const R: usize> Concat<(I<A>, I<R>), 1> for (I<A>, I<B>)
+where
+    [(); B + R]:,
+{
+    type Output = (I<A>, I<{ B + R }>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const R: usize> Concat<(I<R>, I<B>, I<C>), 0>
+    for (I<A>, I<B>, I<C>)
+where
+    [(); A + R]:,
+{
+    type Output = (I<{ A + R }>, I<B>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const R: usize> Concat<(I<A>, I<R>, I<C>), 1>
+    for (I<A>, I<B>, I<C>)
+where
+    [(); B + R]:,
+{
+    type Output = (I<A>, I<{ B + R }>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const R: usize> Concat<(I<A>, I<B>, I<R>), 2>
+    for (I<A>, I<B>, I<C>)
+where
+    [(); C + R]:,
+{
+    type Output = (I<A>, I<B>, I<{ C + R }>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const R: usize>
+    Concat<(I<R>, I<B>, I<C>, I<D>), 0> for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); A + R]:,
+{
+    type Output = (I<{ A + R }>, I<B>, I<C>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const R: usize>
+    Concat<(I<A>, I<R>, I<C>, I<D>), 1> for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); B + R]:,
+{
+    type Output = (I<A>, I<{ B + R }>, I<C>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const R: usize>
+    Concat<(I<A>, I<B>, I<R>, I<D>), 2> for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); C + R]:,
+{
+    type Output = (I<A>, I<B>, I<{ C + R }>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const R: usize>
+    Concat<(I<A>, I<B>, I<C>, I<R>), 3> for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); D + R]:,
+{
+    type Output = (I<A>, I<B>, I<C>, I<{ D + R }>);
+}
+
+/// Shapes that can be padded with `BEFORE` and `AFTER` elements along `AXIS`.
+pub trait Pad<const AXIS: usize, const BEFORE: usize, const AFTER: usize>: Shape {
+    type Output: Shape;
+}
+
+impl<const A: usize, const P: usize, const Q: usize> Pad<0, P, Q> for (I<A>,)
+where
+    [(); A + P + Q]:,
+{
+    type Output = (I<{ A + P + Q }>,);
+}
+
+impl<const A: usize, const B: usize, const P: usize, const Q: usize> Pad<0, P, Q> for (I<A>, I<B>)
+where
+    [(); A + P + Q]:,
+{
+    type Output = (I<{ A + P + Q }>, I<B>);
+}
+
+impl<const A: usize, const B: usize, const P: usize, const Q: usize> Pad<1, P, Q> for (I<A>, I<B>)
+where
+    [(); B + P + Q]:,
+{
+    type Output = (I<A>, I<{ B + P + Q }>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const P: usize, const Q: usize> Pad<0, P, Q>
+    for (I<A>, I<B>, I<C>)
+where
+    [(); A + P + Q]:,
+{
+    type Output = (I<{ A + P + Q }>, I<B>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const P: usize, const Q: usize> Pad<1, P, Q>
+    for (I<A>, I<B>, I<C>)
+where
+    [(); B + P + Q]:,
+{
+    type Output = (I<A>, I<{ B + P + Q }>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const P: usize, const Q: usize> Pad<2, P, Q>
+    for (I<A>, I<B>, I<C>)
+where
+    [(); C + P + Q]:,
+{
+    type Output = (I<A>, I<B>, I<{ C + P + Q }>);
+}
+
+impl<
+        const A: usize,
+        const B: usize,
+        const C: usize,
+        const D: usize,
+        const P: usize,
+        const Q: usize,
+    > Pad<0, P, Q> for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); A + P + Q]:,
+{
+    type Output = (I<{ A + P + Q }>, I<B>, I<C>, I<D>);
+}
+
+impl<
+        const A: usize,
+        const B: usize,
+        const C: usize,
+        const D: usize,
+        const P: usize,
+        const Q: usize,
+    > Pad<1, P, Q> for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); B + P + Q]:,
+{
+    type Output = (I<A>, I<{ B + P + Q }>, I<C>, I<D>);
+}
+
+impl<
+        const A: usize,
+        const B: usize,
+        const C: usize,
+        const D: usize,
+        const P: usize,
+        const Q: usize,
+    > Pad<2, P, Q> for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); C + P + Q]:,
+{
+    type Output = (I<A>, I<B>, I<{ C + P + Q }>, I<D>);
+}
+
+impl<
+        const A: usize,
+        const B: usize,
+        const C: usize,
+        const D: usize,
+        const P: usize,
+        const Q: usize,
+    > Pad<3, P, Q> for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); D + P + Q]:,
+{
+    type Output = (I<A>, I<B>, I<C>, I<{ D + P + Q }>);
+}
+
+/// Output size of a convolution along one spatial axis of size `n`, for a kernel of size `k`.
+/// Fails const evaluation if the dilated kernel doesn't fit in the padded input.
+pub const fn conv_out_size(
+    n: usize,
+    k: usize,
+    stride: usize,
+    padding: usize,
+    dilation: usize,
+) -> usize {
+    if stride == 0 || dilation == 0 || k == 0 {
+        panic!("Convolution stride, dilation and kernel size must be positive")
+    }
+    let span = dilation * (k - 1) + 1;
+    if n + 2 * padding < span {
+        panic!("Convolution kernel is larger than the padded input")
+    }
+    (n + 2 * padding - span) / stride + 1
+}
+
+/// Checks that `c` input and `o` output channels split into `groups` groups, where each output
+/// channel sees `cg` input channels. Fails const evaluation otherwise.
+pub const fn conv_groups(c: usize, cg: usize, o: usize, groups: usize) -> usize {
+    if groups == 0 || c != cg * groups || !o.is_multiple_of(groups) {
+        panic!("Convolution channels are not compatible with the number of groups")
+    }
+    groups
+}
+
+/// Input shapes `(N, C, L)` that can be convolved with weights `W` of shape `(O, C / GROUPS, K)`.
+pub trait Conv1d<
+    W: Shape,
+    const STRIDE: usize,
+    const PADDING: usize,
+    const DILATION: usize,
+    const GROUPS: usize,
+>: Shape
+{
+    type Output: Shape;
+}
+
+impl<
+        const N: usize,
+        const C: usize,
+        const L: usize,
+        const O: usize,
+        const CG: usize,
+        const K: usize,
+        const STRIDE: usize,
+        const PADDING: usize,
+        const DILATION: usize,
+        const GROUPS: usize,
+    > Conv1d<(I<O>, I<CG>, I<K>), STRIDE, PADDING, DILATION, GROUPS> for (I<N>, I<C>, I<L>)
+where
+    [(); conv_out_size(L, K, STRIDE, PADDING, DILATION)]:,
+    [(); conv_groups(C, CG, O, GROUPS)]:,
+{
+    type Output = (
+        I<N>,
+        I<O>,
+        I<{ conv_out_size(L, K, STRIDE, PADDING, DILATION) }>,
+    );
+}
+
+/// Input shapes `(N, C, H, W)` that can be convolved with weights `W` of shape
+/// `(O, C / GROUPS, KH, KW)`. Stride, padding and dilation are the same along both spatial axes.
+///
+/// ```compile_fail
+/// #![feature(generic_const_exprs)]
+/// use mlframework::{ops::Convolves, shape::I, Tensor};
+/// let x: Tensor<f64, (I<1>, I<1>, I<2>, I<2>)> = Tensor::new([[[[1.0; 2]; 2]]]);
+/// let w: Tensor<f64, (I<1>, I<1>, I<3>, I<3>)> = Tensor::new([[[[1.0; 3]; 3]]]);
+/// let _ = x.conv2d::<1, 0, 1, 1>(w);
+/// ```
+pub trait Conv2d<
+    W: Shape,
+    const STRIDE: usize,
+    const PADDING: usize,
+    const DILATION: usize,
+    const GROUPS: usize,
+>: Shape
+{
+    type Output: Shape;
+}
+
+impl<
+        const N: usize,
+        const C: usize,
+        const H: usize,
+        const W: usize,
+        const O: usize,
+        const CG: usize,
+        const KH: usize,
+        const KW: usize,
+        const STRIDE: usize,
+        const PADDING: usize,
+        const DILATION: usize,
+        const GROUPS: usize,
+    > Conv2d<(I<O>, I<CG>, I<KH>, I<KW>), STRIDE, PADDING, DILATION, GROUPS>
+    for (I<N>, I<C>, I<H>, I<W>)
+where
+    [(); conv_out_size(H, KH, STRIDE, PADDING, DILATION)]:,
+    [(); conv_out_size(W, KW, STRIDE, PADDING, DILATION)]:,
+    [(); conv_groups(C, CG, O, GROUPS)]:,
+{
+    type Output = (
+        I<N>,
+        I<O>,
+        I<{ conv_out_size(H, KH, STRIDE, PADDING, DILATION) }>,
+        I<{ conv_out_size(W, KW, STRIDE, PADDING, DILATION) }>,
+    );
+}
+
+/// Output size of a pooling window of size `k` along one spatial axis of size `n`. Fails const
+/// evaluation if the padding is more than half the window, as windows could then only see
+/// padding.
+pub const fn pool_out_size(n: usize, k: usize, stride: usize, padding: usize) -> usize {
+    if 2 * padding > k {
+        panic!("Pooling padding must be at most half the kernel size")
+    }
+    conv_out_size(n, k, stride, padding, 1)
+}
+
+/// Input shapes `(N, C, L)` that can be pooled with windows of size `KERNEL`.
+pub trait Pool1d<const KERNEL: usize, const STRIDE: usize, const PADDING: usize>: Shape {
+    type Output: Shape;
+}
+
+impl<
+        const N: usize,
+        const C: usize,
+        const L: usize,
+        const KERNEL: usize,
+        const STRIDE: usize,
+        const PADDING: usize,
+    > Pool1d<KERNEL, STRIDE, PADDING> for (I<N>, I<C>, I<L>)
+where
+    [(); pool_out_size(L, KERNEL, STRIDE, PADDING)]:,
+{
+    type Output = (I<N>, I<C>, I<{ pool_out_size(L, KERNEL, STRIDE, PADDING) }>);
+}
+
+/// Input shapes `(N, C, H, W)` that can be pooled with `KERNEL x KERNEL` windows.
+pub trait Pool2d<const KERNEL: usize, const STRIDE: usize, const PADDING: usize>: Shape {
+    type Output: Shape;
+}
+
+impl<
+        const N: usize,
+        const C: usize,
+        const H: usize,
+        const W: usize,
+        const KERNEL: usize,
+        const STRIDE: usize,
+        const PADDING: usize,
+    > Pool2d<KERNEL, STRIDE, PADDING> for (I<N>, I<C>, I<H>, I<W>)
+where
+    [(); pool_out_size(H, KERNEL, STRIDE, PADDING)]:,
+    [(); pool_out_size(W, KERNEL, STRIDE, PADDING)]:,
+{
+    type Output = (
+        I<N>,
+        I<C>,
+        I<{ pool_out_size(H, KERNEL, STRIDE, PADDING) }>,
+        I<{ pool_out_size(W, KERNEL, STRIDE, PADDING) }>,
+    );
+}
+
+/// Shapes that can have a new axis of size `N` inserted at `AXIS`, e.g. when stacking `N`
+/// tensors of this shape.
+pub trait InsertAxis<const AXIS: usize, const N: usize>: Shape {
+    type Output: Shape;
+}
+
+impl<const A: usize, const N: usize> InsertAxis<0, N> for (I<A>,) {
+    type Output = (I<N>, I<A>);
+}
+
+impl<const A: usize, const N: usize> InsertAxis<1, N> for (I<A>,) {
+    type Output = (I<A>, I<N>);
+}
+
+impl<const A: usize, const B: usize, const N: usize> InsertAxis<0, N> for (I<A>, I<B>) {
+    type Output = (I<N>, I<A>, I<B>);
+}
+
+impl<const A: usize, const B: usize, const N: usize> InsertAxis<1, N> for (I<A>, I<B>) {
+    type Output = (I<A>, I<N>, I<B>);
+}
+
+impl<const A: usize, const B: usize, const N: usize> InsertAxis<2, N> for (I<A>, I<B>) {
+    type Output = (I<A>, I<B>, I<N>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const N: usize> InsertAxis<0, N>
+    for (I<A>, I<B>, I<C>)
+{
+    type Output = (I<N>, I<A>, I<B>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const N: usize> InsertAxis<1, N>
+    for (I<A>, I<B>, I<C>)
+{
+    type Output = (I<A>, I<N>, I<B>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const N: usize> InsertAxis<2, N>
+    for (I<A>, I<B>, I<C>)
+{
+    type Output = (I<A>, I<B>, I<N>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const N: usize> InsertAxis<3, N>
+    for (I<A>, I<B>, I<C>)
+{
+    type Output = (I<A>, I<B>, I<C>, I<N>);
+}
+
+/// Shapes with a size 1 axis at `AXIS` that can be removed.
+pub trait Squeeze<const AXIS: usize>: Shape {
+    type Output: Shape;
+}
+
+impl<const B: usize> Squeeze<0> for (I<1>, I<B>) {
+    type Output = (I<B>,);
+}
+
+impl<const A: usize> Squeeze<1> for (I<A>, I<1>) {
+    type Output = (I<A>,);
+}
+
+impl<const B: usize, const C: usize> Squeeze<0> for (I<1>, I<B>, I<C>) {
+    type Output = (I<B>, I<C>);
+}
+
+impl<const A: usize, const C: usize> Squeeze<1> for (I<A>, I<1>, I<C>) {
+    type Output = (I<A>, I<C>);
+}
+
+impl<const A: usize, const B: usize> Squeeze<2> for (I<A>, I<B>, I<1>) {
+    type Output = (I<A>, I<B>);
+}
+
+impl<const B: usize, const C: usize, const D: usize> Squeeze<0> for (I<1>, I<B>, I<C>, I<D>) {
+    type Output = (I<B>, I<C>, I<D>);
+}
+
+impl<const A: usize, const C: usize, const D: usize> Squeeze<1> for (I<A>, I<1>, I<C>, I<D>) {
+    type Output = (I<A>, I<C>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const D: usize> Squeeze<2> for (I<A>, I<B>, I<1>, I<D>) {
+    type Output = (I<A>, I<B>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize> Squeeze<3> for (I<A>, I<B>, I<C>, I<1>) {
+    type Output = (I<A>, I<B>, I<C>);
+}
+
+/// Index shapes for gathering from or scattering into this shape along `AXIS`. `Idx` matches this
+/// shape on every axis except `AXIS`.
+pub trait GatherIndex<Idx: Shape, const AXIS: usize>: Shape {}
+
+impl<const A: usize, const K: usize> GatherIndex<(I<K>,), 0> for (I<A>,) {}
+
+impl<const A: usize, const B: usize, const K: usize> GatherIndex<(I<K>, I<B>), 0> for (I<A>, I<B>) {}
+
+impl<const A: usize, const B: usize, const K: usize> GatherIndex<(I<A>, I<K>), 1> for (I<A>, I<B>) {}
+
+impl<const A: usize, const B: usize, const C: usize, const K: usize>
+    GatherIndex<(I<K>, I<B>, I<C>), 0> for (I<A>, I<B>, I<C>)
+{
+}
+
+impl<const A: usize, const B: usize, const C: usize, const K: usize>
+    GatherIndex<(I<A>, I<K>, I<C>), 1> for (I<A>, I<B>, I<C>)
+{
+}
+
+impl<const A: usize, const B: usize, const C: usize, const K: usize>
+    GatherIndex<(I<A>, I<B>, I<K>), 2> for (I<A>, I<B>, I<C>)
+{
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const K: usize>
+    GatherIndex<(I<K>, I<B>, I<C>, I<D>), 0> for (I<A>, I<B>, I<C>, I<D>)
+{
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const K: usize>
+    GatherIndex<(I<A>, I<K>, I<C>, I<D>), 1> for (I<A>, I<B>, I<C>, I<D>)
+{
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const K: usize>
+    GatherIndex<(I<A>, I<B>, I<K>, I<D>), 2> for (I<A>, I<B>, I<C>, I<D>)
+{
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const K: usize>
+    GatherIndex<(I<A>, I<B>, I<C>, I<K>), 3> for (I<A>, I<B>, I<C>, I<D>)
+{
+}
+
+/// Shapes whose `AXIS` dim can be replaced with `N`, e.g. when selecting `N` indices along it.
+pub trait ResizeAxis<const AXIS: usize, const N: usize>: Shape {
+    type Output: Shape;
+}
+
+impl<const A: usize, const N: usize> ResizeAxis<0, N> for (I<A>,) {
+    type Output = (I<N>,);
+}
+
+impl<const A: usize, const B: usize, const N: usize> ResizeAxis<0, N> for (I<A>, I<B>) {
+    type Output = (I<N>, I<B>);
+}
+
+impl<const A: usize, const B: usize, const N: usize> ResizeAxis<1, N> for (I<A>, I<B>) {
+    type Output = (I<A>, I<N>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const N: usize> ResizeAxis<0, N>
+    for (I<A>, I<B>, I<C>)
+{
+    type Output = (I<N>, I<B>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const N: usize> ResizeAxis<1, N>
+    for (I<A>, I<B>, I<C>)
+{
+    type Output = (I<A>, I<N>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const N: usize> ResizeAxis<2, N>
+    for (I<A>, I<B>, I<C>)
+{
+    type Output = (I<A>, I<B>, I<N>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const N: usize>
+    ResizeAxis<0, N> for (I<A>, I<B>, I<C>, I<D>)
+{
+    type Output = (I<N>, I<B>, I<C>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const N: usize>
+    ResizeAxis<1, N> for (I<A>, I<B>, I<C>, I<D>)
+{
+    type Output = (I<A>, I<N>, I<C>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const N: usize>
+    ResizeAxis<2, N> for (I<A>, I<B>, I<C>, I<D>)
+{
+    type Output = (I<A>, I<B>, I<N>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const N: usize>
+    ResizeAxis<3, N> for (I<A>, I<B>, I<C>, I<D>)
+{
+    type Output = (I<A>, I<B>, I<C>, I<N>);
+}
+
+/// Shapes that can be split along `AXIS` at index `AT` into `Left` (`..AT`) and `Right` (`AT..`).
+pub trait SplitAt<const AXIS: usize, const AT: usize>: Shape {
+    type Left: Shape;
+    type Right: Shape;
+}
+
+impl<const A: usize, const AT: usize> SplitAt<0, AT> for (I<A>,)
+where
+    [(); narrow_len(A, 0, AT)]:,
+    [(); A - AT]:,
+{
+    type Left = (I<{ narrow_len(A, 0, AT) }>,);
+    type Right = (I<{ A - AT }>,);
+}
+
+impl<const A: usize, const B: usize, const AT: usize> SplitAt<0, AT> for (I<A>, I<B>)
+where
+    [(); narrow_len(A, 0, AT)]:,
+    [(); A - AT]:,
+{
+    type Left = (I<{ narrow_len(A, 0, AT) }>, I<B>);
+    type Right = (I<{ A - AT }>, I<B>);
+}
+
+impl<const A: usize, const B: usize, const AT: usize> SplitAt<1, AT> for (I<A>, I<B>)
+where
+    [(); narrow_len(B, 0, AT)]:,
+    [(); B - AT]:,
+{
+    type Left = (I<A>, I<{ narrow_len(B, 0, AT) }>);
+    type Right = (I<A>, I<{ B - AT }>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const AT: usize> SplitAt<0, AT>
+    for (I<A>, I<B>, I<C>)
+where
+    [(); narrow_len(A, 0, AT)]:,
+    [(); A - AT]:,
+{
+    type Left = (I<{ narrow_len(A, 0, AT) }>, I<B>, I<C>);
+    type Right = (I<{ A - AT }>, I<B>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const AT: usize> SplitAt<1, AT>
+    for (I<A>, I<B>, I<C>)
+where
+    [(); narrow_len(B, 0, AT)]:,
+    [(); B - AT]:,
+{
+    type Left = (I<A>, I<{ narrow_len(B, 0, AT) }>, I<C>);
+    type Right = (I<A>, I<{ B - AT }>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const AT: usize> SplitAt<2, AT>
+    for (I<A>, I<B>, I<C>)
+where
+    [(); narrow_len(C, 0, AT)]:,
+    [(); C - AT]:,
+{
+    type Left = (I<A>, I<B>, I<{ narrow_len(C, 0, AT) }>);
+    type Right = (I<A>, I<B>, I<{ C - AT }>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const AT: usize> SplitAt<0, AT>
+    for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); narrow_len(A, 0, AT)]:,
+    [(); A - AT]:,
+{
+    type Left = (I<{ narrow_len(A, 0, AT) }>, I<B>, I<C>, I<D>);
+    type Right = (I<{ A - AT }>, I<B>, I<C>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const AT: usize> SplitAt<1, AT>
+    for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); narrow_len(B, 0, AT)]:,
+    [(); B - AT]:,
+{
+    type Left = (I<A>, I<{ narrow_len(B, 0, AT) }>, I<C>, I<D>);
+    type Right = (I<A>, I<{ B - AT }>, I<C>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const AT: usize> SplitAt<2, AT>
+    for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); narrow_len(C, 0, AT)]:,
+    [(); C - AT]:,
+{
+    type Left = (I<A>, I<B>, I<{ narrow_len(C, 0, AT) }>, I<D>);
+    type Right = (I<A>, I<B>, I<{ C - AT }>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const AT: usize> SplitAt<3, AT>
+    for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); narrow_len(D, 0, AT)]:,
+    [(); D - AT]:,
+{
+    type Left = (I<A>, I<B>, I<C>, I<{ narrow_len(D, 0, AT) }>);
+    type Right = (I<A>, I<B>, I<C>, I<{ D - AT }>);
+}
+
+/// Size of each of `n` equal chunks of a dim of size `dim`. Fails const evaluation if `dim` is
+/// not divisible by `n`.
+pub const fn chunk_len(dim: usize, n: usize) -> usize {
+    if n == 0 || !dim.is_multiple_of(n) {
+        panic!("Dim is not divisible into equal chunks")
+    }
+    dim / n
+}
+
+/// Shapes that can be split into `N` equal chunks along `AXIS`, with `Output` the chunk shape.
+pub trait Chunk<const AXIS: usize, const N: usize>: Shape {
+    type Output: Shape;
+}
+
+impl<const A: usize, const N: usize> Chunk<0, N> for (I<A>,)
+where
+    [(); chunk_len(A, N)]:,
+{
+    type Output = (I<{ chunk_len(A, N) }>,);
+}
+
+impl<const A: usize, const B: usize, const N: usize> Chunk<0, N> for (I<A>, I<B>)
+where
+    [(); chunk_len(A, N)]:,
+{
+    type Output = (I<{ chunk_len(A, N) }>, I<B>);
+}
+
+impl<const A: usize, const B: usize, const N: usize> Chunk<1, N> for (I<A>, I<B>)
+where
+    [(); chunk_len(B, N)]:,
+{
+    type Output = (I<A>, I<{ chunk_len(B, N) }>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const N: usize> Chunk<0, N>
+    for (I<A>, I<B>, I<C>)
+where
+    [(); chunk_len(A, N)]:,
+{
+    type Output = (I<{ chunk_len(A, N) }>, I<B>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const N: usize> Chunk<1, N>
+    for (I<A>, I<B>, I<C>)
+where
+    [(); chunk_len(B, N)]:,
+{
+    type Output = (I<A>, I<{ chunk_len(B, N) }>, I<C>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const N: usize> Chunk<2, N>
+    for (I<A>, I<B>, I<C>)
+where
+    [(); chunk_len(C, N)]:,
+{
+    type Output = (I<A>, I<B>, I<{ chunk_len(C, N) }>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const N: usize> Chunk<0, N>
+    for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); chunk_len(A, N)]:,
+{
+    type Output = (I<{ chunk_len(A, N) }>, I<B>, I<C>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const N: usize> Chunk<1, N>
+    for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); chunk_len(B, N)]:,
+{
+    type Output = (I<A>, I<{ chunk_len(B, N) }>, I<C>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const N: usize> Chunk<2, N>
+    for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); chunk_len(C, N)]:,
+{
+    type Output = (I<A>, I<B>, I<{ chunk_len(C, N) }>, I<D>);
+}
+
+impl<const A: usize, const B: usize, const C: usize, const D: usize, const N: usize> Chunk<3, N>
+    for (I<A>, I<B>, I<C>, I<D>)
+where
+    [(); chunk_len(D, N)]:,
+{
+    type Output = (I<A>, I<B>, I<C>, I<{ chunk_len(D, N) }>);
+}
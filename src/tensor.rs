@@ -1,23 +1,22 @@
-use std::cell::Ref;
 use std::collections::{BinaryHeap, HashSet};
+use std::fmt;
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
-use std::marker::PhantomData;
 use std::rc::Rc;
-use std::{fmt, usize};
 
-use crate::dtype::Dtype;
+use crate::dtype::{Dtype, NumDtype, SignedDtype};
+use crate::ops::vec::strides;
 use crate::ops::Op;
 use crate::optim::Optimizer;
-use crate::shape::{Shape, I};
-use crate::tensor_data::TensorData;
+use crate::shape::{Dims, Shape, I};
+use crate::tensor_data::{TensorData, ValueRef};
 use crate::tensor_id::generate_id;
 
-pub struct Tensor<T: Dtype, S: Shape> {
+pub struct Tensor<T: Dtype, S: Dims> {
     pub(crate) data: TensorData<T>,
     pub(crate) op: Option<Rc<dyn Op<Produces = Tensor<T, S>>>>,
     pub id: usize,
-    pub(crate) _shape: PhantomData<S>,
+    pub(crate) shape: S,
 }
 
 #[macro_export]
@@ -36,11 +35,11 @@ macro_rules! t {
 pub trait TensorTrait: Debug {
     fn process_grad(&self) -> bool;
     fn requires_grad(&self) -> bool;
-    fn parents(&self) -> Vec<TensorBox>;
+    fn parents(&self) -> Vec<TensorBox<'_>>;
     fn grad_to_string(&self) -> String;
     fn recompute(&self);
 }
-impl<T: Dtype, S: Shape> TensorTrait for Tensor<T, S> {
+impl<T: Dtype, S: Dims> TensorTrait for Tensor<T, S> {
     fn process_grad(&self) -> bool {
         if self.requires_grad() {
             if let Some(op) = self.op.as_ref() {
@@ -55,7 +54,7 @@ impl<T: Dtype, S: Shape> TensorTrait for Tensor<T, S> {
         self.data.has_grad_field()
     }
 
-    fn parents(&self) -> Vec<TensorBox> {
+    fn parents(&self) -> Vec<TensorBox<'_>> {
         match &self.op {
             Some(o) => o.operands(),
             None => vec![],
@@ -109,16 +108,19 @@ impl<'a> Hash for TensorBox<'a> {
     }
 }
 
-impl<T: Dtype, S: Shape> Clone for Tensor<T, S> {
+/// Clones are the same node in the graph and share its grad, but their values are copy-on-write:
+/// `replace_data_with` or `consume_grad` on one clone doesn't change the others. See
+/// `Tensor::shallow_clone` for a clone which aliases the value.
+impl<T: Dtype, S: Dims> Clone for Tensor<T, S> {
     fn clone(&self) -> Self {
         Self {
-            data: self.data.clone(),
+            data: self.data.cow_clone(),
             op: match &self.op {
                 Some(_op) => Some(Rc::clone(_op)),
                 None => None,
             },
             id: self.id,
-            _shape: Default::default(),
+            shape: self.shape.clone(),
         }
     }
 }
@@ -127,20 +129,22 @@ pub trait HasDtype {
     type Dtype;
 }
 
-impl<T: Dtype, S: Shape> HasDtype for Tensor<T, S> {
+impl<T: Dtype, S: Dims> HasDtype for Tensor<T, S> {
     type Dtype = T;
 }
 
-impl<T: Dtype + fmt::Debug, S: Shape + fmt::Debug> fmt::Debug for Tensor<T, S> {
+impl<T: Dtype, S: Dims + fmt::Debug> fmt::Debug for Tensor<T, S> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let strides = S::strides();
+        let shape = self.shape.dims();
+        let num_dims = shape.len();
+        let strides = strides(&shape);
         let data = self.borrow_value();
         let mut t_str = String::with_capacity(data.len() * 4);
-        t_str += &"[".repeat(S::NUM_DIMS);
+        t_str += &"[".repeat(num_dims);
         t_str += &format!("{:.2?}", data[0]);
-        for i in 1..S::NUM_ELS {
+        for i in 1..self.shape.num_els() {
             let mut n_match = 0;
-            for s in strides[..S::NUM_DIMS - 1].iter() {
+            for s in strides[..num_dims - 1].iter() {
                 if i % s == 0 {
                     n_match += 1;
                     t_str += "]";
@@ -150,14 +154,8 @@ impl<T: Dtype + fmt::Debug, S: Shape + fmt::Debug> fmt::Debug for Tensor<T, S> {
             t_str += &"[".repeat(n_match);
             t_str += &format!("{:.2?}", data[i]);
         }
-        t_str += &"]".repeat(S::NUM_DIMS);
-        write!(
-            f,
-            "Tensor({}, shape={:?}, id={})",
-            t_str,
-            S::shape(),
-            self.id
-        )
+        t_str += &"]".repeat(num_dims);
+        write!(f, "Tensor({}, shape={:?}, id={})", t_str, shape, self.id)
     }
 }
 
@@ -167,43 +165,96 @@ impl<T: Dtype, S: Shape> Tensor<T, S> {
             data: TensorData::new(value, false),
             op: None,
             id: generate_id(),
-            _shape: Default::default(),
+            shape: Default::default(),
         }
     }
     pub(crate) unsafe fn from_rc_td_and_op_unchecked(
         value: TensorData<T>,
         op: Rc<dyn Op<Produces = Tensor<T, S>>>,
+    ) -> Self {
+        Self::from_rc_td_op_and_shape_unchecked(value, op, Default::default())
+    }
+    pub fn new(data: impl Into<Tensor<T, S>>) -> Self {
+        data.into()
+    }
+
+    pub(crate) fn new_with_op(
+        data: impl Into<Tensor<T, S>>,
+        op: Rc<dyn Op<Produces = Tensor<T, S>>>,
+    ) -> Self {
+        let new_t = data.into();
+        Self {
+            op: Some(op),
+            ..new_t
+        }
+    }
+}
+
+impl<T: Dtype, S: Dims> Tensor<T, S> {
+    /// Create a tensor from a flat row-major vec and a shape value, which carries the runtime
+    /// size of any `Dyn` dims. Panics if the number of elements doesn't match the shape.
+    pub fn from_vec_and_shape(value: Vec<T>, shape: S) -> Self {
+        assert_eq!(
+            value.len(),
+            shape.num_els(),
+            "Data length does not match shape {:?}",
+            shape
+        );
+        Self {
+            data: TensorData::new(value, false),
+            op: None,
+            id: generate_id(),
+            shape,
+        }
+    }
+
+    pub(crate) unsafe fn from_rc_td_op_and_shape_unchecked(
+        value: TensorData<T>,
+        op: Rc<dyn Op<Produces = Tensor<T, S>>>,
+        shape: S,
     ) -> Self {
         Self {
             data: value,
             op: Some(op),
             id: generate_id(),
-            _shape: Default::default(),
+            shape,
         }
     }
-    pub fn new(data: impl Into<Tensor<T, S>>) -> Self {
-        data.into()
+
+    /// A clone which aliases the value of this tensor, so writes through either are seen by both.
+    pub fn shallow_clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+            op: self.op.clone(),
+            id: self.id,
+            shape: self.shape.clone(),
+        }
     }
 
-    pub fn new_with_grad(data: impl Into<Tensor<T, S>>) -> Self {
-        let tensor: Tensor<T, S> = data.into();
-        unsafe { tensor.data.add_grad_field() }
-        tensor
+    /// A new leaf tensor with copies of the value and grad of this one, sharing nothing with it.
+    pub fn deep_clone(&self) -> Self {
+        Self {
+            data: self.data.deep_clone(),
+            op: None,
+            id: generate_id(),
+            shape: self.shape.clone(),
+        }
     }
 
-    pub(crate) fn borrow_value(&self) -> Ref<Vec<T>> {
-        self.data.value_ref()
+    /// The shape value of this tensor, including the runtime size of any `Dyn` dims.
+    pub fn shape(&self) -> &S {
+        &self.shape
     }
 
-    pub(crate) fn borrow_grad(&self) -> Ref<Option<Vec<T>>> {
-        self.data.grad_ref()
+    pub(crate) fn borrow_value(&self) -> ValueRef<'_, T> {
+        self.data.value_ref()
     }
 
-    pub(crate) fn update_grad(&self, new_grad: Vec<T>) {
-        self.data.update_grad(new_grad);
+    pub(crate) fn borrow_grad(&self) -> Option<ValueRef<'_, T>> {
+        self.data.grad_ref()
     }
 
-    pub(crate) fn ancestors(&self) -> HashSet<TensorBox> {
+    pub(crate) fn ancestors(&self) -> HashSet<TensorBox<'_>> {
         let mut visited_set = HashSet::new();
         let mut to_visit = vec![TensorBox::new(self.id, self)];
 
@@ -217,39 +268,12 @@ impl<T: Dtype, S: Shape> Tensor<T, S> {
         visited_set
     }
 
-    pub fn leaves(&self) -> HashSet<TensorBox> {
+    pub fn leaves(&self) -> HashSet<TensorBox<'_>> {
         let mut ans = self.ancestors();
         ans.retain(|TensorBox { id: _, tensor: t }| t.parents() == vec![]);
         ans
     }
 
-    pub fn consume_grad<Opt: Optimizer>(&self, optim: &mut Opt) {
-        let new_value = {
-            let t_grad = self.borrow_grad();
-            if let Some(t_grad) = t_grad.as_ref() {
-                let t_value = self.borrow_value();
-                Some(optim.compute(self.id, &t_value, t_grad))
-            } else {
-                None
-            }
-        };
-        if let Some(new_value) = new_value {
-            // Broken up like this to ensure the borrows above are out of scope before replace is called
-            self.data.replace(new_value);
-        }
-    }
-
-    pub(crate) fn new_with_op(
-        data: impl Into<Tensor<T, S>>,
-        op: Rc<dyn Op<Produces = Tensor<T, S>>>,
-    ) -> Self {
-        let new_t = data.into();
-        Self {
-            op: Some(op),
-            ..new_t
-        }
-    }
-
     pub fn recompute(&self) {
         let ancestors = self.ancestors();
         let mut ancestors: Vec<_> = ancestors.iter().collect::<Vec<_>>();
@@ -266,11 +290,51 @@ impl<T: Dtype, S: Shape> Tensor<T, S> {
         {
             assert_eq!(new_data.len(), self.borrow_value().len());
         }
-        self.data.replace(new_data);
+        self.data.write(new_data);
+    }
+}
+
+// Only signed tensors can require grad, as grads of unsigned ones would underflow, e.g. through
+// subtraction. Bool and unsigned tensors can still be operands of a graph, e.g. masks or indices.
+impl<T: SignedDtype, S: Shape> Tensor<T, S> {
+    pub fn new_with_grad(data: impl Into<Tensor<T, S>>) -> Self {
+        let tensor: Tensor<T, S> = data.into();
+        unsafe { tensor.data.add_grad_field() }
+        tensor
+    }
+}
+
+impl<T: SignedDtype, S: Dims> Tensor<T, S> {
+    pub fn from_vec_and_shape_with_grad(value: Vec<T>, shape: S) -> Self {
+        let tensor = Self::from_vec_and_shape(value, shape);
+        unsafe { tensor.data.add_grad_field() }
+        tensor
+    }
+}
+
+impl<T: NumDtype, S: Dims> Tensor<T, S> {
+    pub(crate) fn update_grad(&self, new_grad: Vec<T>) {
+        self.data.update_grad(new_grad);
+    }
+
+    pub fn consume_grad<Opt: Optimizer>(&self, optim: &mut Opt) {
+        let new_value = {
+            let t_grad = self.borrow_grad();
+            if let Some(t_grad) = t_grad.as_ref() {
+                let t_value = self.borrow_value();
+                Some(optim.compute(self.id, &t_value, t_grad))
+            } else {
+                None
+            }
+        };
+        if let Some(new_value) = new_value {
+            // Broken up like this to ensure the borrows above are out of scope before write is called
+            self.data.write(new_value);
+        }
     }
 }
 
-impl<T: Dtype> Tensor<T, (I<1>,)> {
+impl<T: NumDtype> Tensor<T, (I<1>,)> {
     pub fn backward(&self) {
         assert!(
             self.requires_grad(),
@@ -295,6 +359,38 @@ impl<T: Dtype> Tensor<T, (I<1>,)> {
     }
 }
 
-pub fn remove_inputs(tensors: &mut HashSet<TensorBox>, input_ids: &[usize]) {
+pub fn remove_inputs(tensors: &mut HashSet<TensorBox<'_>>, input_ids: &[usize]) {
     tensors.retain(|e| !input_ids.contains(&e.id));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Tensor;
+
+    #[test]
+    fn test_clone_semantics() {
+        let w = Tensor::new_with_grad([1.0, 2.0]);
+        let stashed = w.clone();
+        let aliased = w.shallow_clone();
+        let copied = w.deep_clone();
+
+        let y = w.clone() * Tensor::new([3.0, 4.0]);
+        y.reduce_sum().backward();
+        // Clones share the grad, deep clones don't
+        assert_eq!(stashed.borrow_grad().as_deref(), Some(&[3.0, 4.0][..]));
+        assert_eq!(copied.borrow_grad().as_deref(), None);
+
+        w.replace_data_with(vec![5.0, 6.0]);
+        assert_eq!(*aliased.borrow_value(), [5.0, 6.0]);
+        assert_eq!(*stashed.borrow_value(), [1.0, 2.0]);
+        assert_eq!(*copied.borrow_value(), [1.0, 2.0]);
+
+        // Recomputing the graph still updates every clone of the output
+        let x = Tensor::new([1.0, 2.0]);
+        let z = x.shallow_clone() * Tensor::new([2.0, 2.0]);
+        let z_clone = z.clone();
+        x.replace_data_with(vec![3.0, 4.0]);
+        z.recompute();
+        assert_eq!(*z_clone.borrow_value(), [6.0, 8.0]);
+    }
+}
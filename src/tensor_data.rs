@@ -1,104 +1,304 @@
 use std::cell::{Ref, RefCell};
-use std::rc::Rc;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
 
-use crate::dtype::Dtype;
-use crate::ops::vec::el_add;
+use crate::dtype::{Dtype, NumDtype};
+use crate::ops::vec::{el_add, strided_add_assign, strided_assign, strided_copy, strides};
 
+/// Storage for the value and grad of a tensor. Cloning gives a handle to the same storage, see
+/// `cow_clone` and `deep_clone` for copies.
 #[derive(Debug, Clone)]
 pub(crate) struct TensorData<T: Dtype> {
-    inner: Rc<RefCell<TensorDataInner<T>>>,
+    /// The cell is shared with shallow clones and views. The buffer inside it is also shared by
+    /// `cow_clone`s, until one of them writes to it.
+    value: Rc<ValueCell<T>>,
+    /// The value cells of every `cow_clone` of this storage. Recomputing a tensor updates those
+    /// which still share its buffer.
+    siblings: Rc<RefCell<Vec<Weak<ValueCell<T>>>>>,
+    /// Shared by every kind of clone except `deep_clone`, so grads reach a tensor whichever copy
+    /// of it the graph holds.
+    grad: Rc<RefCell<TensorDataGrad<T>>>,
+    /// Set for strided views into storage shared with another tensor. `None` means the tensor
+    /// is the whole storage in row-major order.
+    layout: Option<Rc<Layout>>,
+}
+
+type ValueCell<T> = RefCell<Rc<Vec<T>>>;
+
+/// Where the elements of a strided view live in its storage. Element `i` of the view, in
+/// row-major order over `shape`, is at `offset + sum_k(index_k * strides[k])`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Layout {
+    pub(crate) offset: usize,
+    pub(crate) shape: Vec<usize>,
+    pub(crate) strides: Vec<usize>,
+}
+
+impl Layout {
+    pub(crate) fn contiguous(shape: &[usize]) -> Self {
+        Self {
+            offset: 0,
+            shape: shape.to_vec(),
+            strides: strides(shape),
+        }
+    }
+
+    /// Reorder axes so that output axis `i` is axis `axes[i]`.
+    pub(crate) fn permute(&self, axes: &[usize]) -> Self {
+        assert_eq!(axes.len(), self.shape.len());
+        Self {
+            offset: self.offset,
+            shape: axes.iter().map(|ax| self.shape[*ax]).collect(),
+            strides: axes.iter().map(|ax| self.strides[*ax]).collect(),
+        }
+    }
+
+    /// Keep `start..start + len` along `axis`.
+    pub(crate) fn narrow(&self, axis: usize, start: usize, len: usize) -> Self {
+        assert!(start + len <= self.shape[axis]);
+        let mut shape = self.shape.clone();
+        shape[axis] = len;
+        Self {
+            offset: self.offset + start * self.strides[axis],
+            shape,
+            strides: self.strides.clone(),
+        }
+    }
+
+    /// Drop a size 1 axis.
+    pub(crate) fn remove_axis(&self, axis: usize) -> Self {
+        assert_eq!(self.shape[axis], 1);
+        let mut layout = self.clone();
+        layout.shape.remove(axis);
+        layout.strides.remove(axis);
+        layout
+    }
+
+    /// Insert a size 1 axis at `axis`.
+    pub(crate) fn insert_axis(&self, axis: usize) -> Self {
+        let mut layout = self.clone();
+        layout.shape.insert(axis, 1);
+        layout.strides.insert(axis, 0);
+        layout
+    }
+
+    /// Broadcast to `out_shape` by giving new and expanded size 1 axes a stride of 0.
+    pub(crate) fn broadcast_to(&self, out_shape: &[usize]) -> Self {
+        assert!(self.shape.len() <= out_shape.len());
+        let lead = out_shape.len() - self.shape.len();
+        let mut strides = vec![0; out_shape.len()];
+        for (i, (d, s)) in self.shape.iter().zip(&self.strides).enumerate() {
+            if *d != 1 {
+                assert_eq!(*d, out_shape[i + lead]);
+                strides[i + lead] = *s;
+            }
+        }
+        Self {
+            offset: self.offset,
+            shape: out_shape.to_vec(),
+            strides,
+        }
+    }
+}
+
+/// A row-major value or grad. Borrowed from the storage for contiguous tensors, or copied out of
+/// it for strided views.
+pub(crate) enum ValueRef<'a, T> {
+    Borrowed(Ref<'a, [T]>),
+    Owned(Vec<T>),
+}
+
+impl<T> Deref for ValueRef<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            ValueRef::Borrowed(r) => r,
+            ValueRef::Owned(v) => v,
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ValueRef<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.deref().fmt(f)
+    }
 }
 
 #[derive(Debug)]
-pub(crate) enum TensorDataInner<T: Dtype> {
-    ValueWithGradOption { value: Vec<T>, grad: Option<Vec<T>> },
-    Value { value: Vec<T> },
+pub(crate) enum TensorDataGrad<T: Dtype> {
+    GradOption(Option<Vec<T>>),
+    NoGrad,
 }
 
-use TensorDataInner::*;
+use TensorDataGrad::*;
 
 impl<T: Dtype> TensorData<T> {
     pub(crate) fn new(value: Vec<T>, requires_grad: bool) -> Self {
+        let value = Rc::new(RefCell::new(Rc::new(value)));
         Self {
-            inner: Rc::new(RefCell::new(if requires_grad {
-                ValueWithGradOption { value, grad: None }
+            siblings: Rc::new(RefCell::new(vec![Rc::downgrade(&value)])),
+            value,
+            grad: Rc::new(RefCell::new(if requires_grad {
+                GradOption(None)
             } else {
-                Value { value }
+                NoGrad
             })),
+            layout: None,
+        }
+    }
+
+    /// A copy which shares the value buffer until either copy writes to it. Grads stay shared.
+    pub(crate) fn cow_clone(&self) -> Self {
+        let value = Rc::new(RefCell::new(Rc::clone(&self.value.borrow())));
+        let mut siblings = self.siblings.borrow_mut();
+        siblings.retain(|s| s.strong_count() > 0);
+        siblings.push(Rc::downgrade(&value));
+        Self {
+            value,
+            siblings: Rc::clone(&self.siblings),
+            grad: Rc::clone(&self.grad),
+            layout: self.layout.clone(),
+        }
+    }
+
+    /// A copy which shares nothing, with the value and grad copied out of the storage.
+    pub(crate) fn deep_clone(&self) -> Self {
+        let value = Rc::new(RefCell::new(Rc::new(self.value.borrow().to_vec())));
+        let grad = match &*self.grad.borrow() {
+            GradOption(g) => GradOption(g.clone()),
+            NoGrad => NoGrad,
+        };
+        Self {
+            siblings: Rc::new(RefCell::new(vec![Rc::downgrade(&value)])),
+            value,
+            grad: Rc::new(RefCell::new(grad)),
+            layout: self.layout.clone(),
+        }
+    }
+
+    /// A strided view of the same storage (and grad).
+    pub(crate) fn view(&self, layout: Layout) -> Self {
+        Self {
+            value: Rc::clone(&self.value),
+            siblings: Rc::clone(&self.siblings),
+            grad: Rc::clone(&self.grad),
+            layout: Some(Rc::new(layout)),
         }
     }
 
+    /// The layout of this tensor in its storage, given its `shape`.
+    pub(crate) fn layout(&self, shape: &[usize]) -> Layout {
+        match &self.layout {
+            Some(layout) => Layout::clone(layout),
+            None => Layout::contiguous(shape),
+        }
+    }
+
+    pub(crate) fn is_contiguous(&self) -> bool {
+        self.layout.is_none()
+    }
+
     pub(crate) unsafe fn add_grad_field(&self) {
-        self.inner.replace_with(|tdi| {
-            let v = match tdi {
-                Value { value } => value,
-                ValueWithGradOption { value: _, grad: _ } => {
-                    panic!("TensorData already has grad field.")
-                }
-            };
-            ValueWithGradOption {
-                value: v.clone(),
-                grad: None,
-            }
+        self.grad.replace_with(|g| match g {
+            NoGrad => GradOption(None),
+            GradOption(_) => panic!("TensorData already has grad field."),
         });
     }
 
     pub(crate) fn has_grad_field(&self) -> bool {
-        match *self.inner.borrow() {
-            Value { value: _ } => false,
-            ValueWithGradOption { value: _, grad: _ } => true,
+        match *self.grad.borrow() {
+            NoGrad => false,
+            GradOption(_) => true,
         }
     }
 
+    /// Replace the value of every clone which still shares this buffer, e.g. when recomputing
+    /// the graph.
     pub(crate) fn replace(&self, new_value: Vec<T>) {
-        match *self.inner.borrow_mut() {
-            ValueWithGradOption {
-                ref mut value,
-                ref mut grad,
-            } => {
-                *value = new_value;
-                *grad = None;
+        assert!(self.layout.is_none(), "Cannot replace the value of a view");
+        let old = Rc::clone(&self.value.borrow());
+        let new = Rc::new(new_value);
+        for sibling in self.siblings.borrow().iter().filter_map(Weak::upgrade) {
+            let mut value = sibling.borrow_mut();
+            if Rc::ptr_eq(&value, &old) {
+                *value = Rc::clone(&new);
             }
-            Value { ref mut value } => *value = new_value,
         }
+        self.clear_grad();
     }
 
-    pub(crate) fn grad_ref(&self) -> Ref<Option<Vec<T>>> {
-        Ref::map(self.inner.borrow(), |t| match t {
-            ValueWithGradOption { value: _, ref grad } => grad,
-            Value { value: _ } => &None,
-        })
+    /// Replace the value of this tensor, its shallow clones and views only. Other clones keep
+    /// the old buffer. The grad is cleared, for views that of the whole storage, as it was
+    /// computed for the old value.
+    pub(crate) fn write(&self, new_value: Vec<T>) {
+        let mut value = self.value.borrow_mut();
+        match &self.layout {
+            Some(l) => {
+                // Elements of a broadcast axis share a position in the storage, so they can't
+                // be given different values
+                assert!(
+                    l.shape
+                        .iter()
+                        .zip(&l.strides)
+                        .all(|(d, s)| *d <= 1 || *s != 0),
+                    "Cannot write to a broadcast view"
+                );
+                // Write through to the storage, leaving the rest of it untouched. The buffer is
+                // copied first if a clone still shares it.
+                let value = Rc::make_mut(&mut value);
+                strided_assign(value, &new_value, l.offset, &l.shape, &l.strides);
+            }
+            None => *value = Rc::new(new_value),
+        }
+        self.clear_grad();
+    }
+
+    fn clear_grad(&self) {
+        if let GradOption(ref mut grad) = *self.grad.borrow_mut() {
+            *grad = None;
+        }
     }
 
-    pub(crate) fn value_ref(&self) -> Ref<Vec<T>> {
-        Ref::map(self.inner.borrow(), |t| match t {
-            ValueWithGradOption { ref value, grad: _ } | Value { ref value } => value,
+    pub(crate) fn grad_ref(&self) -> Option<ValueRef<'_, T>> {
+        let grad = Ref::filter_map(self.grad.borrow(), |g| match g {
+            GradOption(Some(ref grad)) => Some(&grad[..]),
+            _ => None,
+        })
+        .ok()?;
+        Some(match &self.layout {
+            Some(l) => ValueRef::Owned(strided_copy(&grad, l.offset, &l.shape, &l.strides)),
+            None => ValueRef::Borrowed(grad),
         })
     }
 
-    pub(crate) fn update_grad(&self, new_grad: Vec<T>) {
-        match *self.inner.borrow_mut() {
-            Value { value: _ } => {}
-            ValueWithGradOption {
-                value: _,
-                grad: ref mut g,
-            } => {
-                let new_g = match g {
-                    Some(cur_g) => el_add(cur_g, &new_grad),
-                    None => new_grad,
-                };
-                *g = Some(new_g)
-            }
-        };
+    pub(crate) fn value_ref(&self) -> ValueRef<'_, T> {
+        let value = Ref::map(self.value.borrow(), |v| &v[..]);
+        match &self.layout {
+            Some(l) => ValueRef::Owned(strided_copy(&value, l.offset, &l.shape, &l.strides)),
+            None => ValueRef::Borrowed(value),
+        }
     }
 }
 
-impl<T: Dtype> TensorDataInner<T> {
-    fn replace_with_grad_variant(self) -> Self {
-        match self {
-            Value { value } => ValueWithGradOption { value, grad: None },
-            ValueWithGradOption { value: _, grad: _ } => {
-                panic!("TensorData already has grad field.")
+impl<T: NumDtype> TensorData<T> {
+    pub(crate) fn update_grad(&self, new_grad: Vec<T>) {
+        if let GradOption(ref mut g) = *self.grad.borrow_mut() {
+            match &self.layout {
+                // Scatter the grad of the view back into the grad of the whole storage
+                Some(l) => {
+                    let len = self.value.borrow().len();
+                    let g = g.get_or_insert_with(|| vec![T::zero(); len]);
+                    strided_add_assign(g, &new_grad, l.offset, &l.shape, &l.strides);
+                }
+                None => {
+                    let new_g = match g {
+                        Some(cur_g) => el_add(cur_g, &new_grad),
+                        None => new_grad,
+                    };
+                    *g = Some(new_g)
+                }
             }
         }
     }
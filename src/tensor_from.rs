@@ -30,6 +30,15 @@ impl<const D1: usize, const D2: usize, const D3: usize, T: Dtype> From<Vec<T>>
     }
 }
 
+impl<const D1: usize, const D2: usize, const D3: usize, const D4: usize, T: Dtype> From<Vec<T>>
+    for Tensor<T, (I<D1>, I<D2>, I<D3>, I<D4>)>
+{
+    fn from(value: Vec<T>) -> Self {
+        assert_eq!(value.len(), D1 * D2 * D3 * D4);
+        unsafe { Self::from_vec_unchecked(value) }
+    }
+}
+
 // Array to constant size tensor
 impl<T: Dtype, const D1: usize> From<[T; D1]> for Tensor<T, (I<D1>,)> {
     fn from(value: [T; D1]) -> Self {
@@ -51,9 +60,17 @@ impl<T: Dtype, const D1: usize, const D2: usize, const D3: usize> From<[[[T; D3]
     }
 }
 
+impl<T: Dtype, const D1: usize, const D2: usize, const D3: usize, const D4: usize>
+    From<[[[[T; D4]; D3]; D2]; D1]> for Tensor<T, (I<D1>, I<D2>, I<D3>, I<D4>)>
+{
+    fn from(value: [[[[T; D4]; D3]; D2]; D1]) -> Self {
+        unsafe { Self::from_vec_unchecked(value.concat().concat().concat()) }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::shape::I;
+    use crate::shape::{Dims, I};
 
     use super::*;
 
@@ -66,7 +83,7 @@ mod tests {
     fn test_create_tensor_from_tensor() {
         let t = Tensor::new([[2, 3]; 7]);
         let t2 = Tensor::new(t.clone());
-        assert_eq!(t._shape, t2._shape);
+        assert_eq!(t.shape, t2.shape);
     }
 
     #[test]
@@ -84,4 +101,11 @@ mod tests {
         let _t = Tensor::new([[[2, 9], [8, 7]], [[8, 2], [3, 0]], [[0, 0], [1, 2]]]);
         //todo: Test these values better
     }
+
+    #[test]
+    fn test_create_tensor_from_4d_array() {
+        let t = Tensor::new([[[[1, 2], [3, 4]]], [[[5, 6], [7, 8]]]]);
+        assert_eq!(t.shape.dims(), [2, 1, 2, 2]);
+        assert_eq!(*t.borrow_value(), [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
 }
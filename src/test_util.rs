@@ -0,0 +1,45 @@
+use crate::{
+    shape::{Dims, Shape, I},
+    tensor::Tensor,
+};
+
+pub(crate) fn assert_close(a: &[f64], b: &[f64]) {
+    assert_eq!(a.len(), b.len());
+    for (x, y) in a.iter().zip(b) {
+        assert!((x - y).abs() < 1e-6, "{:?} != {:?}", a, b);
+    }
+}
+
+/// Compare the grad of `f` at `x` against a central difference. The outputs of `f` are summed
+/// with distinct weights, otherwise ops whose outputs have a constant sum (e.g. softmax) would
+/// have a grad of 0.
+pub(crate) fn check_grad<S: Shape, So: Dims>(
+    f: impl Fn(Tensor<f64, S>) -> Tensor<f64, So>,
+    x: &[f64],
+) {
+    let loss = |t: Tensor<f64, S>| -> Tensor<f64, (I<1>,)> {
+        let y = f(t).into_dyn();
+        let n = y.shape().num_els();
+        let weights = (0..n).map(|i| 1.0 + (i as f64 * 0.37).sin()).collect();
+        let w = Tensor::from_vec_and_shape(weights, y.shape().clone());
+        (y * w).reshape(&[n]).sum_axis(0).try_into().unwrap()
+    };
+    let t = Tensor::from_vec_and_shape_with_grad(x.to_vec(), S::default());
+    loss(t.clone()).backward();
+
+    let eps = 1e-6;
+    let numeric: Vec<f64> = (0..x.len())
+        .map(|i| {
+            let f = |d: f64| {
+                let mut v = x.to_vec();
+                v[i] += d;
+                loss(Tensor::from_vec_and_shape(v, S::default())).borrow_value()[0]
+            };
+            (f(eps) - f(-eps)) / (2.0 * eps)
+        })
+        .collect();
+    let grad = t.borrow_grad().unwrap().to_vec();
+    for (g, n) in grad.iter().zip(&numeric) {
+        assert!((g - n).abs() < 1e-5, "{:?} != {:?}", grad, numeric);
+    }
+}